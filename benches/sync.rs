@@ -0,0 +1,107 @@
+//! Measures the throughput of the demuxer on synthetic input made of null
+//! packets interleaved with bursts of garbage, which forces it to lose and
+//! regain sync over and over.
+//!
+//! Run with `cargo bench --bench sync`. The amount of input defaults to 4 GiB,
+//! and can be set in bytes with the TRANSMUX_BENCH_BYTES environment variable.
+//! When run by `cargo test`, which does not pass `--bench`, it only parses a
+//! few MiB as a smoke test.
+
+use std::env;
+use std::io;
+use std::io::Read;
+use std::time::Instant;
+use transmux::mp2t::demuxer::Demuxer;
+
+const DEFAULT_BYTES: u64 = 4 << 30;
+const TEST_BYTES: u64 = 4 << 20;
+const PACKET_SIZE: usize = 188;
+const BLOCK_SIZE: usize = 1 << 20;
+
+// A xorshift generator, so that the input is the same on every run.
+struct Rng(u64);
+
+impl Rng {
+  fn next(&mut self) -> u64 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    self.0
+  }
+}
+
+// Builds a block of runs of null packets separated by garbage. The garbage is
+// made denser in sync bytes than random data, so that false candidates are
+// frequent.
+fn block() -> Vec<u8> {
+  let mut null_packet = vec![0xffu8; PACKET_SIZE];
+  null_packet[..4].copy_from_slice(&[0x47, 0x1f, 0xff, 0x10]);
+
+  let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+  let mut block = Vec::with_capacity(BLOCK_SIZE + 64 * PACKET_SIZE);
+  while block.len() < BLOCK_SIZE {
+    for _ in 0..rng.next() % 64 {
+      block.extend_from_slice(&null_packet);
+    }
+    for _ in 0..rng.next() % 4096 {
+      let r = rng.next();
+      block.push(if r & 0xf == 0 { 0x47 } else { (r >> 8) as u8 });
+    }
+  }
+  block.truncate(BLOCK_SIZE);
+  block
+}
+
+// Repeats a block until the given number of bytes has been read.
+struct Input {
+  block: Vec<u8>,
+  pos: usize,
+  remaining: u64,
+}
+
+impl Read for Input {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let n = buf
+      .len()
+      .min(self.block.len() - self.pos)
+      .min(self.remaining as usize);
+    buf[..n].copy_from_slice(&self.block[self.pos..self.pos + n]);
+    self.pos = (self.pos + n) % self.block.len();
+    self.remaining -= n as u64;
+    Ok(n)
+  }
+}
+
+fn main() {
+  let bytes = env::var("TRANSMUX_BENCH_BYTES")
+    .ok()
+    .and_then(|s| s.parse().ok())
+    .unwrap_or_else(|| {
+      if env::args().any(|arg| arg == "--bench") {
+        DEFAULT_BYTES
+      } else {
+        TEST_BYTES
+      }
+    });
+  let mut input = Input {
+    block: block(),
+    pos: 0,
+    remaining: bytes,
+  };
+
+  let mut demuxer = Demuxer::new();
+  let start = Instant::now();
+  let mut events = 0u64;
+  while demuxer.parse(&mut input).unwrap().is_some() {
+    events += 1;
+  }
+  let elapsed = start.elapsed().as_secs_f64();
+
+  println!(
+    "sync: {} bytes in {:.2} s, {:.1} MB/s, {} events",
+    bytes,
+    elapsed,
+    bytes as f64 / elapsed / 1e6,
+    events
+  );
+}
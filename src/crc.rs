@@ -1,5 +1,5 @@
 // Table for CRC32/MPEG2.
-const MPEG2_CRC32_TABLE: &'static [u32] = &[
+const MPEG2_CRC32_TABLE: &[u32] = &[
   0x00000000, 0x04c11db7, 0x09823b6e, 0x0d4326d9, 0x130476dc, 0x17c56b6b,
   0x1a864db2, 0x1e475005, 0x2608edb8, 0x22c9f00f, 0x2f8ad6d6, 0x2b4bcb61,
   0x350c9b64, 0x31cd86d3, 0x3c8ea00a, 0x384fbdbd, 0x4c11db70, 0x48d0c6c7,
@@ -51,8 +51,7 @@ const MPEG2_CRC32_TABLE: &'static [u32] = &[
 pub fn mpeg2(data: &[u8]) -> u32 {
   let mut crc: u32 = 0xFFFFFFFF;
   for b in data {
-    crc = MPEG2_CRC32_TABLE[((((crc >> 24) as u8) ^ b) & 0xFF) as usize]
-      ^ (crc << 8)
+    crc = MPEG2_CRC32_TABLE[(((crc >> 24) as u8) ^ b) as usize] ^ (crc << 8)
   }
   crc
 }
@@ -4,6 +4,8 @@ use snafu::Snafu;
 pub enum Error {
   #[snafu(display("Invalid program number"))]
   InvalidProgramNumber,
+  #[snafu(display("PID already demuxed"))]
+  PidInUse,
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -1,4 +1,7 @@
 #![allow(dead_code)]
+// The twiddle crate expresses bit ranges from most to least significant bit
+// (e.g. `bits(12..=0)`), which clippy mistakes for empty ranges.
+#![allow(clippy::reversed_empty_ranges)]
 
 mod crc;
 mod error;
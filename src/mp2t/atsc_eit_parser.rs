@@ -0,0 +1,195 @@
+use crate::mp2t::atsc_text;
+use crate::mp2t::demuxer::{Context, Event};
+use crate::mp2t::desc;
+use crate::mp2t::psi_parser::PsiHandler;
+use crate::mp2t::{AtscEit, AtscEvent};
+use bytes::Buf;
+use std::collections::hash_map::HashMap;
+use twiddle::Twiddle;
+
+// ATSC A/65 Table 4.2
+const ATSC_EIT_TABLE_ID: u8 = 0xCB;
+
+/// Parses the instances of an ATSC EIT-k, one per source_id. Each instance is
+/// reported once per version.
+pub struct AtscEitParser {
+  // The version_number of the instances already reported, by source_id.
+  versions: HashMap<u16, u8>,
+}
+
+impl AtscEitParser {
+  pub fn new() -> AtscEitParser {
+    AtscEitParser {
+      versions: HashMap::new(),
+    }
+  }
+
+  fn parse_psi(&mut self, ctx: &mut Context, sections: &[&[u8]]) -> bool {
+    let mut eit: AtscEit = Default::default();
+
+    for (i, section) in sections.iter().enumerate() {
+      let mut buf = *section;
+
+      if buf.len() < 7 {
+        return false;
+      }
+
+      let source_id = buf.get_u16();
+      let b = buf.get_u8();
+      let _section_number = buf.get_u8();
+      let _last_section_number = buf.get_u8();
+      let protocol_version = buf.get_u8();
+
+      // All sections share the same header: take it from the first one.
+      if i == 0 {
+        eit.source_id = source_id;
+        eit.version = b.bits(5..=1);
+        eit.current_next = b.bit(0);
+        eit.protocol_version = protocol_version;
+
+        if !eit.current_next
+          || self.versions.get(&eit.source_id) == Some(&eit.version)
+        {
+          return true;
+        }
+      }
+
+      let num_events_in_section = buf.get_u8();
+      for _ in 0..num_events_in_section {
+        match parse_event(&mut buf) {
+          Some(event) => eit.events.push(event),
+          None => return false,
+        }
+      }
+    }
+
+    self.versions.insert(eit.source_id, eit.version);
+    ctx.events.push_back(Event::AtscEit(eit));
+    true
+  }
+}
+
+// Each event is made of:
+//
+//  1          0
+// 54321098 76543210
+// ..eeeeee eeeeeeee event_id
+//
+// followed by start_time (32 bits), then:
+//
+//  2          1          0
+// 32109876 54321098 76543210
+// ..llssss ssssssss ssssssss
+//
+// l: ETM_location
+// s: length_in_seconds
+//
+// followed by title_length, title_text, and descriptors_length (12 bits) with
+// the descriptors.
+fn parse_event(buf: &mut &[u8]) -> Option<AtscEvent> {
+  if buf.len() < 10 {
+    return None;
+  }
+  let event_id = buf.get_u16().bits(13..=0);
+  let start_time = buf.get_u32();
+  let b = buf.get_uint(3) as u32;
+  let title_len = buf.get_u8() as usize;
+  if buf.len() < title_len + 2 {
+    return None;
+  }
+  let title = atsc_text::parse_multiple_string(&buf[..title_len])?;
+  buf.advance(title_len);
+
+  let descs_len = buf.get_u16().bits(11..=0) as usize;
+  if descs_len > buf.len() {
+    return None;
+  }
+  let descs = desc::parse_descs(&buf[..descs_len])?;
+  buf.advance(descs_len);
+
+  Some(AtscEvent {
+    event_id,
+    start_time,
+    etm_location: b.bits(21..=20) as u8,
+    length_in_seconds: b.bits(19..=0),
+    title,
+    descs,
+  })
+}
+
+impl PsiHandler for AtscEitParser {
+  const TABLE_ID: u8 = ATSC_EIT_TABLE_ID;
+
+  fn on_psi(&mut self, ctx: &mut Context, _table_id: u8, sections: &[&[u8]]) {
+    if !self.parse_psi(ctx, sections) {
+      ctx.stats.invalid_psi += 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // EIT-0 section of source_id 4 with one event, "News", lasting 30 minutes.
+  static EIT: &[u8] = &[
+    0x00, 0x04, 0xc3, 0x00, 0x00, // section header
+    0x00, 0x01, // protocol_version, num_events_in_section
+    0xc0, 0x10, // event_id
+    0x4b, 0x36, 0xa3, 0x92, // start_time
+    0xd0, 0x07, 0x08, // ETM_location, length_in_seconds
+    0x0c, 0x01, 0x65, 0x6e, 0x67, 0x01, 0x00, 0x00, 0x04, 0x4e, 0x65, 0x77,
+    0x73, // title_length, title_text
+    0xf0, 0x00, // descriptors_length
+  ];
+
+  #[test]
+  fn basic() {
+    let mut ctx = Context::new();
+    let mut parser = AtscEitParser::new();
+    parser.on_psi(&mut ctx, ATSC_EIT_TABLE_ID, &[EIT]);
+    parser.on_psi(&mut ctx, ATSC_EIT_TABLE_ID, &[EIT]);
+    assert_eq!(ctx.stats.invalid_psi, 0);
+    assert_eq!(ctx.events.len(), 1);
+
+    let eit = match ctx.events[0] {
+      Event::AtscEit(ref eit) => eit,
+      ref e => panic!("unexpected event {:?}", e),
+    };
+    assert_eq!(eit.source_id, 4);
+    assert_eq!(eit.version, 1);
+    assert_eq!(eit.events.len(), 1);
+
+    let event = &eit.events[0];
+    assert_eq!(event.event_id, 0x10);
+    assert_eq!(event.start_time, 0x4b36a392);
+    assert_eq!(event.etm_location, 1);
+    assert_eq!(event.length_in_seconds, 1800);
+    assert_eq!(event.title.text(), Some("News"));
+  }
+
+  #[test]
+  fn title_overrun() {
+    let mut eit = EIT.to_vec();
+    eit[16] = 0x20;
+
+    let mut ctx = Context::new();
+    let mut parser = AtscEitParser::new();
+    parser.on_psi(&mut ctx, ATSC_EIT_TABLE_ID, &[&eit]);
+    assert!(ctx.events.is_empty());
+    assert_eq!(ctx.stats.invalid_psi, 1);
+  }
+
+  #[test]
+  fn multiple_sections() {
+    let mut ctx = Context::new();
+    let mut parser = AtscEitParser::new();
+    parser.on_psi(&mut ctx, ATSC_EIT_TABLE_ID, &[EIT, EIT]);
+
+    // The events of all the sections make up the instance.
+    match ctx.events[0] {
+      Event::AtscEit(ref eit) => assert_eq!(eit.events.len(), 2),
+      ref e => panic!("unexpected event {:?}", e),
+    }
+  }
+}
@@ -0,0 +1,121 @@
+//! Decoding of the multiple_string_structure of ATSC PSIP, as specified in
+//! ATSC A/65 6.10.
+
+use crate::mp2t::{LanguageString, MultipleString};
+use bytes::Buf;
+
+// ATSC A/65 Table 6.40: the mode selects a page of 256 Unicode code points,
+// except for the modes below.
+const MODE_SCSU: u8 = 0x3E;
+const MODE_UTF16: u8 = 0x3F;
+
+/// Parses a multiple_string_structure. Segments that are compressed with the
+/// Huffman tables of ATSC A/65 Annex C, or coded with SCSU, are not supported:
+/// they decode to U+FFFD.
+pub fn parse_multiple_string(buf: &[u8]) -> Option<MultipleString> {
+  let mut buf = buf;
+  let mut mss = MultipleString::default();
+  if buf.is_empty() {
+    return Some(mss);
+  }
+
+  let number_strings = buf.get_u8();
+  for _ in 0..number_strings {
+    if buf.len() < 4 {
+      return None;
+    }
+    let language = buf[..3].iter().map(|&b| b as char).collect();
+    buf.advance(3);
+    let number_segments = buf.get_u8();
+
+    let mut text = String::new();
+    for _ in 0..number_segments {
+      if buf.len() < 3 {
+        return None;
+      }
+      let compression_type = buf.get_u8();
+      let mode = buf.get_u8();
+      let number_bytes = buf.get_u8() as usize;
+      if buf.len() < number_bytes {
+        return None;
+      }
+      text.push_str(&decode_segment(
+        compression_type,
+        mode,
+        &buf[..number_bytes],
+      ));
+      buf.advance(number_bytes);
+    }
+    mss.strings.push(LanguageString { language, text });
+  }
+  Some(mss)
+}
+
+fn decode_segment(compression_type: u8, mode: u8, buf: &[u8]) -> String {
+  if buf.is_empty() {
+    return String::new();
+  }
+  if compression_type != 0 {
+    return '\u{FFFD}'.to_string();
+  }
+  match mode {
+    MODE_UTF16 => {
+      let units: Vec<u16> = buf
+        .chunks_exact(2)
+        .map(|c| ((c[0] as u16) << 8) | c[1] as u16)
+        .collect();
+      String::from_utf16_lossy(&units)
+    }
+    MODE_SCSU => '\u{FFFD}'.to_string(),
+    // Modes 0x00 to 0x33 select the upper byte of the code points.
+    0x00..=0x33 => buf
+      .iter()
+      .map(|&b| {
+        char::from_u32(((mode as u32) << 8) | b as u32).unwrap_or('\u{FFFD}')
+      })
+      .collect(),
+    _ => '\u{FFFD}'.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn strings() {
+    let mss = parse_multiple_string(&[
+      0x02, // number_strings
+      0x65, 0x6e, 0x67, 0x02, // ISO_639_language_code, number_segments
+      0x00, 0x00, 0x05, 0x4e, 0x65, 0x77, 0x73, 0x20, // "News "
+      0x00, 0x3f, 0x04, 0x00, 0xe9, 0x00, 0x21, // "é!" in UTF-16
+      0x73, 0x70, 0x61, 0x01, // ISO_639_language_code, number_segments
+      0x01, 0x00, 0x02, 0xaa, 0xbb, // Huffman compressed
+    ])
+    .unwrap();
+    assert_eq!(mss.text(), Some("News é!"));
+    assert_eq!(
+      mss.strings,
+      vec![
+        LanguageString {
+          language: "eng".to_string(),
+          text: "News é!".to_string(),
+        },
+        LanguageString {
+          language: "spa".to_string(),
+          text: "\u{FFFD}".to_string(),
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn segment_overrun() {
+    assert_eq!(
+      parse_multiple_string(&[
+        0x01, 0x65, 0x6e, 0x67, 0x01, 0x00, 0x00, 0x05, 0x4e
+      ]),
+      None
+    );
+  }
+}
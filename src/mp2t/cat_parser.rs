@@ -0,0 +1,129 @@
+use crate::mp2t::demuxer::{Context, Event};
+use crate::mp2t::desc;
+use crate::mp2t::psi_parser::{PsiHandler, TableVersions};
+use crate::mp2t::Cat;
+use bytes::Buf;
+use twiddle::Twiddle;
+
+pub struct CatParser {
+  versions: TableVersions<Cat>,
+}
+
+impl CatParser {
+  pub fn new() -> CatParser {
+    CatParser {
+      versions: TableVersions::new(),
+    }
+  }
+
+  fn parse_psi(&mut self, ctx: &mut Context, sections: &[&[u8]]) -> bool {
+    let mut cat: Cat = Default::default();
+
+    for (i, section) in sections.iter().enumerate() {
+      let mut buf = *section;
+
+      if buf.len() < 5 {
+        return false;
+      }
+
+      // The table_id_extension is reserved in the CAT.
+      let _reserved = buf.get_u16();
+      let b = buf.get_u8();
+      let _section_number = buf.get_u8();
+      let _last_section_number = buf.get_u8();
+
+      // All sections share the same header: take it from the first one.
+      if i == 0 {
+        cat.version = b.bits(5..=1);
+        cat.current_next = b.bit(0);
+      }
+
+      match desc::parse_descs(buf) {
+        Some(descs) => cat.descs.extend(descs),
+        None => return false,
+      }
+    }
+
+    let (version, current_next) = (cat.version, cat.current_next);
+    if let Some(update) = self.versions.update(cat, version, current_next) {
+      ctx.events.push_back(Event::Cat {
+        new: update.new,
+        old: update.old,
+        versions: update.versions,
+      });
+    }
+
+    true
+  }
+}
+
+impl PsiHandler for CatParser {
+  const TABLE_ID: u8 = 1; // From ISO/IEC 13818-1: Table 2-31
+
+  fn on_psi(&mut self, ctx: &mut Context, _table_id: u8, sections: &[&[u8]]) {
+    if !self.parse_psi(ctx, sections) {
+      ctx.stats.invalid_psi += 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mp2t::{CaDesc, StreamDesc};
+
+  // CAT with the EMM PIDs of two CA systems.
+  static CAT: &[u8] = &[
+    0xff, 0xff, 0xc3, 0x00, 0x00, // section header
+    0x09, 0x04, 0x01, 0x00, 0xe0, 0x20, // CA_descriptor
+    0x09, 0x06, 0x06, 0x04, 0xe0, 0x21, 0xaa, 0xbb, // CA_descriptor
+  ];
+
+  #[test]
+  fn basic() {
+    let mut ctx = Context::new();
+    let mut parser = CatParser::new();
+    parser.parse_psi(&mut ctx, &[CAT]);
+    parser.parse_psi(&mut ctx, &[CAT]);
+
+    assert_eq!(ctx.events.len(), 1);
+    match ctx.events[0] {
+      Event::Cat {
+        new: ref cat,
+        old: None,
+        ..
+      } => assert_eq!(
+        cat,
+        &Cat {
+          version: 1,
+          current_next: true,
+          descs: vec![
+            StreamDesc::Ca(CaDesc {
+              ca_system_id: 0x0100,
+              ca_pid: 0x20,
+              private_data: Vec::new(),
+            }),
+            StreamDesc::Ca(CaDesc {
+              ca_system_id: 0x0604,
+              ca_pid: 0x21,
+              private_data: vec![0xaa, 0xbb],
+            }),
+          ],
+        }
+      ),
+      ref e => panic!("unexpected event {:?}", e),
+    }
+  }
+
+  #[test]
+  fn descriptor_overrun() {
+    let mut cat = CAT.to_vec();
+    cat[12] = 0x07;
+
+    let mut ctx = Context::new();
+    let mut parser = CatParser::new();
+    parser.on_psi(&mut ctx, 1, &[&cat]);
+    assert!(ctx.events.is_empty());
+    assert_eq!(ctx.stats.invalid_psi, 1);
+  }
+}
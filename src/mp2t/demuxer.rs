@@ -1,20 +1,59 @@
+use crate::mp2t::atsc_eit_parser::AtscEitParser;
+use crate::mp2t::cat_parser::CatParser;
+use crate::mp2t::eit_parser::EitParser;
+use crate::mp2t::ett_parser::EttParser;
+use crate::mp2t::mgt_parser::MgtParser;
+use crate::mp2t::nit_parser::{
+  NitParser, NIT_ACTUAL_TABLE_ID, NIT_OTHER_TABLE_ID,
+};
 use crate::mp2t::pat_parser::PatParser;
+use crate::mp2t::pes_parser::PesParser;
+use crate::mp2t::pid_control::PidControl;
 use crate::mp2t::pmt_parser::PmtParser;
-use crate::mp2t::psi_parser::PsiParser;
+use crate::mp2t::psi_demux::PsiDemux;
+use crate::mp2t::psi_parser::{PsiParser, SectionHandler, TableCollector};
+use crate::mp2t::sdt_parser::{
+  SdtParser, SDT_ACTUAL_TABLE_ID, SDT_OTHER_TABLE_ID,
+};
+use crate::mp2t::stt_parser::SttParser;
+use crate::mp2t::time_parser::TimeParser;
 use crate::mp2t::ts_parser::{TsHandler, TsPacket, TsParser};
-use crate::mp2t::{Pat, Pmt, ProgramInfo};
+use crate::mp2t::vct_parser::{VctParser, CVCT_TABLE_ID, TVCT_TABLE_ID};
+use crate::mp2t::{
+  AtscEit, CaPid, CaPidKind, Cat, Eit, Ett, Guide, Mgt, Nit, Pat, Pmt,
+  ProgramInfo, Sdt, StreamDesc, StreamInfo, Stt, SyncConfig, Time, Vct,
+};
 use crate::stats::Stats;
 use crate::{Error, Result};
-use std::collections::hash_map::HashMap;
+use std::collections::hash_map::{Entry, HashMap};
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::io;
 use std::io::Read;
 
+const NULL_PID: u16 = 0x1FFF;
+// ETSI EN 300 468 Table 1
+const SDT_PID: u16 = 0x11;
+const EIT_PID: u16 = 0x12;
+const TDT_PID: u16 = 0x14;
+// ATSC A/65 6.2
+const PSIP_BASE_PID: u16 = 0x1FFB;
+
 #[derive(Default, Debug, Clone)]
 pub struct Program {
   pub program_info: ProgramInfo,
   pub pmt: Option<Pmt>,
   pub enabled: bool,
+  /// PIDs of the streams that were explicitly disabled. All other streams of
+  /// an enabled program are demuxed.
+  pub disabled_streams: HashSet<u16>,
+  /// The names of the program's service, from the SDT of the transport
+  /// stream.
+  pub provider_name: Option<String>,
+  pub service_name: Option<String>,
+  /// The logical channel number of the program's service, from the NIT of the
+  /// network.
+  pub logical_channel_number: Option<u16>,
 }
 
 pub struct Context {
@@ -22,6 +61,12 @@ pub struct Context {
   pub events: VecDeque<Event>,
 }
 
+impl Default for Context {
+  fn default() -> Context {
+    Context::new()
+  }
+}
+
 impl Context {
   pub fn new() -> Context {
     Context {
@@ -33,17 +78,140 @@ impl Context {
 
 #[derive(Debug)]
 pub enum Event {
-  Pat { new: Pat, old: Option<Pat> },
-  Pmt { new: Pmt, old: Option<Pmt> },
-  Pes,
+  /// A new version of the PAT became current. `versions` holds the
+  /// version_numbers of the PATs applied so far, oldest first.
+  Pat {
+    new: Pat,
+    old: Option<Pat>,
+    versions: Vec<u8>,
+  },
+  /// A new version of the PMT of a program became current. `versions` holds
+  /// the version_numbers of the program's PMTs applied so far, oldest first.
+  Pmt {
+    new: Pmt,
+    old: Option<Pmt>,
+    versions: Vec<u8>,
+  },
+  /// A version of the PAT was announced with current_next_indicator = 0. It
+  /// is only applied once sent again as the current one.
+  NextPat(Pat),
+  /// A version of the PMT of a program was announced with
+  /// current_next_indicator = 0. It is only applied once sent again as the
+  /// current one.
+  NextPmt(Pmt),
+  /// A new version of the CAT became current.
+  Cat {
+    new: Cat,
+    old: Option<Cat>,
+    versions: Vec<u8>,
+  },
+  /// A new version of an SDT became current, either of the actual transport
+  /// stream or of another one.
+  Sdt {
+    new: Sdt,
+    old: Option<Sdt>,
+    versions: Vec<u8>,
+  },
+  /// A new version of a NIT became current, either of the actual network or
+  /// of another one.
+  Nit {
+    new: Nit,
+    old: Option<Nit>,
+    versions: Vec<u8>,
+  },
+  /// A new section of an EIT, present/following or schedule, of the actual
+  /// transport stream or of another one. The events are also collected into
+  /// the demuxer's guide.
+  Eit(Eit),
+  /// The UTC time from a TDT or TOT. Together with the PCRs that surround it,
+  /// it maps the stream time to the broadcast time.
+  Time(Time),
+  /// A new version of the ATSC MGT became current. The EITs and ETTs it lists
+  /// are parsed from then on.
+  Mgt {
+    new: Mgt,
+    old: Option<Mgt>,
+    versions: Vec<u8>,
+  },
+  /// A new version of the ATSC TVCT or CVCT became current.
+  Vct {
+    new: Vct,
+    old: Option<Vct>,
+    versions: Vec<u8>,
+  },
+  /// The ATSC system time.
+  Stt(Stt),
+  /// A new version of the ATSC EIT of a source.
+  AtscEit(AtscEit),
+  /// A new version of an ATSC ETT.
+  Ett(Ett),
+  /// A program was added to the PAT.
+  ProgramAdded {
+    program: ProgramInfo,
+  },
+  /// A program was removed from the PAT. It is no longer tracked, and any
+  /// handlers of its PIDs were removed.
+  ProgramRemoved {
+    program: ProgramInfo,
+  },
+  /// The program_map_PID of a program changed. The program remains enabled if
+  /// it was, but its PMT is reset until it is received on the new PID.
+  ProgramPidChanged {
+    new: ProgramInfo,
+    old: ProgramInfo,
+  },
+  Pes(PesPacket),
+  /// Packet sync was acquired at the given byte position, with packets of the
+  /// given size including their framing.
+  SyncAcquired {
+    pos: i64,
+    packet_size: usize,
+  },
+  /// Packet sync was lost at the packet starting at the given byte position.
+  SyncLost {
+    pos: i64,
+  },
+  /// A program_clock_reference found on the PCR_PID of an enabled program.
+  Pcr {
+    program_number: u16,
+    pcr: u64,
+    pos: i64,
+  },
 }
 
-pub struct PesPacket {}
+/// Handles the sections of a CA PID. ECMs and EMMs are specific to their CA
+/// system, so they are left to the user to decode.
+pub trait CaHandler {
+  /// Called with every section of the PID, from table_id to the end of the
+  /// section.
+  fn on_section(&mut self, pid: u16, section: &[u8]);
+}
+
+/// A reassembled PES packet as specified in ISO/IEC 13818-1 2.4.3.6.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct PesPacket {
+  /// Byte position of the first TS packet of the PES packet.
+  pub pos: i64,
+  /// Index of the originating stream in `Pmt::streams`.
+  pub stream_index: usize,
+  pub stream_id: u8,
+  pub pts: Option<u64>,
+  pub dts: Option<u64>,
+  /// PES_packet_data_bytes.
+  pub data: Vec<u8>,
+}
 
 pub struct Demuxer {
   ctx: Context,
   ts_parser: TsParser<Demult>,
   buf: [u8; 10240],
+  eos: bool,
+}
+
+impl Default for Demuxer {
+  fn default() -> Demuxer {
+    Demuxer::new()
+  }
 }
 
 impl Demuxer {
@@ -52,29 +220,62 @@ impl Demuxer {
       ctx: Context::new(),
       ts_parser: TsParser::new(Demult::new()),
       buf: [0; 10240],
+      eos: false,
     }
   }
 
-  pub fn parse<'a, 'b>(
-    &'a mut self,
-    input: &'b mut dyn Read,
-  ) -> io::Result<Option<Event>> {
+  pub fn parse(&mut self, input: &mut dyn Read) -> io::Result<Option<Event>> {
     loop {
-      self.ts_parser.parse(&mut self.ctx);
+      // Events can change how subsequent packets are handled (e.g. a PMT
+      // installs handlers for its streams), so all pending events must be
+      // processed before parsing more packets.
+      if self.ctx.events.is_empty() {
+        self.ts_parser.parse(&mut self.ctx);
+      }
       match self.ctx.events.pop_front() {
         Some(e) => {
           match e {
             Event::Pat { new: ref pat, .. } => {
-              self.ts_parser.mut_handler().on_pat(pat)
+              self.ts_parser.mut_handler().on_pat(&mut self.ctx, pat)
+            }
+            Event::Pmt { new: ref pmt, .. } => {
+              self.ts_parser.mut_handler().on_pmt(pmt)
+            }
+            Event::Cat { new: ref cat, .. } => {
+              self.ts_parser.mut_handler().on_cat(cat)
+            }
+            Event::Sdt { new: ref sdt, .. } => {
+              self.ts_parser.mut_handler().on_sdt(sdt)
+            }
+            Event::Nit { new: ref nit, .. } => {
+              self.ts_parser.mut_handler().on_nit(nit)
+            }
+            Event::Eit(ref eit) => self.ts_parser.mut_handler().on_eit(eit),
+            Event::Mgt { new: ref mgt, .. } => {
+              self.ts_parser.mut_handler().on_mgt(mgt)
             }
             _ => (),
           }
           return Ok(Some(e));
         }
         None => {
+          if self.eos {
+            return Ok(None);
+          }
+          if self.ts_parser.eos() {
+            // All the packets have been parsed. Flush whatever the handlers
+            // have accumulated so far, and return any resulting events before
+            // reporting the end of the stream.
+            self.eos = true;
+            self.ts_parser.mut_handler().on_eos(&mut self.ctx);
+            continue;
+          }
           let n = input.read(&mut self.buf)?;
           if n == 0 {
-            return Ok(None);
+            // Parse the packets that were waiting for the ones following them
+            // to confirm their sync words.
+            self.ts_parser.set_eos();
+            continue;
           }
           self.ts_parser.push(&self.buf[..n]);
         }
@@ -82,18 +283,101 @@ impl Demuxer {
     }
   }
 
-  pub fn programs<'a>(&'a self) -> impl Iterator<Item = &'a Program> {
+  pub fn programs(&self) -> impl Iterator<Item = &Program> {
     self.ts_parser.handler().programs()
   }
 
   pub fn enable_program(&mut self, program_number: u16) -> Result<()> {
     self.ts_parser.mut_handler().enable_program(program_number)
   }
+
+  /// Sets whether packets with the transport_error_indicator set are dropped
+  /// rather than handed to the PSI and PES parsers. Dropped packets count as
+  /// lost, so the units they belong to are discarded. Off by default.
+  pub fn set_drop_tei_packets(&mut self, drop: bool) {
+    self.ts_parser.mut_handler().drop_tei_packets = drop;
+  }
+
+  /// Sets how packet sync is acquired and lost. By default, four consecutive
+  /// sync bytes acquire it and a single bad one loses it.
+  pub fn set_sync_config(&mut self, config: SyncConfig) {
+    self.ts_parser.set_sync_config(config);
+  }
+
+  /// Returns the program guide collected from the EITs so far.
+  pub fn guide(&self) -> &Guide {
+    &self.ts_parser.handler().guide
+  }
+
+  /// Returns the PIDs of the ECMs and EMMs of a program. ECMs are known once
+  /// the program's PMT is received, and EMMs once the CAT is received.
+  pub fn ca_pids(&self, program_number: u16) -> Result<Vec<CaPid>> {
+    self.ts_parser.handler().ca_pids(program_number)
+  }
+
+  /// Hands the sections of a CA PID, such as those returned by `ca_pids`, to a
+  /// handler. Fails if the PID is already demuxed as SI or as a stream.
+  pub fn set_ca_handler(
+    &mut self,
+    pid: u16,
+    handler: Box<dyn CaHandler>,
+  ) -> Result<()> {
+    self.ts_parser.mut_handler().set_ca_handler(pid, handler)
+  }
+
+  /// Stops handing the sections of a CA PID to its handler.
+  pub fn remove_ca_handler(&mut self, pid: u16) {
+    self.ts_parser.mut_handler().remove_ca_handler(pid)
+  }
+
+  /// Stops demuxing a program, including its PMT and all of its streams.
+  pub fn disable_program(&mut self, program_number: u16) -> Result<()> {
+    self.ts_parser.mut_handler().disable_program(program_number)
+  }
+
+  /// Resumes demuxing a stream previously disabled with `disable_stream`.
+  pub fn enable_stream(&mut self, program_number: u16, pid: u16) -> Result<()> {
+    self
+      .ts_parser
+      .mut_handler()
+      .enable_stream(program_number, pid)
+  }
+
+  /// Stops demuxing the stream with the given PID. The selection applies to
+  /// future versions of the program's PMT as well, and can be made before the
+  /// PMT is received.
+  pub fn disable_stream(
+    &mut self,
+    program_number: u16,
+    pid: u16,
+  ) -> Result<()> {
+    self
+      .ts_parser
+      .mut_handler()
+      .disable_stream(program_number, pid)
+  }
 }
 
 struct Demult {
   pids: HashMap<u16, Box<dyn TsHandler>>,
   programs: HashMap<u16, Program>,
+  // Maps the PCR_PID of the enabled programs to their program_numbers. Several
+  // programs may share the same PCR_PID.
+  pcr_pids: HashMap<u16, Vec<u16>>,
+  cat: Option<Cat>,
+  // The current SDT of the actual transport stream.
+  sdt: Option<Sdt>,
+  // From the current PAT.
+  transport_stream_id: Option<u16>,
+  network_pid: Option<u16>,
+  // The current NIT of the actual network.
+  nit: Option<Nit>,
+  guide: Guide,
+  // The PIDs of the ATSC EITs and ETTs, as listed by the current MGT.
+  psip_pids: Vec<u16>,
+  // The PIDs handed to a user CaHandler.
+  ca_handler_pids: HashSet<u16>,
+  drop_tei_packets: bool,
 }
 
 impl Demult {
@@ -101,74 +385,410 @@ impl Demult {
     let mut d = Demult {
       pids: HashMap::new(),
       programs: HashMap::new(),
+      pcr_pids: HashMap::new(),
+      cat: None,
+      sdt: None,
+      transport_stream_id: None,
+      network_pid: None,
+      nit: None,
+      guide: Guide::new(),
+      psip_pids: Vec::new(),
+      ca_handler_pids: HashSet::new(),
+      drop_tei_packets: false,
     };
-    d.pids.insert(0, Box::new(PsiParser::new(PatParser::new())));
-    return d;
+    d.pids
+      .insert(0, pid_handler(PsiParser::new(PatParser::new())));
+    d.pids
+      .insert(1, pid_handler(PsiParser::new(CatParser::new())));
+
+    // The SDT actual and other share their PID with the BAT, which is skipped.
+    let sdt_collector = TableCollector::with_table_ids(
+      SdtParser::new(),
+      SDT_ACTUAL_TABLE_ID..=SDT_OTHER_TABLE_ID,
+    );
+    d.pids.insert(
+      SDT_PID,
+      pid_handler(PsiParser::with_section_handler(sdt_collector)),
+    );
+    d.pids.insert(
+      EIT_PID,
+      pid_handler(PsiParser::with_section_handler(EitParser::new())),
+    );
+    d.pids.insert(
+      TDT_PID,
+      pid_handler(PsiParser::with_section_handler(TimeParser::new())),
+    );
+
+    // The ATSC PSIP base tables.
+    let mut psip_demux = PsiDemux::new();
+    psip_demux.add(TableCollector::new(MgtParser::new()));
+    psip_demux.add(TableCollector::with_table_ids(
+      VctParser::new(),
+      TVCT_TABLE_ID..=CVCT_TABLE_ID,
+    ));
+    psip_demux.add(TableCollector::new(SttParser::new()));
+    d.pids.insert(
+      PSIP_BASE_PID,
+      pid_handler(PsiParser::with_section_handler(psip_demux)),
+    );
+    d
   }
 
-  pub fn on_pat(&mut self, pat: &Pat) {
+  pub fn on_pat(&mut self, ctx: &mut Context, pat: &Pat) {
+    self.transport_stream_id = Some(pat.transport_stream_id);
+    if pat.network_pid != self.network_pid {
+      if let Some(pid) = self.network_pid {
+        self.pids.remove(&pid);
+      }
+      if let Some(pid) = pat.network_pid {
+        self.pids.insert(pid, nit_handler());
+      }
+      self.network_pid = pat.network_pid;
+    }
+
     let valid_programs: HashMap<u16, &ProgramInfo> =
       pat.programs.iter().map(|p| (p.number, p)).collect();
 
-    // Compile list of programs currently tracked that were invalidated by this
-    // new PAT. This includes programs not in the current PAT, or programs whose
-    // program_pid changed.
-    let dead_program_nums: Vec<u16> = self
+    // Stop tracking the programs that are not in the new PAT.
+    let mut removed_program_nums: Vec<u16> = self
       .programs
-      .values()
-      .filter(|existing_prog| {
-        match valid_programs.get(&existing_prog.program_info.number) {
-          Some(valid_prog) => existing_prog.program_info.pid != valid_prog.pid,
-          None => false,
-        }
-      })
-      .map(|prog| prog.program_info.number)
+      .keys()
+      .filter(|number| !valid_programs.contains_key(number))
+      .cloned()
       .collect();
+    removed_program_nums.sort();
+
+    for number in removed_program_nums {
+      let prog = self.programs.remove(&number).unwrap();
+      self.remove_program_pids(&prog);
+      ctx.events.push_back(Event::ProgramRemoved {
+        program: prog.program_info,
+      });
+    }
+
+    for program_info in &pat.programs {
+      let mut prog = match self.programs.remove(&program_info.number) {
+        Some(prog) => prog,
+        None => {
+          let mut prog = Program {
+            program_info: program_info.clone(),
+            ..Default::default()
+          };
+          if let Some(ref sdt) = self.sdt {
+            set_service_names(&mut prog, sdt);
+          }
+          if let Some(ref nit) = self.nit {
+            set_logical_channel(&mut prog, nit, pat.transport_stream_id);
+          }
+          self.programs.insert(program_info.number, prog);
+          ctx.events.push_back(Event::ProgramAdded {
+            program: program_info.clone(),
+          });
+          continue;
+        }
+      };
 
-    for dead_program_num in dead_program_nums {
-      // Remove all pid mappings associated with the dead program, including the
-      // PMT's pid.
-      let program_pid = self.programs[&dead_program_num].program_info.pid;
-      self.pids.remove(&program_pid);
-      if let Some(ref pmt) = self.programs[&dead_program_num].pmt {
-        for ref stream in &pmt.streams {
-          self.pids.remove(&stream.pid);
+      if prog.program_info.pid != program_info.pid {
+        // The PMT moved to a different PID. Everything we know about the
+        // program is stale, but it remains enabled: start parsing the PMT at
+        // the new PID.
+        self.remove_program_pids(&prog);
+        let old =
+          std::mem::replace(&mut prog.program_info, program_info.clone());
+        prog.pmt = None;
+        if prog.enabled {
+          self.pids.insert(
+            program_info.pid,
+            pid_handler(PsiParser::new(PmtParser::new())),
+          );
         }
+        ctx.events.push_back(Event::ProgramPidChanged {
+          new: program_info.clone(),
+          old,
+        });
       }
 
-      // Stop tracking the program.
-      self.programs.remove(&dead_program_num);
+      self.programs.insert(program_info.number, prog);
     }
+  }
 
-    let mut new_programs: Vec<Program> = pat
+  // Removes all pid mappings associated with a program that is no longer
+  // tracked, including the PMT's pid.
+  fn remove_program_pids(&mut self, prog: &Program) {
+    // Several programs may share the same PMT pid.
+    let pmt_pid_shared = self
       .programs
-      .iter()
-      .filter(|program_info| !self.programs.contains_key(&program_info.number))
-      .map(|program_info| Program {
-        program_info: program_info.clone(),
-        pmt: None,
-        enabled: false,
-      })
+      .values()
+      .any(|p| p.enabled && p.program_info.pid == prog.program_info.pid);
+    if prog.enabled && !pmt_pid_shared {
+      self.pids.remove(&prog.program_info.pid);
+    }
+
+    if let Some(ref pmt) = prog.pmt {
+      for stream in pes_streams(prog, pmt) {
+        self.remove_stream_handler(prog.program_info.number, stream.pid);
+      }
+      self.remove_pcr_pid(prog.program_info.number, pmt.pcr_pid);
+    }
+  }
+
+  pub fn on_pmt(&mut self, pmt: &Pmt) {
+    let number = pmt.program_number;
+    let (old_streams, new_streams) = match self.programs.get(&number) {
+      Some(prog) if prog.enabled => {
+        let old_streams: HashMap<u16, StreamInfo> = prog
+          .pmt
+          .iter()
+          .flat_map(|old_pmt| pes_streams(prog, old_pmt))
+          .map(|s| (s.pid, s.clone()))
+          .collect();
+        let new_streams: HashMap<u16, StreamInfo> =
+          pes_streams(prog, pmt).map(|s| (s.pid, s.clone())).collect();
+        (old_streams, new_streams)
+      }
+      _ => return,
+    };
+
+    // Remove the handlers of streams that are no longer part of the program.
+    for pid in old_streams.keys() {
+      if !new_streams.contains_key(pid) {
+        self.remove_stream_handler(number, *pid);
+      }
+    }
+
+    // Install handlers for new streams, and replace the handlers of streams
+    // whose type or position in the PMT changed. The handler of a stream
+    // shared with another program is kept, so that its PES packets are not cut
+    // short.
+    for (pid, stream) in &new_streams {
+      if self.is_si_pid(*pid) {
+        continue;
+      }
+      let changed = match old_streams.get(pid) {
+        Some(old) => {
+          old.stream_type != stream.stream_type || old.index != stream.index
+        }
+        None => true,
+      };
+      if !self.pids.contains_key(pid)
+        || (changed && !self.stream_pid_shared(number, *pid))
+      {
+        self
+          .pids
+          .insert(*pid, pid_handler(PesParser::new(stream.index)));
+      }
+    }
+
+    let old_pcr_pid = self.programs[&number].pmt.as_ref().map(|p| p.pcr_pid);
+    if let Some(pcr_pid) = old_pcr_pid {
+      self.remove_pcr_pid(number, pcr_pid);
+    }
+    if pmt.pcr_pid != NULL_PID {
+      self.pcr_pids.entry(pmt.pcr_pid).or_default().push(number);
+    }
+
+    self.programs.get_mut(&number).unwrap().pmt = Some(pmt.clone());
+  }
+
+  // Removes the handler of a stream of a program, unless the PID is also a
+  // stream of another enabled program, or carries SI.
+  fn remove_stream_handler(&mut self, program_number: u16, pid: u16) {
+    if !self.is_si_pid(pid) && !self.stream_pid_shared(program_number, pid) {
+      self.pids.remove(&pid);
+    }
+  }
+
+  // Returns whether the PID is a stream of an enabled program other than the
+  // given one.
+  fn stream_pid_shared(&self, program_number: u16, pid: u16) -> bool {
+    self.programs.values().any(|p| {
+      p.enabled
+        && p.program_info.number != program_number
+        && p
+          .pmt
+          .iter()
+          .any(|pmt| pes_streams(p, pmt).any(|s| s.pid == pid))
+    })
+  }
+
+  // Returns whether the PID carries tables that are parsed regardless of the
+  // PMTs: these PIDs never get a stream handler.
+  fn is_si_pid(&self, pid: u16) -> bool {
+    [0, 1, SDT_PID, EIT_PID, TDT_PID, PSIP_BASE_PID].contains(&pid)
+      || self.network_pid == Some(pid)
+      || self.psip_pids.contains(&pid)
+      || self.ca_handler_pids.contains(&pid)
+      || self
+        .programs
+        .values()
+        .any(|p| p.enabled && p.program_info.pid == pid)
+  }
+
+  fn remove_pcr_pid(&mut self, program_number: u16, pcr_pid: u16) {
+    if let Entry::Occupied(mut entry) = self.pcr_pids.entry(pcr_pid) {
+      entry.get_mut().retain(|&number| number != program_number);
+      if entry.get().is_empty() {
+        entry.remove();
+      }
+    }
+  }
+
+  pub fn on_cat(&mut self, cat: &Cat) {
+    self.cat = Some(cat.clone());
+  }
+
+  pub fn on_sdt(&mut self, sdt: &Sdt) {
+    self.guide.on_sdt(sdt);
+    if !sdt.actual {
+      return;
+    }
+    for prog in self.programs.values_mut() {
+      set_service_names(prog, sdt);
+    }
+    self.sdt = Some(sdt.clone());
+  }
+
+  pub fn on_eit(&mut self, eit: &Eit) {
+    self.guide.on_eit(eit);
+  }
+
+  // Installs the parsers of the EITs and ETTs listed by the MGT. PIDs that are
+  // already handled otherwise are left alone. ATSC A/65 gives each EIT-k and
+  // ETT its own PID: one listed by several EITs, or by several ETTs, is not
+  // parsed, since the instances of the tables could not be told apart.
+  pub fn on_mgt(&mut self, mgt: &Mgt) {
+    let mut eit_pids: HashMap<u16, usize> = HashMap::new();
+    let mut ett_pids: HashMap<u16, usize> = HashMap::new();
+    for table in &mgt.tables {
+      if table.is_eit() {
+        *eit_pids.entry(table.pid).or_default() += 1;
+      } else if table.is_ett() {
+        *ett_pids.entry(table.pid).or_default() += 1;
+      }
+    }
+    let shared = |pid: &u16| {
+      eit_pids.get(pid).iter().any(|&&n| n > 1)
+        || ett_pids.get(pid).iter().any(|&&n| n > 1)
+    };
+    let mut pids: Vec<u16> = eit_pids
+      .keys()
+      .chain(ett_pids.keys())
+      .filter(|pid| !shared(pid))
+      .cloned()
       .collect();
+    pids.sort();
+    pids.dedup();
+
+    let old_pids = std::mem::take(&mut self.psip_pids);
+    for pid in &old_pids {
+      if !pids.contains(pid) {
+        self.pids.remove(pid);
+      }
+    }
+    for pid in pids {
+      if old_pids.contains(&pid) {
+        self.psip_pids.push(pid);
+      } else if let Entry::Vacant(entry) = self.pids.entry(pid) {
+        entry.insert(psip_handler());
+        self.psip_pids.push(pid);
+      }
+    }
+  }
 
-    for prog in new_programs.drain(..) {
-      self.programs.insert(prog.program_info.number, prog);
+  pub fn on_nit(&mut self, nit: &Nit) {
+    if !nit.actual {
+      return;
     }
+    if let Some(transport_stream_id) = self.transport_stream_id {
+      for prog in self.programs.values_mut() {
+        set_logical_channel(prog, nit, transport_stream_id);
+      }
+    }
+    self.nit = Some(nit.clone());
   }
 
-  pub fn programs<'a>(&'a self) -> impl Iterator<Item = &'a Program> {
+  pub fn programs(&self) -> impl Iterator<Item = &Program> {
     self.programs.values()
   }
 
+  // The ECMs of a program are signaled by the CA descriptors of its PMT. The
+  // EMMs are not specific to a program: those of the CA systems used by the
+  // program are the ones that apply to it.
+  pub fn ca_pids(&self, program_number: u16) -> Result<Vec<CaPid>> {
+    let prog = match self.programs.get(&program_number) {
+      Some(prog) => prog,
+      None => return Err(Error::InvalidProgramNumber),
+    };
+    let pmt = match prog.pmt {
+      Some(ref pmt) => pmt,
+      None => return Ok(Vec::new()),
+    };
+
+    let mut ca_pids: Vec<CaPid> = Vec::new();
+    for ca in pmt.ca_descs() {
+      let ca_pid = CaPid {
+        kind: CaPidKind::Ecm,
+        ca_system_id: ca.ca_system_id,
+        pid: ca.ca_pid,
+      };
+      if !ca_pids.contains(&ca_pid) {
+        ca_pids.push(ca_pid);
+      }
+    }
+
+    let emms = self.cat.iter().flat_map(|cat| cat.descs.iter());
+    for desc in emms {
+      let ca = match desc {
+        StreamDesc::Ca(ca) => ca,
+        _ => continue,
+      };
+      let used = pmt.ca_descs().any(|c| c.ca_system_id == ca.ca_system_id);
+      let ca_pid = CaPid {
+        kind: CaPidKind::Emm,
+        ca_system_id: ca.ca_system_id,
+        pid: ca.ca_pid,
+      };
+      if used && !ca_pids.contains(&ca_pid) {
+        ca_pids.push(ca_pid);
+      }
+    }
+
+    Ok(ca_pids)
+  }
+
+  pub fn set_ca_handler(
+    &mut self,
+    pid: u16,
+    handler: Box<dyn CaHandler>,
+  ) -> Result<()> {
+    if !self.ca_handler_pids.contains(&pid)
+      && (self.is_si_pid(pid) || self.pids.contains_key(&pid))
+    {
+      return Err(Error::PidInUse);
+    }
+    let section_handler = CaSectionHandler { pid, handler };
+    self.pids.insert(
+      pid,
+      pid_handler(PsiParser::with_section_handler(section_handler)),
+    );
+    self.ca_handler_pids.insert(pid);
+    Ok(())
+  }
+
+  pub fn remove_ca_handler(&mut self, pid: u16) {
+    if self.ca_handler_pids.remove(&pid) {
+      self.pids.remove(&pid);
+    }
+  }
+
   pub fn enable_program(&mut self, program_number: u16) -> Result<()> {
     match self.programs.get_mut(&program_number) {
       Some(ref mut prog) => {
         if !prog.enabled {
           prog.enabled = true;
-          println!("Enabling program {:?}", prog);
           self.pids.insert(
             prog.program_info.pid,
-            Box::new(PsiParser::new(PmtParser::new())),
+            pid_handler(PsiParser::new(PmtParser::new())),
           );
         }
         Ok(())
@@ -176,13 +796,758 @@ impl Demult {
       None => Err(Error::InvalidProgramNumber),
     }
   }
+
+  pub fn disable_program(&mut self, program_number: u16) -> Result<()> {
+    let mut prog = match self.programs.remove(&program_number) {
+      Some(prog) => prog,
+      None => return Err(Error::InvalidProgramNumber),
+    };
+    self.remove_program_pids(&prog);
+    prog.enabled = false;
+    prog.pmt = None;
+    self.programs.insert(program_number, prog);
+    Ok(())
+  }
+
+  pub fn enable_stream(&mut self, program_number: u16, pid: u16) -> Result<()> {
+    let prog = match self.programs.get_mut(&program_number) {
+      Some(prog) => prog,
+      None => return Err(Error::InvalidProgramNumber),
+    };
+    if !prog.disabled_streams.remove(&pid) || !prog.enabled {
+      return Ok(());
+    }
+    let stream = prog
+      .pmt
+      .as_ref()
+      .and_then(|pmt| pmt.streams.iter().find(|s| s.pid == pid));
+    let index = match stream {
+      Some(stream) if stream.stream_type.is_pes() => stream.index,
+      _ => return Ok(()),
+    };
+    // The PID may still be handled for another program.
+    if !self.is_si_pid(pid) && !self.pids.contains_key(&pid) {
+      self.pids.insert(pid, pid_handler(PesParser::new(index)));
+    }
+    Ok(())
+  }
+
+  pub fn disable_stream(
+    &mut self,
+    program_number: u16,
+    pid: u16,
+  ) -> Result<()> {
+    let prog = match self.programs.get_mut(&program_number) {
+      Some(prog) => prog,
+      None => return Err(Error::InvalidProgramNumber),
+    };
+    if !prog.disabled_streams.insert(pid) || !prog.enabled {
+      return Ok(());
+    }
+    let in_pmt = match prog.pmt {
+      Some(ref pmt) => pmt.streams.iter().any(|s| s.pid == pid),
+      None => false,
+    };
+    if in_pmt {
+      self.remove_stream_handler(program_number, pid);
+    }
+    Ok(())
+  }
+}
+
+// Returns the streams of the PMT of a program that are parsed as PES.
+fn pes_streams<'a>(
+  prog: &'a Program,
+  pmt: &'a Pmt,
+) -> impl Iterator<Item = &'a StreamInfo> {
+  pmt.streams.iter().filter(move |s| {
+    s.stream_type.is_pes()
+      && s.pid != prog.program_info.pid
+      && !prog.disabled_streams.contains(&s.pid)
+  })
+}
+
+// Joins the names of the service with the program's number onto the program.
+fn set_service_names(prog: &mut Program, sdt: &Sdt) {
+  let service = sdt
+    .services
+    .iter()
+    .find(|s| s.service_id == prog.program_info.number);
+  prog.provider_name = service.and_then(|s| s.provider_name.clone());
+  prog.service_name = service.and_then(|s| s.service_name.clone());
+}
+
+// Joins the logical channel number of the service with the program's number
+// onto the program.
+fn set_logical_channel(
+  prog: &mut Program,
+  nit: &Nit,
+  transport_stream_id: u16,
+) {
+  prog.logical_channel_number = nit
+    .transport_streams
+    .iter()
+    .filter(|ts| ts.transport_stream_id == transport_stream_id)
+    .flat_map(|ts| ts.logical_channels())
+    .find(|lcn| lcn.service_id == prog.program_info.number)
+    .map(|lcn| lcn.logical_channel_number);
+}
+
+// The NIT actual and other share the network PID.
+fn nit_handler() -> Box<dyn TsHandler> {
+  let nit_collector = TableCollector::with_table_ids(
+    NitParser::new(),
+    NIT_ACTUAL_TABLE_ID..=NIT_OTHER_TABLE_ID,
+  );
+  pid_handler(PsiParser::with_section_handler(nit_collector))
+}
+
+// Parses the ATSC EIT-k and the ETT listed by the MGT on a PID.
+fn psip_handler() -> Box<dyn TsHandler> {
+  let mut psip_demux = PsiDemux::new();
+  psip_demux.add(TableCollector::new(AtscEitParser::new()));
+  psip_demux.add(TableCollector::new(EttParser::new()));
+  pid_handler(PsiParser::with_section_handler(psip_demux))
+}
+
+// Hands all the sections of a CA PID to a user handler.
+struct CaSectionHandler {
+  pid: u16,
+  handler: Box<dyn CaHandler>,
+}
+
+impl SectionHandler for CaSectionHandler {
+  fn accepts(&self, _table_id: u8) -> bool {
+    true
+  }
+
+  fn on_section(&mut self, _ctx: &mut Context, section: &[u8]) -> bool {
+    self.handler.on_section(self.pid, section);
+    true
+  }
+}
+
+// Wraps the handler of a PID in continuity control, so that lost packets are
+// accounted for and partial units discarded before reaching the handler.
+fn pid_handler<H>(handler: H) -> Box<dyn TsHandler>
+where
+  H: TsHandler + 'static,
+{
+  Box::new(PidControl::new(handler))
 }
 
 impl TsHandler for Demult {
   fn on_pkt(&mut self, ctx: &mut Context, pkt: &TsPacket) {
+    if pkt.transport_error && self.drop_tei_packets {
+      return;
+    }
+
+    let pcr_programs = self.pcr_pids.get(&pkt.pid);
+    if let (Some(programs), Some(pcr)) = (pcr_programs, pkt.pcr) {
+      for &program_number in programs {
+        ctx.events.push_back(Event::Pcr {
+          program_number,
+          pcr,
+          pos: pkt.pos,
+        });
+      }
+    }
+    let is_pcr_pid = pcr_programs.is_some();
+
     match self.pids.get_mut(&pkt.pid) {
       Some(handler) => handler.on_pkt(ctx, pkt),
+      // Packets of PCR-only PIDs are fully handled above.
+      None if is_pcr_pid => (),
       None => ctx.stats.ignored_ts_packets += 1,
     }
   }
+
+  fn on_eos(&mut self, ctx: &mut Context) {
+    for handler in self.pids.values_mut() {
+      handler.on_eos(ctx);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mp2t::StreamType;
+  use std::cell::RefCell;
+  use std::rc::Rc;
+
+  fn pat(programs: &[(u16, u16)]) -> Pat {
+    Pat {
+      programs: programs
+        .iter()
+        .map(|&(number, pid)| ProgramInfo { number, pid })
+        .collect(),
+      ..Default::default()
+    }
+  }
+
+  fn pmt(program_number: u16, pcr_pid: u16, streams: &[(u32, u16)]) -> Pmt {
+    Pmt {
+      program_number,
+      pcr_pid,
+      streams: streams
+        .iter()
+        .enumerate()
+        .map(|(index, &(stream_type, pid))| StreamInfo {
+          pid,
+          stream_type: StreamType(stream_type),
+          index,
+          descs: Vec::new(),
+          es_info: Vec::new(),
+        })
+        .collect(),
+      ..Default::default()
+    }
+  }
+
+  // Returns the PIDs with a handler, other than those of the SI tables that are
+  // always parsed (PAT, CAT, SDT, EIT, TDT and PSIP).
+  fn pids(demult: &Demult) -> Vec<u16> {
+    let mut pids: Vec<u16> = demult
+      .pids
+      .keys()
+      .cloned()
+      .filter(|&pid| {
+        pid > 1 && ![SDT_PID, EIT_PID, TDT_PID, PSIP_BASE_PID].contains(&pid)
+      })
+      .collect();
+    pids.sort();
+    pids
+  }
+
+  #[test]
+  fn pmt_installs_stream_handlers() {
+    let mut demult = Demult::new();
+    demult.on_pat(&mut Context::new(), &pat(&[(1, 0x100)]));
+    demult.enable_program(1).unwrap();
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101), (0x0f, 0x102)]));
+
+    assert_eq!(pids(&demult), vec![0x100, 0x101, 0x102]);
+    assert_eq!(demult.programs[&1].pmt.as_ref().unwrap().streams.len(), 2);
+  }
+
+  #[test]
+  fn pmt_ignored_for_disabled_program() {
+    let mut demult = Demult::new();
+    demult.on_pat(&mut Context::new(), &pat(&[(1, 0x100)]));
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101)]));
+
+    assert!(pids(&demult).is_empty());
+    assert!(demult.programs[&1].pmt.is_none());
+  }
+
+  #[test]
+  fn pmt_update_removes_streams() {
+    let mut demult = Demult::new();
+    demult.on_pat(&mut Context::new(), &pat(&[(1, 0x100)]));
+    demult.enable_program(1).unwrap();
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101), (0x0f, 0x102)]));
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101), (0x81, 0x103)]));
+
+    assert_eq!(pids(&demult), vec![0x100, 0x101, 0x103]);
+  }
+
+  #[test]
+  fn pcr_only_pid() {
+    let mut demult = Demult::new();
+    demult.on_pat(&mut Context::new(), &pat(&[(1, 0x100)]));
+    demult.enable_program(1).unwrap();
+    demult.on_pmt(&pmt(1, 0x1ff, &[(0x1b, 0x101)]));
+
+    let mut ctx = Context::new();
+    demult.on_pkt(
+      &mut ctx,
+      &TsPacket {
+        pid: 0x1ff,
+        pcr: Some(1234),
+        pos: 188,
+        ..Default::default()
+      },
+    );
+
+    assert_eq!(ctx.stats.ignored_ts_packets, 0);
+    match ctx.events.pop_front() {
+      Some(Event::Pcr {
+        program_number,
+        pcr,
+        pos,
+      }) => assert_eq!((program_number, pcr, pos), (1, 1234, 188)),
+      e => panic!("unexpected event {:?}", e),
+    }
+
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101)]));
+    demult.on_pkt(
+      &mut ctx,
+      &TsPacket {
+        pid: 0x1ff,
+        pcr: Some(1234),
+        ..Default::default()
+      },
+    );
+    assert_eq!(ctx.stats.ignored_ts_packets, 1);
+    assert!(ctx.events.is_empty());
+  }
+
+  #[test]
+  fn shared_pcr_pid() {
+    let mut ctx = Context::new();
+    let mut demult = Demult::new();
+    demult.on_pat(&mut ctx, &pat(&[(1, 0x100), (2, 0x200)]));
+    demult.enable_program(1).unwrap();
+    demult.enable_program(2).unwrap();
+    demult.on_pmt(&pmt(1, 0x1ff, &[(0x1b, 0x101)]));
+    demult.on_pmt(&pmt(2, 0x1ff, &[(0x1b, 0x201)]));
+
+    let pcr_pkt = TsPacket {
+      pid: 0x1ff,
+      pcr: Some(1234),
+      ..Default::default()
+    };
+    let pcr_programs = |ctx: &mut Context| -> Vec<u16> {
+      ctx
+        .events
+        .drain(..)
+        .map(|e| match e {
+          Event::Pcr { program_number, .. } => program_number,
+          e => panic!("unexpected event {:?}", e),
+        })
+        .collect()
+    };
+    let mut ctx = Context::new();
+    demult.on_pkt(&mut ctx, &pcr_pkt);
+    assert_eq!(pcr_programs(&mut ctx), vec![1, 2]);
+
+    // A new PMT of one program leaves the other's PCR alone.
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101)]));
+    demult.on_pkt(&mut ctx, &pcr_pkt);
+    assert_eq!(pcr_programs(&mut ctx), vec![2]);
+
+    demult.on_pat(&mut ctx, &pat(&[(1, 0x100)]));
+    assert_eq!(demult.pcr_pids.keys().collect::<Vec<_>>(), vec![&0x101]);
+  }
+
+  // Identifies the handler of a PID, to tell whether it was replaced.
+  fn handler_ptr(demult: &Demult, pid: u16) -> *const u8 {
+    &*demult.pids[&pid] as *const dyn TsHandler as *const u8
+  }
+
+  #[test]
+  fn shared_stream_pid() {
+    let mut ctx = Context::new();
+    let mut demult = Demult::new();
+    demult.on_pat(&mut ctx, &pat(&[(1, 0x100), (2, 0x200)]));
+    demult.enable_program(1).unwrap();
+    demult.enable_program(2).unwrap();
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101), (0x0f, 0x300)]));
+    demult.on_pmt(&pmt(2, 0x201, &[(0x1b, 0x201), (0x0f, 0x300)]));
+    let handler = handler_ptr(&demult, 0x300);
+
+    // The handler is not replaced when the stream moves in one of the PMTs.
+    demult.on_pmt(&pmt(2, 0x201, &[(0x0f, 0x300), (0x1b, 0x201)]));
+    assert_eq!(handler_ptr(&demult, 0x300), handler);
+
+    // Nor removed with one of the programs.
+    demult.on_pat(&mut ctx, &pat(&[(2, 0x200)]));
+    assert_eq!(pids(&demult), vec![0x200, 0x201, 0x300]);
+    assert_eq!(handler_ptr(&demult, 0x300), handler);
+
+    demult.disable_program(2).unwrap();
+    assert!(pids(&demult).is_empty());
+  }
+
+  #[test]
+  fn stream_on_si_pid() {
+    let mut ctx = Context::new();
+    let mut demult = Demult::new();
+    demult.on_pat(&mut ctx, &pat(&[(1, 0x100), (2, 0x200)]));
+    demult.enable_program(1).unwrap();
+    demult.enable_program(2).unwrap();
+    let pmt_handler = handler_ptr(&demult, 0x200);
+    let eit_handler = handler_ptr(&demult, EIT_PID);
+
+    // Streams on the PID of another PMT or of an SI table are not parsed.
+    demult.on_pmt(&pmt(
+      1,
+      0x101,
+      &[(0x1b, 0x101), (0x0f, 0x200), (0x0f, EIT_PID)],
+    ));
+    assert_eq!(handler_ptr(&demult, 0x200), pmt_handler);
+    assert_eq!(handler_ptr(&demult, EIT_PID), eit_handler);
+
+    demult.disable_program(1).unwrap();
+    assert_eq!(handler_ptr(&demult, 0x200), pmt_handler);
+    assert_eq!(handler_ptr(&demult, EIT_PID), eit_handler);
+  }
+
+  #[test]
+  fn drop_tei_packets() {
+    let mut demult = Demult::new();
+    demult.on_pat(&mut Context::new(), &pat(&[(1, 0x100)]));
+    demult.enable_program(1).unwrap();
+    demult.on_pmt(&pmt(1, 0x1ff, &[(0x1b, 0x101)]));
+
+    let tei_pkt = TsPacket {
+      pid: 0x1ff,
+      pcr: Some(1234),
+      transport_error: true,
+      ..Default::default()
+    };
+    let mut ctx = Context::new();
+    demult.on_pkt(&mut ctx, &tei_pkt);
+    assert_eq!(ctx.events.len(), 1);
+
+    demult.drop_tei_packets = true;
+    let mut ctx = Context::new();
+    demult.on_pkt(&mut ctx, &tei_pkt);
+    assert!(ctx.events.is_empty());
+  }
+
+  fn event_names(ctx: &Context) -> Vec<String> {
+    ctx
+      .events
+      .iter()
+      .map(|e| match e {
+        Event::ProgramAdded { program } => format!("+{}", program.number),
+        Event::ProgramRemoved { program } => format!("-{}", program.number),
+        Event::ProgramPidChanged { new, old } => {
+          format!("{}:{:x}->{:x}", new.number, old.pid, new.pid)
+        }
+        e => panic!("unexpected event {:?}", e),
+      })
+      .collect()
+  }
+
+  #[test]
+  fn pat_program_lifecycle() {
+    let mut ctx = Context::new();
+    let mut demult = Demult::new();
+    demult.on_pat(&mut ctx, &pat(&[(1, 0x100), (2, 0x200), (3, 0x300)]));
+    assert_eq!(event_names(&ctx), vec!["+1", "+2", "+3"]);
+
+    ctx.events.clear();
+    demult.on_pat(&mut ctx, &pat(&[(2, 0x200), (3, 0x310), (4, 0x400)]));
+    assert_eq!(event_names(&ctx), vec!["-1", "3:300->310", "+4"]);
+
+    let mut numbers: Vec<u16> =
+      demult.programs().map(|p| p.program_info.number).collect();
+    numbers.sort();
+    assert_eq!(numbers, vec![2, 3, 4]);
+  }
+
+  #[test]
+  fn pat_removed_program_handlers() {
+    let mut ctx = Context::new();
+    let mut demult = Demult::new();
+    demult.on_pat(&mut ctx, &pat(&[(1, 0x100), (2, 0x200)]));
+    demult.enable_program(1).unwrap();
+    demult.enable_program(2).unwrap();
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101)]));
+    demult.on_pmt(&pmt(2, 0x201, &[(0x1b, 0x201)]));
+    assert_eq!(pids(&demult), vec![0x100, 0x101, 0x200, 0x201]);
+
+    demult.on_pat(&mut ctx, &pat(&[(2, 0x200)]));
+    assert_eq!(pids(&demult), vec![0x200, 0x201]);
+    assert_eq!(demult.pcr_pids.keys().collect::<Vec<_>>(), vec![&0x201]);
+  }
+
+  #[test]
+  fn pat_program_pid_changed() {
+    let mut ctx = Context::new();
+    let mut demult = Demult::new();
+    demult.on_pat(&mut ctx, &pat(&[(1, 0x100)]));
+    demult.enable_program(1).unwrap();
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101)]));
+
+    demult.on_pat(&mut ctx, &pat(&[(1, 0x110)]));
+    assert_eq!(pids(&demult), vec![0x110]);
+    assert!(demult.programs[&1].enabled);
+    assert!(demult.programs[&1].pmt.is_none());
+  }
+
+  #[test]
+  fn disable_program() {
+    let mut demult = Demult::new();
+    demult.on_pat(&mut Context::new(), &pat(&[(1, 0x100)]));
+    demult.enable_program(1).unwrap();
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101), (0x0f, 0x102)]));
+
+    demult.disable_program(1).unwrap();
+    assert!(pids(&demult).is_empty());
+    assert!(demult.pcr_pids.is_empty());
+    assert!(!demult.programs[&1].enabled);
+    assert!(demult.programs[&1].pmt.is_none());
+
+    // A PMT that was already in flight is ignored.
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101), (0x0f, 0x102)]));
+    assert!(pids(&demult).is_empty());
+
+    demult.enable_program(1).unwrap();
+    assert_eq!(pids(&demult), vec![0x100]);
+
+    assert!(demult.disable_program(2).is_err());
+  }
+
+  #[test]
+  fn stream_selection() {
+    let mut demult = Demult::new();
+    demult.on_pat(&mut Context::new(), &pat(&[(1, 0x100)]));
+    demult.enable_program(1).unwrap();
+
+    // Streams can be disabled before the PMT is received.
+    demult.disable_stream(1, 0x103).unwrap();
+    demult.on_pmt(&pmt(
+      1,
+      0x101,
+      &[(0x1b, 0x101), (0x0f, 0x102), (0x0f, 0x103)],
+    ));
+    assert_eq!(pids(&demult), vec![0x100, 0x101, 0x102]);
+
+    demult.disable_stream(1, 0x102).unwrap();
+    demult.enable_stream(1, 0x103).unwrap();
+    assert_eq!(pids(&demult), vec![0x100, 0x101, 0x103]);
+
+    // The selection persists across PMT versions.
+    demult.on_pmt(&pmt(
+      1,
+      0x101,
+      &[(0x1b, 0x101), (0x0f, 0x102), (0x0f, 0x103), (0x81, 0x104)],
+    ));
+    assert_eq!(pids(&demult), vec![0x100, 0x101, 0x103, 0x104]);
+
+    assert!(demult.enable_stream(2, 0x101).is_err());
+    assert!(demult.disable_stream(2, 0x101).is_err());
+  }
+
+  #[test]
+  fn ca_pids() {
+    let mut demult = Demult::new();
+    demult.on_pat(&mut Context::new(), &pat(&[(1, 0x100), (2, 0x200)]));
+    demult.enable_program(1).unwrap();
+
+    let ca = |ca_system_id, ca_pid| {
+      StreamDesc::Ca(crate::mp2t::CaDesc {
+        ca_system_id,
+        ca_pid,
+        private_data: Vec::new(),
+      })
+    };
+    let ca_pid = |kind, ca_system_id, pid| CaPid {
+      kind,
+      ca_system_id,
+      pid,
+    };
+
+    // The program is scrambled with CA system 0x100, the video stream with CA
+    // system 0x604 too.
+    let mut p = pmt(1, 0x101, &[(0x1b, 0x101), (0x0f, 0x102)]);
+    p.descs.push(ca(0x100, 0x110));
+    p.streams[0].descs.push(ca(0x604, 0x111));
+    demult.on_pmt(&p);
+    assert_eq!(
+      demult.ca_pids(1).unwrap(),
+      vec![
+        ca_pid(CaPidKind::Ecm, 0x100, 0x110),
+        ca_pid(CaPidKind::Ecm, 0x604, 0x111),
+      ]
+    );
+
+    demult.on_cat(&Cat {
+      descs: vec![
+        ca(0x100, 0x20),
+        ca(0x500, 0x21),
+        ca(0x604, 0x22),
+        ca(0x100, 0x20),
+      ],
+      ..Default::default()
+    });
+    assert_eq!(
+      demult.ca_pids(1).unwrap(),
+      vec![
+        ca_pid(CaPidKind::Ecm, 0x100, 0x110),
+        ca_pid(CaPidKind::Ecm, 0x604, 0x111),
+        ca_pid(CaPidKind::Emm, 0x100, 0x20),
+        ca_pid(CaPidKind::Emm, 0x604, 0x22),
+      ]
+    );
+
+    // No PMT yet.
+    assert!(demult.ca_pids(2).unwrap().is_empty());
+    assert!(demult.ca_pids(3).is_err());
+  }
+
+  // Records the PID and the data of the sections it receives.
+  type CaSections = Rc<RefCell<Vec<(u16, Vec<u8>)>>>;
+
+  struct CaRecorder(CaSections);
+
+  impl CaHandler for CaRecorder {
+    fn on_section(&mut self, pid: u16, section: &[u8]) {
+      self.0.borrow_mut().push((pid, section.to_vec()));
+    }
+  }
+
+  #[test]
+  fn ca_handler() {
+    let mut demult = Demult::new();
+    demult.on_pat(&mut Context::new(), &pat(&[(1, 0x100)]));
+    demult.enable_program(1).unwrap();
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101)]));
+
+    let res: CaSections = Rc::new(RefCell::new(Vec::new()));
+    let handler = || Box::new(CaRecorder(res.clone()));
+    assert!(demult.set_ca_handler(1, handler()).is_err());
+    assert!(demult.set_ca_handler(0x100, handler()).is_err());
+    assert!(demult.set_ca_handler(0x101, handler()).is_err());
+    demult.set_ca_handler(0x110, handler()).unwrap();
+
+    // An ECM, as a short-form private section, followed by stuffing.
+    let ecm = [0x80, 0x70, 0x03, 0xaa, 0xbb, 0xcc];
+    let mut payload = vec![0x00];
+    payload.extend_from_slice(&ecm);
+    payload.extend_from_slice(&[0xff; 4]);
+    let mut ctx = Context::new();
+    demult.on_pkt(
+      &mut ctx,
+      &TsPacket {
+        pid: 0x110,
+        payload_start: true,
+        payload: &payload,
+        ..Default::default()
+      },
+    );
+    assert_eq!(*res.borrow(), vec![(0x110, ecm.to_vec())]);
+
+    // A PMT listing the PID as a stream leaves its handler alone.
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101), (0x0f, 0x110)]));
+    demult.on_pmt(&pmt(1, 0x101, &[(0x1b, 0x101)]));
+    assert_eq!(pids(&demult), vec![0x100, 0x101, 0x110]);
+
+    demult.remove_ca_handler(0x110);
+    assert_eq!(pids(&demult), vec![0x100, 0x101]);
+  }
+
+  #[test]
+  fn service_names() {
+    let service = |service_id, name: &str| crate::mp2t::Service {
+      service_id,
+      provider_name: Some("ABC".to_string()),
+      service_name: Some(name.to_string()),
+      ..Default::default()
+    };
+    let sdt = |services| Sdt {
+      actual: true,
+      services,
+      ..Default::default()
+    };
+
+    let mut demult = Demult::new();
+    demult.on_pat(&mut Context::new(), &pat(&[(1, 0x100)]));
+    demult.on_sdt(&sdt(vec![service(1, "ABC 1"), service(2, "ABC 2")]));
+    assert_eq!(demult.programs[&1].service_name.as_deref(), Some("ABC 1"));
+    assert_eq!(demult.programs[&1].provider_name.as_deref(), Some("ABC"));
+
+    // Programs added after the SDT was received get their names too.
+    demult.on_pat(&mut Context::new(), &pat(&[(1, 0x100), (2, 0x200)]));
+    assert_eq!(demult.programs[&2].service_name.as_deref(), Some("ABC 2"));
+
+    // Only the SDT of the actual transport stream applies.
+    let mut other = sdt(vec![service(1, "Other")]);
+    other.actual = false;
+    demult.on_sdt(&other);
+    assert_eq!(demult.programs[&1].service_name.as_deref(), Some("ABC 1"));
+
+    demult.on_sdt(&sdt(vec![service(2, "ABC 2")]));
+    assert_eq!(demult.programs[&1].service_name, None);
+  }
+
+  #[test]
+  fn logical_channels() {
+    let nit = Nit {
+      actual: true,
+      transport_streams: vec![crate::mp2t::NitTransportStream {
+        transport_stream_id: 4,
+        original_network_id: 0x3001,
+        descs: vec![StreamDesc::LogicalChannel(
+          crate::mp2t::LogicalChannelDesc {
+            channels: vec![crate::mp2t::LogicalChannel {
+              service_id: 1,
+              visible: true,
+              logical_channel_number: 7,
+            }],
+          },
+        )],
+      }],
+      ..Default::default()
+    };
+
+    let mut demult = Demult::new();
+    let mut p = pat(&[(1, 0x100)]);
+    p.transport_stream_id = 4;
+    p.network_pid = Some(0x10);
+    demult.on_pat(&mut Context::new(), &p);
+    assert!(demult.pids.contains_key(&0x10));
+
+    demult.on_nit(&nit);
+    assert_eq!(demult.programs[&1].logical_channel_number, Some(7));
+
+    // The NIT describes another transport stream.
+    p.transport_stream_id = 5;
+    p.network_pid = Some(0x11f);
+    p.programs.push(ProgramInfo {
+      number: 2,
+      pid: 0x200,
+    });
+    demult.on_pat(&mut Context::new(), &p);
+    assert!(!demult.pids.contains_key(&0x10));
+    assert!(demult.pids.contains_key(&0x11f));
+    assert_eq!(demult.programs[&2].logical_channel_number, None);
+  }
+
+  #[test]
+  fn psip_pids() {
+    let table = |table_type, pid| crate::mp2t::MgtTable {
+      table_type,
+      pid,
+      ..Default::default()
+    };
+
+    let mut demult = Demult::new();
+    let mut p = pat(&[]);
+    p.network_pid = Some(0x10);
+    demult.on_pat(&mut Context::new(), &p);
+    demult.on_mgt(&Mgt {
+      tables: vec![
+        table(0x0000, PSIP_BASE_PID),
+        table(0x0100, 0x1D00),
+        table(0x0101, 0x1D01),
+        table(0x0004, 0x1E00),
+        // Already taken by the NIT.
+        table(0x0200, 0x10),
+      ],
+      ..Default::default()
+    });
+    assert_eq!(pids(&demult), vec![0x10, 0x1D00, 0x1D01, 0x1E00]);
+
+    demult.on_mgt(&Mgt {
+      tables: vec![table(0x0100, 0x1D00), table(0x0004, 0x1E01)],
+      ..Default::default()
+    });
+    assert_eq!(pids(&demult), vec![0x10, 0x1D00, 0x1E01]);
+    // An EIT-k and an ETT may share a PID, but two EITs may not.
+    demult.on_mgt(&Mgt {
+      tables: vec![
+        table(0x0100, 0x1D00),
+        table(0x0200, 0x1D00),
+        table(0x0101, 0x1D01),
+        table(0x0102, 0x1D01),
+      ],
+      ..Default::default()
+    });
+    assert_eq!(pids(&demult), vec![0x10, 0x1D00]);
+  }
 }
@@ -1,4 +1,6 @@
+use crate::mp2t::dvb_time;
 use bytes::Buf;
+use twiddle::Twiddle;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum StreamDesc {
@@ -6,6 +8,45 @@ pub enum StreamDesc {
   Metadata(MetadataDesc),
   Ac3(Ac3Desc),
   Eac3(Eac3Desc),
+  Ca(CaDesc),
+  VideoStream(VideoStreamDesc),
+  AudioStream(AudioStreamDesc),
+  Hierarchy(HierarchyDesc),
+  DataStreamAlignment(DataStreamAlignmentDesc),
+  Iso639Language(Iso639LanguageDesc),
+  MaximumBitrate(MaximumBitrateDesc),
+  Mpeg4Audio(Mpeg4AudioDesc),
+  MetadataPointer(MetadataPointerDesc),
+  MetadataStd(MetadataStdDesc),
+  AvcVideo(AvcVideoDesc),
+  Mpeg2AacAudio(Mpeg2AacAudioDesc),
+  HevcVideo(HevcVideoDesc),
+  Extension(ExtensionDesc),
+  StreamIdentifier(StreamIdentifierDesc),
+  Subtitling(SubtitlingDesc),
+  Teletext(TeletextDesc),
+  VbiTeletext(TeletextDesc),
+  Component(ComponentDesc),
+  Service(ServiceDesc),
+  Aac(AacDesc),
+  Dts(DtsDesc),
+  SupplementaryAudio(SupplementaryAudioDesc),
+  NetworkName(NetworkNameDesc),
+  ServiceList(ServiceListDesc),
+  SatelliteDelivery(SatelliteDeliveryDesc),
+  CableDelivery(CableDeliveryDesc),
+  TerrestrialDelivery(TerrestrialDeliveryDesc),
+  LogicalChannel(LogicalChannelDesc),
+  ShortEvent(ShortEventDesc),
+  ExtendedEvent(ExtendedEventDesc),
+  Content(ContentDesc),
+  LocalTimeOffset(LocalTimeOffsetDesc),
+  /// A descriptor with a tag that is not known, or that could not be parsed.
+  /// `data` holds the descriptor bytes after descriptor_length.
+  Unknown {
+    tag: u8,
+    data: Vec<u8>,
+  },
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -21,13 +62,545 @@ pub struct MetadataDesc {
 const METADATA_DESC_TAG: u8 = 38; // ISO/IEC 13818-1 Table 2-45
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Ac3Desc;
+pub struct Ac3Desc {
+  pub component_type: Option<Ac3ComponentType>,
+  pub bsid: Option<u8>,
+  pub mainid: Option<u8>,
+  pub asvc: Option<u8>,
+  pub additional_info: Vec<u8>,
+}
 const AC3_DESCRIPTOR_TAG: u8 = 106; // ETSI EN 300 468 Annex D (D.3)
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Eac3Desc;
+pub struct Eac3Desc {
+  pub component_type: Option<Ac3ComponentType>,
+  pub bsid: Option<u8>,
+  pub mainid: Option<u8>,
+  pub asvc: Option<u8>,
+  /// Whether the stream contains mixing metadata.
+  pub mixinfoexists: bool,
+  /// The component types of the independent substreams 1 to 3, if present.
+  pub substream1: Option<Ac3ComponentType>,
+  pub substream2: Option<Ac3ComponentType>,
+  pub substream3: Option<Ac3ComponentType>,
+  pub additional_info: Vec<u8>,
+}
 const EAC3_DESCRIPTOR_TAG: u8 = 122; // ETSI EN 300 468 Annex D (D.5)
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CaDesc {
+  pub ca_system_id: u16,
+  /// The PID of the ECMs when found in the PMT, or of the EMMs when found in
+  /// the CAT.
+  pub ca_pid: u16,
+  pub private_data: Vec<u8>,
+}
+const CA_DESC_TAG: u8 = 9; // ISO/IEC 13818-1 2.6.16
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VideoStreamDesc {
+  pub multiple_frame_rate: bool,
+  pub frame_rate_code: u8,
+  pub mpeg1_only: bool,
+  pub constrained_parameter: bool,
+  pub still_picture: bool,
+  /// The following fields are only present if `mpeg1_only` is not set.
+  pub profile_and_level_indication: Option<u8>,
+  pub chroma_format: Option<u8>,
+  pub frame_rate_extension: Option<bool>,
+}
+const VIDEO_STREAM_DESC_TAG: u8 = 2; // ISO/IEC 13818-1 2.6.2
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AudioStreamDesc {
+  pub free_format: bool,
+  pub id: u8,
+  pub layer: u8,
+  pub variable_rate_audio: bool,
+}
+const AUDIO_STREAM_DESC_TAG: u8 = 3; // ISO/IEC 13818-1 2.6.4
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HierarchyDesc {
+  pub no_view_scalability: bool,
+  pub no_temporal_scalability: bool,
+  pub no_spatial_scalability: bool,
+  pub no_quality_scalability: bool,
+  pub hierarchy_type: u8,
+  pub layer_index: u8,
+  pub tref_present: bool,
+  pub embedded_layer_index: u8,
+  pub channel: u8,
+}
+const HIERARCHY_DESC_TAG: u8 = 4; // ISO/IEC 13818-1 2.6.6
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DataStreamAlignmentDesc {
+  pub alignment_type: u8,
+}
+const DATA_STREAM_ALIGNMENT_DESC_TAG: u8 = 6; // ISO/IEC 13818-1 2.6.10
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Iso639LanguageDesc {
+  pub languages: Vec<Iso639Language>,
+}
+const ISO_639_LANGUAGE_DESC_TAG: u8 = 10; // ISO/IEC 13818-1 2.6.18
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Iso639Language {
+  /// The ISO 639-2 language code, e.g. "eng".
+  pub language: String,
+  /// 0: undefined, 1: clean effects, 2: hearing impaired, 3: visual impaired
+  /// commentary.
+  pub audio_type: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MaximumBitrateDesc {
+  /// In units of 50 bytes/second.
+  pub maximum_bitrate: u32,
+}
+const MAXIMUM_BITRATE_DESC_TAG: u8 = 14; // ISO/IEC 13818-1 2.6.26
+
+impl MaximumBitrateDesc {
+  pub fn bits_per_second(&self) -> u64 {
+    self.maximum_bitrate as u64 * 50 * 8
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Mpeg4AudioDesc {
+  pub profile_and_level: u8,
+}
+const MPEG4_AUDIO_DESC_TAG: u8 = 28; // ISO/IEC 13818-1 2.6.38
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MetadataPointerDesc {
+  pub metadata_application_format: u16,
+  /// Only present if `metadata_application_format` is 0xFFFF.
+  pub app_format_id: Option<u32>,
+  pub metadata_format: u8,
+  /// Only present if `metadata_format` is 0xFF.
+  pub format_id: Option<u32>,
+  pub metadata_service_id: u8,
+  pub metadata_locator: Option<Vec<u8>>,
+  pub mpeg_carriage: u8,
+  pub program_number: Option<u16>,
+  pub transport_stream_location: Option<u16>,
+  pub transport_stream_id: Option<u16>,
+  pub private_data: Vec<u8>,
+}
+const METADATA_POINTER_DESC_TAG: u8 = 37; // ISO/IEC 13818-1 2.6.58
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MetadataStdDesc {
+  /// In units of 400 bits/second.
+  pub input_leak_rate: u32,
+  /// In units of 1024 bytes.
+  pub buffer_size: u32,
+  /// In units of 400 bits/second.
+  pub output_leak_rate: u32,
+}
+const METADATA_STD_DESC_TAG: u8 = 39; // ISO/IEC 13818-1 2.6.62
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AvcVideoDesc {
+  pub profile_idc: u8,
+  /// constraint_set0_flag to constraint_set5_flag, and AVC_compatible_flags.
+  pub constraint_flags: u8,
+  pub level_idc: u8,
+  pub still_present: bool,
+  pub picture_24_hour: bool,
+  pub frame_packing_sei_not_present: bool,
+}
+const AVC_VIDEO_DESC_TAG: u8 = 40; // ISO/IEC 13818-1 2.6.64
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Mpeg2AacAudioDesc {
+  pub profile: u8,
+  pub channel_configuration: u8,
+  pub additional_information: u8,
+}
+const MPEG2_AAC_AUDIO_DESC_TAG: u8 = 43; // ISO/IEC 13818-1 2.6.68
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HevcVideoDesc {
+  pub profile_space: u8,
+  pub tier: bool,
+  pub profile_idc: u8,
+  pub profile_compatibility_indication: u32,
+  pub progressive_source: bool,
+  pub interlaced_source: bool,
+  pub non_packed_constraint: bool,
+  pub frame_only_constraint: bool,
+  pub level_idc: u8,
+  pub still_present: bool,
+  pub picture_24_hour: bool,
+  pub sub_pic_hrd_params_not_present: bool,
+  pub hdr_wcg_idc: u8,
+  /// temporal_id_min and temporal_id_max, if temporal_layer_subset_flag is
+  /// set.
+  pub temporal_id_range: Option<(u8, u8)>,
+}
+const HEVC_VIDEO_DESC_TAG: u8 = 56; // ISO/IEC 13818-1 2.6.95
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ExtensionDesc {
+  pub extension_desc_tag: u8,
+  pub data: Vec<u8>,
+}
+const EXTENSION_DESC_TAG: u8 = 63; // ISO/IEC 13818-1 2.6.90
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StreamIdentifierDesc {
+  pub component_tag: u8,
+}
+const STREAM_IDENTIFIER_DESC_TAG: u8 = 0x52; // ETSI EN 300 468 6.2.39
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SubtitlingDesc {
+  pub subtitles: Vec<Subtitling>,
+}
+const SUBTITLING_DESC_TAG: u8 = 0x59; // ETSI EN 300 468 6.2.41
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Subtitling {
+  pub language: String,
+  pub subtitling_type: u8,
+  pub composition_page_id: u16,
+  pub ancillary_page_id: u16,
+}
+
+/// Used by both the teletext and the VBI teletext descriptors.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TeletextDesc {
+  pub pages: Vec<TeletextPage>,
+}
+const TELETEXT_DESC_TAG: u8 = 0x56; // ETSI EN 300 468 6.2.43
+const VBI_TELETEXT_DESC_TAG: u8 = 0x46; // ETSI EN 300 468 6.2.48
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TeletextPage {
+  pub language: String,
+  /// 1: initial page, 2: subtitle page, 3: additional information page, 4:
+  /// programme schedule page, 5: subtitle page for hearing impaired.
+  pub teletext_type: u8,
+  pub magazine_number: u8,
+  /// Two BCD digits.
+  pub page_number: u8,
+}
+
+impl TeletextPage {
+  /// Returns the page number as usually displayed, e.g. 888. Magazine 0 is
+  /// magazine 8.
+  pub fn page(&self) -> u16 {
+    let magazine = if self.magazine_number == 0 {
+      8
+    } else {
+      self.magazine_number as u16
+    };
+    magazine * 100
+      + self.page_number.bits(7..=4) as u16 * 10
+      + self.page_number.bits(3..=0) as u16
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ComponentDesc {
+  pub stream_content_ext: u8,
+  pub stream_content: u8,
+  pub component_type: u8,
+  pub component_tag: u8,
+  pub language: String,
+  /// The text, still encoded with the DVB character tables.
+  pub text: Vec<u8>,
+}
+const COMPONENT_DESC_TAG: u8 = 0x50; // ETSI EN 300 468 6.2.8
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ServiceDesc {
+  pub service_type: u8,
+  /// The names, still encoded with the DVB character tables.
+  pub provider_name: Vec<u8>,
+  pub service_name: Vec<u8>,
+}
+const SERVICE_DESC_TAG: u8 = 0x48; // ETSI EN 300 468 6.2.33
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AacDesc {
+  pub profile_and_level: u8,
+  pub saoc_de: bool,
+  pub aac_type: Option<u8>,
+  pub additional_info: Vec<u8>,
+}
+const AAC_DESC_TAG: u8 = 0x7c; // ETSI EN 300 468 Annex H (H.2)
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DtsDesc {
+  pub sample_rate_code: u8,
+  pub bit_rate_code: u8,
+  pub nblks: u8,
+  pub fsize: u16,
+  pub surround_mode: u8,
+  pub lfe: bool,
+  pub extended_surround: u8,
+  pub additional_info: Vec<u8>,
+}
+const DTS_DESC_TAG: u8 = 0x7b; // ETSI EN 300 468 Annex G (G.2)
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SupplementaryAudioDesc {
+  /// Whether the stream is a complete and independent stream, rather than a
+  /// supplementary stream to be mixed with the main audio.
+  pub mix_type: bool,
+  pub editorial_classification: u8,
+  pub language: Option<String>,
+  pub private_data: Vec<u8>,
+}
+
+// DVB extension descriptor (ETSI EN 300 468 6.2.16), and the
+// descriptor_tag_extension of the supplementary_audio_descriptor (6.4.11).
+const DVB_EXTENSION_DESC_TAG: u8 = 0x7f;
+const SUPPLEMENTARY_AUDIO_DESC_TAG_EXT: u8 = 0x06;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct NetworkNameDesc {
+  /// The name, still encoded with the DVB character tables.
+  pub name: Vec<u8>,
+}
+const NETWORK_NAME_DESC_TAG: u8 = 0x40; // ETSI EN 300 468 6.2.27
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ServiceListDesc {
+  /// The service_id and service_type of each service.
+  pub services: Vec<(u16, u8)>,
+}
+const SERVICE_LIST_DESC_TAG: u8 = 0x41; // ETSI EN 300 468 6.2.35
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SatelliteDeliveryDesc {
+  /// In units of 10 kHz.
+  pub frequency: u32,
+  /// In units of 0.1 degrees.
+  pub orbital_position: u16,
+  /// Whether `orbital_position` is east, rather than west.
+  pub east: bool,
+  pub polarization: u8,
+  pub roll_off: u8,
+  /// Whether the modulation system is DVB-S2, rather than DVB-S.
+  pub s2: bool,
+  pub modulation_type: u8,
+  /// In units of 100 symbols/second.
+  pub symbol_rate: u32,
+  pub fec_inner: u8,
+}
+const SATELLITE_DELIVERY_DESC_TAG: u8 = 0x43; // ETSI EN 300 468 6.2.13.2
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CableDeliveryDesc {
+  /// In units of 100 Hz.
+  pub frequency: u32,
+  pub fec_outer: u8,
+  pub modulation: u8,
+  /// In units of 100 symbols/second.
+  pub symbol_rate: u32,
+  pub fec_inner: u8,
+}
+const CABLE_DELIVERY_DESC_TAG: u8 = 0x44; // ETSI EN 300 468 6.2.13.1
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TerrestrialDeliveryDesc {
+  /// In units of 10 Hz.
+  pub centre_frequency: u32,
+  pub bandwidth: u8,
+  pub priority: bool,
+  pub time_slicing: bool,
+  pub mpe_fec: bool,
+  pub constellation: u8,
+  pub hierarchy_information: u8,
+  pub code_rate_hp: u8,
+  pub code_rate_lp: u8,
+  pub guard_interval: u8,
+  pub transmission_mode: u8,
+  pub other_frequency: bool,
+}
+const TERRESTRIAL_DELIVERY_DESC_TAG: u8 = 0x5a; // ETSI EN 300 468 6.2.13.4
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LogicalChannelDesc {
+  pub channels: Vec<LogicalChannel>,
+}
+// User private descriptor, used by most European DVB-T and DVB-C networks
+// (IEC 62216, NorDig, D-Book).
+const LOGICAL_CHANNEL_DESC_TAG: u8 = 0x83;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LogicalChannel {
+  pub service_id: u16,
+  pub visible: bool,
+  pub logical_channel_number: u16,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ShortEventDesc {
+  pub language: String,
+  /// The name and text, still encoded with the DVB character tables.
+  pub event_name: Vec<u8>,
+  pub text: Vec<u8>,
+}
+const SHORT_EVENT_DESC_TAG: u8 = 0x4d; // ETSI EN 300 468 6.2.37
+
+/// A part of the extended description of an event. Long descriptions are
+/// split across several descriptors, numbered from 0 to
+/// `last_descriptor_number`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ExtendedEventDesc {
+  pub descriptor_number: u8,
+  pub last_descriptor_number: u8,
+  pub language: String,
+  /// Item descriptions and items (e.g. "Director", "Name"), still encoded
+  /// with the DVB character tables.
+  pub items: Vec<(Vec<u8>, Vec<u8>)>,
+  pub text: Vec<u8>,
+}
+const EXTENDED_EVENT_DESC_TAG: u8 = 0x4e; // ETSI EN 300 468 6.2.15
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ContentDesc {
+  pub contents: Vec<Content>,
+}
+const CONTENT_DESC_TAG: u8 = 0x54; // ETSI EN 300 468 6.2.9
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Content {
+  /// content_nibble_level_1 and content_nibble_level_2 (ETSI EN 300 468 Table
+  /// 29), e.g. 0x10 for movie/drama.
+  pub content_nibbles: u8,
+  pub user_byte: u8,
+}
+
+impl Content {
+  /// Returns the name of the genre of the content_nibble_level_1, if defined.
+  pub fn genre(&self) -> Option<&'static str> {
+    match self.content_nibbles.bits(7..=4) {
+      0x1 => Some("Movie/Drama"),
+      0x2 => Some("News/Current affairs"),
+      0x3 => Some("Show/Game show"),
+      0x4 => Some("Sports"),
+      0x5 => Some("Children's/Youth programmes"),
+      0x6 => Some("Music/Ballet/Dance"),
+      0x7 => Some("Arts/Culture (without music)"),
+      0x8 => Some("Social/Political issues/Economics"),
+      0x9 => Some("Education/Science/Factual topics"),
+      0xA => Some("Leisure hobbies"),
+      0xB => Some("Special characteristics"),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LocalTimeOffsetDesc {
+  pub offsets: Vec<LocalTimeOffset>,
+}
+const LOCAL_TIME_OFFSET_DESC_TAG: u8 = 0x58; // ETSI EN 300 468 6.2.20
+
+/// The offset from UTC of the local time of a country, or of a region of it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LocalTimeOffset {
+  /// ISO 3166 alpha-3 country code.
+  pub country_code: String,
+  pub country_region_id: u8,
+  /// The current offset, in seconds.
+  pub local_time_offset: i32,
+  /// The UTC time, in seconds since the Unix epoch, at which the offset
+  /// changes to `next_time_offset`.
+  pub time_of_change: Option<i64>,
+  pub next_time_offset: i32,
+}
+
+/// The AC-3 component_type as specified in ETSI EN 300 468 Table D.1.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Ac3ComponentType(pub u8);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Ac3Channels {
+  Mono,
+  DualMono,
+  Stereo,
+  DolbySurroundStereo,
+  /// More than 2 channels, up to 5.1.
+  Multichannel,
+  /// More than 5.1 channels. Only used by Enhanced AC-3.
+  MultichannelOver5_1,
+  Reserved,
+}
+
+impl Ac3ComponentType {
+  /// Whether the stream is Enhanced AC-3.
+  pub fn enhanced(&self) -> bool {
+    self.0.bit(7)
+  }
+
+  /// Whether the stream is a full service, suitable for presentation on its
+  /// own.
+  pub fn full_service(&self) -> bool {
+    self.0.bit(6)
+  }
+
+  /// The service_type, e.g. 0 for complete main, 2 for visually impaired.
+  pub fn service_type(&self) -> u8 {
+    self.0.bits(5..=3)
+  }
+
+  pub fn channels(&self) -> Ac3Channels {
+    match self.0.bits(2..=0) {
+      0b000 => Ac3Channels::Mono,
+      0b001 => Ac3Channels::DualMono,
+      0b010 => Ac3Channels::Stereo,
+      0b011 => Ac3Channels::DolbySurroundStereo,
+      0b100 => Ac3Channels::Multichannel,
+      0b101 => Ac3Channels::MultichannelOver5_1,
+      _ => Ac3Channels::Reserved,
+    }
+  }
+}
+
+/// Splits a loop of descriptors, as found in the PMT, where each descriptor is
+/// made of descriptor_tag, descriptor_length and the descriptor data. Returns
+/// the tag and data of each descriptor, or `None` if a descriptor overruns the
+/// loop.
+pub fn split_descs(buf: &[u8]) -> Option<Vec<(u8, &[u8])>> {
+  let mut buf = buf;
+  let mut descs = Vec::new();
+  while buf.len() >= 2 {
+    let desc_tag = buf.get_u8();
+    let desc_len = buf.get_u8() as usize;
+    if desc_len > buf.len() {
+      return None;
+    }
+    descs.push((desc_tag, &buf[..desc_len]));
+    buf.advance(desc_len);
+  }
+  Some(descs)
+}
+
+/// Parses a loop of descriptors. Every descriptor in the loop yields exactly
+/// one `StreamDesc`, in order, so that the result can be matched with the
+/// output of `split_descs`.
+pub fn parse_descs(buf: &[u8]) -> Option<Vec<StreamDesc>> {
+  let descs = split_descs(buf)?
+    .into_iter()
+    .map(|(tag, data)| {
+      parse_stream_desc(tag, data).unwrap_or_else(|| StreamDesc::Unknown {
+        tag,
+        data: data.to_vec(),
+      })
+    })
+    .collect();
+  Some(descs)
+}
+
 pub fn parse_stream_desc(tag: u8, buf: &[u8]) -> Option<StreamDesc> {
   let mut buf = buf;
 
@@ -57,10 +630,1505 @@ pub fn parse_stream_desc(tag: u8, buf: &[u8]) -> Option<StreamDesc> {
       Some(StreamDesc::Metadata(metadata_desc))
     }
 
-    AC3_DESCRIPTOR_TAG => Some(StreamDesc::Ac3(Ac3Desc {})),
+    AC3_DESCRIPTOR_TAG => parse_ac3_desc(buf).map(StreamDesc::Ac3),
+
+    EAC3_DESCRIPTOR_TAG => parse_eac3_desc(buf).map(StreamDesc::Eac3),
+
+    CA_DESC_TAG => {
+      if buf.len() < 4 {
+        return None;
+      }
+      let ca_system_id = buf.get_u16();
+      let ca_pid = buf.get_u16().bits(12..=0);
+      Some(StreamDesc::Ca(CaDesc {
+        ca_system_id,
+        ca_pid,
+        private_data: buf.to_vec(),
+      }))
+    }
+
+    VIDEO_STREAM_DESC_TAG => parse_video_stream_desc(buf),
+
+    AUDIO_STREAM_DESC_TAG => {
+      if buf.is_empty() {
+        return None;
+      }
+      let b = buf.get_u8();
+      Some(StreamDesc::AudioStream(AudioStreamDesc {
+        free_format: b.bit(7),
+        id: b.bits(6..=6),
+        layer: b.bits(5..=4),
+        variable_rate_audio: b.bit(3),
+      }))
+    }
+
+    HIERARCHY_DESC_TAG => parse_hierarchy_desc(buf),
+
+    DATA_STREAM_ALIGNMENT_DESC_TAG => {
+      if buf.is_empty() {
+        return None;
+      }
+      Some(StreamDesc::DataStreamAlignment(DataStreamAlignmentDesc {
+        alignment_type: buf.get_u8(),
+      }))
+    }
+
+    ISO_639_LANGUAGE_DESC_TAG => {
+      let languages = buf
+        .chunks_exact(4)
+        .map(|mut entry| Iso639Language {
+          language: get_language(&mut entry),
+          audio_type: entry.get_u8(),
+        })
+        .collect();
+      Some(StreamDesc::Iso639Language(Iso639LanguageDesc { languages }))
+    }
+
+    MAXIMUM_BITRATE_DESC_TAG => {
+      if buf.len() < 3 {
+        return None;
+      }
+      Some(StreamDesc::MaximumBitrate(MaximumBitrateDesc {
+        maximum_bitrate: get_u22(&mut buf),
+      }))
+    }
+
+    MPEG4_AUDIO_DESC_TAG => {
+      if buf.is_empty() {
+        return None;
+      }
+      Some(StreamDesc::Mpeg4Audio(Mpeg4AudioDesc {
+        profile_and_level: buf.get_u8(),
+      }))
+    }
+
+    METADATA_POINTER_DESC_TAG => parse_metadata_pointer_desc(buf),
+
+    METADATA_STD_DESC_TAG => {
+      if buf.len() < 9 {
+        return None;
+      }
+      Some(StreamDesc::MetadataStd(MetadataStdDesc {
+        input_leak_rate: get_u22(&mut buf),
+        buffer_size: get_u22(&mut buf),
+        output_leak_rate: get_u22(&mut buf),
+      }))
+    }
+
+    AVC_VIDEO_DESC_TAG => {
+      if buf.len() < 4 {
+        return None;
+      }
+      let profile_idc = buf.get_u8();
+      let constraint_flags = buf.get_u8();
+      let level_idc = buf.get_u8();
+      let b = buf.get_u8();
+      Some(StreamDesc::AvcVideo(AvcVideoDesc {
+        profile_idc,
+        constraint_flags,
+        level_idc,
+        still_present: b.bit(7),
+        picture_24_hour: b.bit(6),
+        frame_packing_sei_not_present: b.bit(5),
+      }))
+    }
+
+    MPEG2_AAC_AUDIO_DESC_TAG => {
+      if buf.len() < 3 {
+        return None;
+      }
+      Some(StreamDesc::Mpeg2AacAudio(Mpeg2AacAudioDesc {
+        profile: buf.get_u8(),
+        channel_configuration: buf.get_u8(),
+        additional_information: buf.get_u8(),
+      }))
+    }
+
+    HEVC_VIDEO_DESC_TAG => parse_hevc_video_desc(buf),
+
+    EXTENSION_DESC_TAG => {
+      if buf.is_empty() {
+        return None;
+      }
+      Some(StreamDesc::Extension(ExtensionDesc {
+        extension_desc_tag: buf.get_u8(),
+        data: buf.to_vec(),
+      }))
+    }
+
+    STREAM_IDENTIFIER_DESC_TAG => {
+      if buf.is_empty() {
+        return None;
+      }
+      Some(StreamDesc::StreamIdentifier(StreamIdentifierDesc {
+        component_tag: buf.get_u8(),
+      }))
+    }
+
+    SUBTITLING_DESC_TAG => {
+      let subtitles = buf
+        .chunks_exact(8)
+        .map(|mut entry| Subtitling {
+          language: get_language(&mut entry),
+          subtitling_type: entry.get_u8(),
+          composition_page_id: entry.get_u16(),
+          ancillary_page_id: entry.get_u16(),
+        })
+        .collect();
+      Some(StreamDesc::Subtitling(SubtitlingDesc { subtitles }))
+    }
 
-    EAC3_DESCRIPTOR_TAG => Some(StreamDesc::Eac3(Eac3Desc {})),
+    TELETEXT_DESC_TAG => Some(StreamDesc::Teletext(parse_teletext_desc(buf))),
+
+    VBI_TELETEXT_DESC_TAG => {
+      Some(StreamDesc::VbiTeletext(parse_teletext_desc(buf)))
+    }
+
+    COMPONENT_DESC_TAG => {
+      if buf.len() < 6 {
+        return None;
+      }
+      let b = buf.get_u8();
+      let component_type = buf.get_u8();
+      let component_tag = buf.get_u8();
+      Some(StreamDesc::Component(ComponentDesc {
+        stream_content_ext: b.bits(7..=4),
+        stream_content: b.bits(3..=0),
+        component_type,
+        component_tag,
+        language: get_language(&mut buf),
+        text: buf.to_vec(),
+      }))
+    }
+
+    SERVICE_DESC_TAG => {
+      if buf.is_empty() {
+        return None;
+      }
+      let service_type = buf.get_u8();
+      let provider_name = get_length_prefixed(&mut buf)?;
+      let service_name = get_length_prefixed(&mut buf)?;
+      Some(StreamDesc::Service(ServiceDesc {
+        service_type,
+        provider_name,
+        service_name,
+      }))
+    }
+
+    AAC_DESC_TAG => {
+      if buf.is_empty() {
+        return None;
+      }
+      let mut desc = AacDesc {
+        profile_and_level: buf.get_u8(),
+        saoc_de: false,
+        aac_type: None,
+        additional_info: Vec::new(),
+      };
+      // The descriptor may stop after profile_and_level.
+      if !buf.is_empty() {
+        let b = buf.get_u8();
+        desc.saoc_de = b.bit(6);
+        desc.aac_type = get_opt_u8(&mut buf, b.bit(7))?;
+        desc.additional_info = buf.to_vec();
+      }
+      Some(StreamDesc::Aac(desc))
+    }
+
+    DTS_DESC_TAG => parse_dts_desc(buf),
+
+    DVB_EXTENSION_DESC_TAG => {
+      if buf.is_empty() {
+        return None;
+      }
+      match buf.get_u8() {
+        SUPPLEMENTARY_AUDIO_DESC_TAG_EXT => parse_supplementary_audio_desc(buf),
+        _ => None,
+      }
+    }
+
+    NETWORK_NAME_DESC_TAG => Some(StreamDesc::NetworkName(NetworkNameDesc {
+      name: buf.to_vec(),
+    })),
+
+    SERVICE_LIST_DESC_TAG => {
+      let services = buf
+        .chunks_exact(3)
+        .map(|mut entry| (entry.get_u16(), entry.get_u8()))
+        .collect();
+      Some(StreamDesc::ServiceList(ServiceListDesc { services }))
+    }
+
+    SATELLITE_DELIVERY_DESC_TAG => parse_satellite_delivery_desc(buf),
+
+    CABLE_DELIVERY_DESC_TAG => {
+      if buf.len() < 11 {
+        return None;
+      }
+      let frequency = bcd(buf.get_u32());
+      let fec_outer = buf.get_u16().bits(3..=0) as u8;
+      let modulation = buf.get_u8();
+      let b = buf.get_u32();
+      Some(StreamDesc::CableDelivery(CableDeliveryDesc {
+        frequency,
+        fec_outer,
+        modulation,
+        symbol_rate: bcd(b.bits(31..=4)),
+        fec_inner: b.bits(3..=0) as u8,
+      }))
+    }
+
+    TERRESTRIAL_DELIVERY_DESC_TAG => parse_terrestrial_delivery_desc(buf),
+
+    LOGICAL_CHANNEL_DESC_TAG => {
+      if !buf.len().is_multiple_of(4) {
+        return None;
+      }
+      let channels = buf
+        .chunks_exact(4)
+        .map(|mut entry| {
+          let service_id = entry.get_u16();
+          let b = entry.get_u16();
+          LogicalChannel {
+            service_id,
+            visible: b.bit(15),
+            logical_channel_number: b.bits(9..=0),
+          }
+        })
+        .collect();
+      Some(StreamDesc::LogicalChannel(LogicalChannelDesc { channels }))
+    }
+
+    SHORT_EVENT_DESC_TAG => {
+      if buf.len() < 3 {
+        return None;
+      }
+      let language = get_language(&mut buf);
+      let event_name = get_length_prefixed(&mut buf)?;
+      let text = get_length_prefixed(&mut buf)?;
+      Some(StreamDesc::ShortEvent(ShortEventDesc {
+        language,
+        event_name,
+        text,
+      }))
+    }
+
+    EXTENDED_EVENT_DESC_TAG => parse_extended_event_desc(buf),
+
+    CONTENT_DESC_TAG => {
+      let contents = buf
+        .chunks_exact(2)
+        .map(|entry| Content {
+          content_nibbles: entry[0],
+          user_byte: entry[1],
+        })
+        .collect();
+      Some(StreamDesc::Content(ContentDesc { contents }))
+    }
+
+    LOCAL_TIME_OFFSET_DESC_TAG => {
+      if !buf.len().is_multiple_of(13) {
+        return None;
+      }
+      let offsets = buf
+        .chunks_exact(13)
+        .map(|mut entry| parse_local_time_offset(&mut entry))
+        .collect();
+      Some(StreamDesc::LocalTimeOffset(LocalTimeOffsetDesc { offsets }))
+    }
 
     _ => None,
   }
 }
+
+// Reads a byte if its flag is set. Returns `None` if the flag is set but the
+// byte is missing.
+fn get_opt_u8(buf: &mut &[u8], flag: bool) -> Option<Option<u8>> {
+  if !flag {
+    return Some(None);
+  }
+  if buf.is_empty() {
+    return None;
+  }
+  Some(Some(buf.get_u8()))
+}
+
+// ETSI EN 300 468 Annex D (D.3)
+//
+// 76543210
+// abcd....
+//
+// a: component_type_flag
+// b: bsid_flag
+// c: mainid_flag
+// d: asvc_flag
+fn parse_ac3_desc(buf: &[u8]) -> Option<Ac3Desc> {
+  let mut buf = buf;
+  if buf.is_empty() {
+    return None;
+  }
+  let flags = buf.get_u8();
+  let component_type = get_opt_u8(&mut buf, flags.bit(7))?;
+  let bsid = get_opt_u8(&mut buf, flags.bit(6))?;
+  let mainid = get_opt_u8(&mut buf, flags.bit(5))?;
+  let asvc = get_opt_u8(&mut buf, flags.bit(4))?;
+  Some(Ac3Desc {
+    component_type: component_type.map(Ac3ComponentType),
+    bsid,
+    mainid,
+    asvc,
+    additional_info: buf.to_vec(),
+  })
+}
+
+// ETSI EN 300 468 Annex D (D.5)
+//
+// 76543210
+// abcdefgh
+//
+// a: component_type_flag
+// b: bsid_flag
+// c: mainid_flag
+// d: asvc_flag
+// e: mixinfoexists
+// f: substream1_flag
+// g: substream2_flag
+// h: substream3_flag
+fn parse_eac3_desc(buf: &[u8]) -> Option<Eac3Desc> {
+  let mut buf = buf;
+  if buf.is_empty() {
+    return None;
+  }
+  let flags = buf.get_u8();
+  let component_type = get_opt_u8(&mut buf, flags.bit(7))?;
+  let bsid = get_opt_u8(&mut buf, flags.bit(6))?;
+  let mainid = get_opt_u8(&mut buf, flags.bit(5))?;
+  let asvc = get_opt_u8(&mut buf, flags.bit(4))?;
+  let substream1 = get_opt_u8(&mut buf, flags.bit(2))?;
+  let substream2 = get_opt_u8(&mut buf, flags.bit(1))?;
+  let substream3 = get_opt_u8(&mut buf, flags.bit(0))?;
+  Some(Eac3Desc {
+    component_type: component_type.map(Ac3ComponentType),
+    bsid,
+    mainid,
+    asvc,
+    mixinfoexists: flags.bit(3),
+    substream1: substream1.map(Ac3ComponentType),
+    substream2: substream2.map(Ac3ComponentType),
+    substream3: substream3.map(Ac3ComponentType),
+    additional_info: buf.to_vec(),
+  })
+}
+
+// Reads a 22-bit value preceded by 2 reserved bits.
+fn get_u22(buf: &mut &[u8]) -> u32 {
+  (buf.get_uint(3) as u32).bits(21..=0)
+}
+
+// ISO/IEC 13818-1 2.6.2
+//
+// 76543210
+// abbbbcde
+//
+// a: multiple_frame_rate_flag
+// b: frame_rate_code
+// c: MPEG_1_only_flag
+// d: constrained_parameter_flag
+// e: still_picture_flag
+//
+// If MPEG_1_only_flag is 0:
+//
+//  1          0
+// 54321098 76543210
+// pppppppp ccf.....
+//
+// p: profile_and_level_indication
+// c: chroma_format
+// f: frame_rate_extension_flag
+fn parse_video_stream_desc(buf: &[u8]) -> Option<StreamDesc> {
+  let mut buf = buf;
+  if buf.is_empty() {
+    return None;
+  }
+  let b = buf.get_u8();
+  let mut desc = VideoStreamDesc {
+    multiple_frame_rate: b.bit(7),
+    frame_rate_code: b.bits(6..=3),
+    mpeg1_only: b.bit(2),
+    constrained_parameter: b.bit(1),
+    still_picture: b.bit(0),
+    profile_and_level_indication: None,
+    chroma_format: None,
+    frame_rate_extension: None,
+  };
+  if !desc.mpeg1_only {
+    if buf.len() < 2 {
+      return None;
+    }
+    desc.profile_and_level_indication = Some(buf.get_u8());
+    let b = buf.get_u8();
+    desc.chroma_format = Some(b.bits(7..=6));
+    desc.frame_rate_extension = Some(b.bit(5));
+  }
+  Some(StreamDesc::VideoStream(desc))
+}
+
+// ISO/IEC 13818-1 2.6.6
+//
+//  3          2          1          0
+// 10987654 32109876 54321098 76543210
+// abcdtttt ..llllll r.eeeeee ..hhhhhh
+//
+// a: no_view_scalability_flag
+// b: no_temporal_scalability_flag
+// c: no_spatial_scalability_flag
+// d: no_quality_scalability_flag
+// t: hierarchy_type
+// l: hierarchy_layer_index
+// r: tref_present_flag
+// e: hierarchy_embedded_layer_index
+// h: hierarchy_channel
+fn parse_hierarchy_desc(buf: &[u8]) -> Option<StreamDesc> {
+  let mut buf = buf;
+  if buf.len() < 4 {
+    return None;
+  }
+  let h = buf.get_u32();
+  Some(StreamDesc::Hierarchy(HierarchyDesc {
+    no_view_scalability: h.bit(31),
+    no_temporal_scalability: h.bit(30),
+    no_spatial_scalability: h.bit(29),
+    no_quality_scalability: h.bit(28),
+    hierarchy_type: h.bits(27..=24) as u8,
+    layer_index: h.bits(21..=16) as u8,
+    tref_present: h.bit(15),
+    embedded_layer_index: h.bits(13..=8) as u8,
+    channel: h.bits(5..=0) as u8,
+  }))
+}
+
+// ISO/IEC 13818-1 2.6.58
+fn parse_metadata_pointer_desc(buf: &[u8]) -> Option<StreamDesc> {
+  let mut buf = buf;
+  if buf.len() < 2 {
+    return None;
+  }
+  let metadata_application_format = buf.get_u16();
+  let mut app_format_id = None;
+  if metadata_application_format == 0xffff {
+    if buf.len() < 4 {
+      return None;
+    }
+    app_format_id = Some(buf.get_u32());
+  }
+
+  if buf.is_empty() {
+    return None;
+  }
+  let metadata_format = buf.get_u8();
+  let mut format_id = None;
+  if metadata_format == 0xff {
+    if buf.len() < 4 {
+      return None;
+    }
+    format_id = Some(buf.get_u32());
+  }
+
+  //  1          0
+  // 54321098 76543210
+  // ssssssss lcc.....
+  //
+  // s: metadata_service_id
+  // l: metadata_locator_record_flag
+  // c: MPEG_carriage_flags
+  if buf.len() < 2 {
+    return None;
+  }
+  let metadata_service_id = buf.get_u8();
+  let b = buf.get_u8();
+  let mpeg_carriage = b.bits(6..=5);
+
+  let mut metadata_locator = None;
+  if b.bit(7) {
+    if buf.is_empty() {
+      return None;
+    }
+    let len = buf.get_u8() as usize;
+    if buf.len() < len {
+      return None;
+    }
+    metadata_locator = Some(buf[..len].to_vec());
+    buf.advance(len);
+  }
+
+  // MPEG_carriage_flags 0: same transport stream, 1: another transport stream,
+  // 2: a program stream, 3: not carried by MPEG-2 systems.
+  let mut program_number = None;
+  let mut transport_stream_location = None;
+  let mut transport_stream_id = None;
+  if mpeg_carriage <= 2 {
+    if buf.len() < 2 {
+      return None;
+    }
+    program_number = Some(buf.get_u16());
+  }
+  if mpeg_carriage == 1 {
+    if buf.len() < 4 {
+      return None;
+    }
+    transport_stream_location = Some(buf.get_u16());
+    transport_stream_id = Some(buf.get_u16());
+  }
+
+  Some(StreamDesc::MetadataPointer(MetadataPointerDesc {
+    metadata_application_format,
+    app_format_id,
+    metadata_format,
+    format_id,
+    metadata_service_id,
+    metadata_locator,
+    mpeg_carriage,
+    program_number,
+    transport_stream_location,
+    transport_stream_id,
+    private_data: buf.to_vec(),
+  }))
+}
+
+// ISO/IEC 13818-1 2.6.95
+//
+//  0
+// 76543210
+// sstppppp
+//
+// s: profile_space
+// t: tier_flag
+// p: profile_idc
+//
+// Followed by profile_compatibility_indication (32 bits), then:
+//
+//  5          4          3          2          1          0
+// 76543210 98765432 10987654 32109876 54321098 76543210
+// abcd.... ........ ........ ........ ........ ........
+//
+// a: progressive_source_flag
+// b: interlaced_source_flag
+// c: non_packed_constraint_flag
+// d: frame_only_constraint_flag
+//
+// Followed by level_idc (8 bits), then:
+//
+// 76543210
+// abcd..hh
+//
+// a: temporal_layer_subset_flag
+// b: HEVC_still_present_flag
+// c: HEVC_24hr_picture_present_flag
+// d: sub_pic_hrd_params_not_present_flag
+// h: HDR_WCG_idc
+fn parse_hevc_video_desc(buf: &[u8]) -> Option<StreamDesc> {
+  let mut buf = buf;
+  if buf.len() < 13 {
+    return None;
+  }
+  let b = buf.get_u8();
+  let profile_compatibility_indication = buf.get_u32();
+  let constraints = buf.get_uint(6);
+  let level_idc = buf.get_u8();
+  let flags = buf.get_u8();
+
+  let mut temporal_id_range = None;
+  if flags.bit(7) {
+    if buf.len() < 2 {
+      return None;
+    }
+    let min = buf.get_u8().bits(7..=5);
+    let max = buf.get_u8().bits(7..=5);
+    temporal_id_range = Some((min, max));
+  }
+
+  Some(StreamDesc::HevcVideo(HevcVideoDesc {
+    profile_space: b.bits(7..=6),
+    tier: b.bit(5),
+    profile_idc: b.bits(4..=0),
+    profile_compatibility_indication,
+    progressive_source: constraints.bit(47),
+    interlaced_source: constraints.bit(46),
+    non_packed_constraint: constraints.bit(45),
+    frame_only_constraint: constraints.bit(44),
+    level_idc,
+    still_present: flags.bit(6),
+    picture_24_hour: flags.bit(5),
+    sub_pic_hrd_params_not_present: flags.bit(4),
+    hdr_wcg_idc: flags.bits(1..=0),
+    temporal_id_range,
+  }))
+}
+
+// Reads a 3 character ISO 639-2 language code.
+fn get_language(buf: &mut &[u8]) -> String {
+  let language = buf[..3].iter().map(|&b| b as char).collect();
+  buf.advance(3);
+  language
+}
+
+// Reads bytes preceded by an 8-bit length.
+fn get_length_prefixed(buf: &mut &[u8]) -> Option<Vec<u8>> {
+  if buf.is_empty() {
+    return None;
+  }
+  let len = buf.get_u8() as usize;
+  if buf.len() < len {
+    return None;
+  }
+  let data = buf[..len].to_vec();
+  buf.advance(len);
+  Some(data)
+}
+
+// ETSI EN 300 468 6.2.43
+//
+//  4          3          2          1          0
+// 98765432 10987654 32109876 54321098 76543210
+// llllllll llllllll llllllll tttttmmm pppppppp
+//
+// l: ISO_639_language_code
+// t: teletext_type
+// m: teletext_magazine_number
+// p: teletext_page_number
+fn parse_teletext_desc(buf: &[u8]) -> TeletextDesc {
+  let pages = buf
+    .chunks_exact(5)
+    .map(|mut entry| {
+      let language = get_language(&mut entry);
+      let b = entry.get_u8();
+      TeletextPage {
+        language,
+        teletext_type: b.bits(7..=3),
+        magazine_number: b.bits(2..=0),
+        page_number: entry.get_u8(),
+      }
+    })
+    .collect();
+  TeletextDesc { pages }
+}
+
+// ETSI EN 300 468 Annex G (G.2)
+//
+//  4          3          2          1          0
+// 98765432 10987654 32109876 54321098 76543210
+// ssssbbbb bbnnnnnn nfffffff fffffffm mmmmmlee
+//
+// s: sample_rate_code
+// b: bit_rate_code
+// n: nblks
+// f: fsize
+// m: surround_mode
+// l: lfe_flag
+// e: extended_surround_flag
+fn parse_dts_desc(buf: &[u8]) -> Option<StreamDesc> {
+  let mut buf = buf;
+  if buf.len() < 5 {
+    return None;
+  }
+  let d = buf.get_uint(5);
+  Some(StreamDesc::Dts(DtsDesc {
+    sample_rate_code: d.bits(39..=36) as u8,
+    bit_rate_code: d.bits(35..=30) as u8,
+    nblks: d.bits(29..=23) as u8,
+    fsize: d.bits(22..=9) as u16,
+    surround_mode: d.bits(8..=3) as u8,
+    lfe: d.bit(2),
+    extended_surround: d.bits(1..=0) as u8,
+    additional_info: buf.to_vec(),
+  }))
+}
+
+// ETSI EN 300 468 6.4.11
+//
+// 76543210
+// meeeee.l
+//
+// m: mix_type
+// e: editorial_classification
+// l: language_code_present
+fn parse_supplementary_audio_desc(buf: &[u8]) -> Option<StreamDesc> {
+  let mut buf = buf;
+  if buf.is_empty() {
+    return None;
+  }
+  let b = buf.get_u8();
+  let mut language = None;
+  if b.bit(0) {
+    if buf.len() < 3 {
+      return None;
+    }
+    language = Some(get_language(&mut buf));
+  }
+  Some(StreamDesc::SupplementaryAudio(SupplementaryAudioDesc {
+    mix_type: b.bit(7),
+    editorial_classification: b.bits(6..=2),
+    language,
+    private_data: buf.to_vec(),
+  }))
+}
+
+// ETSI EN 300 468 6.2.20
+//
+// Following country_code (24 bits):
+//
+//  1          0
+// 54321098 76543210
+// rrrrrr.p oooooooo oooooooo [time_of_change (40 bits)] nnnnnnnn nnnnnnnn
+//
+// r: country_region_id
+// p: local_time_offset_polarity (1 for west of Greenwich)
+// o: local_time_offset (4 BCD digits, hhmm)
+// n: next_time_offset (4 BCD digits, hhmm)
+fn parse_local_time_offset(buf: &mut &[u8]) -> LocalTimeOffset {
+  let country_code = get_language(buf);
+  let b = buf.get_u8();
+  let sign = if b.bit(0) { -1 } else { 1 };
+  let local_time_offset = sign * bcd_offset(buf.get_u16());
+  let time_of_change = dvb_time::utc_time(buf.get_uint(5));
+  let next_time_offset = sign * bcd_offset(buf.get_u16());
+  LocalTimeOffset {
+    country_code,
+    country_region_id: b.bits(7..=2),
+    local_time_offset,
+    time_of_change,
+    next_time_offset,
+  }
+}
+
+// Decodes an offset in hours and minutes, coded in 4 BCD digits, to seconds.
+fn bcd_offset(v: u16) -> i32 {
+  let hours = bcd(v.bits(15..=8) as u32) as i32;
+  let minutes = bcd(v.bits(7..=0) as u32) as i32;
+  hours * 3600 + minutes * 60
+}
+
+// Decodes a binary-coded decimal value.
+fn bcd(v: u32) -> u32 {
+  (0..8)
+    .rev()
+    .fold(0, |acc, i| acc * 10 + ((v >> (i * 4)) & 0xf))
+}
+
+// ETSI EN 300 468 6.2.13.2
+//
+// Following frequency (32 bits, BCD) and orbital_position (16 bits, BCD):
+//
+//  4          3          2          1          0
+// 98765432 10987654 32109876 54321098 76543210
+// wpprrsmm ssssssss ssssssss ssssssss ssssffff
+//
+// w: west_east_flag
+// p: polarization
+// r: roll_off
+// s: modulation_system
+// m: modulation_type
+// s: symbol_rate (BCD)
+// f: FEC_inner
+fn parse_satellite_delivery_desc(buf: &[u8]) -> Option<StreamDesc> {
+  let mut buf = buf;
+  if buf.len() < 11 {
+    return None;
+  }
+  let frequency = bcd(buf.get_u32());
+  let orbital_position = bcd(buf.get_u16() as u32) as u16;
+  let d = buf.get_uint(5);
+  Some(StreamDesc::SatelliteDelivery(SatelliteDeliveryDesc {
+    frequency,
+    orbital_position,
+    east: d.bit(39),
+    polarization: d.bits(38..=37) as u8,
+    roll_off: d.bits(36..=35) as u8,
+    s2: d.bit(34),
+    modulation_type: d.bits(33..=32) as u8,
+    symbol_rate: bcd(d.bits(31..=4) as u32),
+    fec_inner: d.bits(3..=0) as u8,
+  }))
+}
+
+// ETSI EN 300 468 6.2.13.4
+//
+// Following centre_frequency (32 bits):
+//
+//  2          1          0
+// 32109876 54321098 76543210
+// bbbptm.. cchhhrrr lllggtto
+//
+// b: bandwidth
+// p: priority
+// t: Time_Slicing_indicator
+// m: MPE-FEC_indicator
+// c: constellation
+// h: hierarchy_information
+// r: code_rate-HP_stream
+// l: code_rate-LP_stream
+// g: guard_interval
+// t: transmission_mode
+// o: other_frequency_flag
+fn parse_terrestrial_delivery_desc(buf: &[u8]) -> Option<StreamDesc> {
+  let mut buf = buf;
+  if buf.len() < 7 {
+    return None;
+  }
+  let centre_frequency = buf.get_u32();
+  let d = buf.get_uint(3);
+  Some(StreamDesc::TerrestrialDelivery(TerrestrialDeliveryDesc {
+    centre_frequency,
+    bandwidth: d.bits(23..=21) as u8,
+    priority: d.bit(20),
+    // Both indicators are active low.
+    time_slicing: !d.bit(19),
+    mpe_fec: !d.bit(18),
+    constellation: d.bits(15..=14) as u8,
+    hierarchy_information: d.bits(13..=11) as u8,
+    code_rate_hp: d.bits(10..=8) as u8,
+    code_rate_lp: d.bits(7..=5) as u8,
+    guard_interval: d.bits(4..=3) as u8,
+    transmission_mode: d.bits(2..=1) as u8,
+    other_frequency: d.bit(0),
+  }))
+}
+
+// ETSI EN 300 468 6.2.15
+fn parse_extended_event_desc(buf: &[u8]) -> Option<StreamDesc> {
+  let mut buf = buf;
+  if buf.len() < 5 {
+    return None;
+  }
+  let b = buf.get_u8();
+  let language = get_language(&mut buf);
+
+  let items_len = buf.get_u8() as usize;
+  if items_len > buf.len() {
+    return None;
+  }
+  let mut items_buf = &buf[..items_len];
+  buf.advance(items_len);
+  let mut items = Vec::new();
+  while !items_buf.is_empty() {
+    let description = get_length_prefixed(&mut items_buf)?;
+    let item = get_length_prefixed(&mut items_buf)?;
+    items.push((description, item));
+  }
+
+  let text = get_length_prefixed(&mut buf)?;
+  Some(StreamDesc::ExtendedEvent(ExtendedEventDesc {
+    descriptor_number: b.bits(7..=4),
+    last_descriptor_number: b.bits(3..=0),
+    language,
+    items,
+    text,
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ac3() {
+    // component_type: full service, complete main, 5.1; bsid 8.
+    let desc = parse_stream_desc(AC3_DESCRIPTOR_TAG, &[0xc0, 0x44, 0x08]);
+    let desc = match desc {
+      Some(StreamDesc::Ac3(desc)) => desc,
+      d => panic!("unexpected desc {:?}", d),
+    };
+    assert_eq!(
+      desc,
+      Ac3Desc {
+        component_type: Some(Ac3ComponentType(0x44)),
+        bsid: Some(8),
+        mainid: None,
+        asvc: None,
+        additional_info: Vec::new(),
+      }
+    );
+    let component_type = desc.component_type.unwrap();
+    assert!(!component_type.enhanced());
+    assert!(component_type.full_service());
+    assert_eq!(component_type.service_type(), 0);
+    assert_eq!(component_type.channels(), Ac3Channels::Multichannel);
+  }
+
+  #[test]
+  fn ac3_truncated() {
+    assert_eq!(parse_stream_desc(AC3_DESCRIPTOR_TAG, &[0xc0, 0x44]), None);
+    assert_eq!(parse_stream_desc(AC3_DESCRIPTOR_TAG, &[]), None);
+  }
+
+  #[test]
+  fn eac3() {
+    // component_type: E-AC-3, full service, more than 5.1; mixinfoexists; one
+    // substream with stereo; 2 bytes of additional_info.
+    let desc =
+      parse_stream_desc(EAC3_DESCRIPTOR_TAG, &[0x8c, 0xc5, 0xc2, 0xaa, 0xbb]);
+    let desc = match desc {
+      Some(StreamDesc::Eac3(desc)) => desc,
+      d => panic!("unexpected desc {:?}", d),
+    };
+    assert_eq!(
+      desc,
+      Eac3Desc {
+        component_type: Some(Ac3ComponentType(0xc5)),
+        bsid: None,
+        mainid: None,
+        asvc: None,
+        mixinfoexists: true,
+        substream1: Some(Ac3ComponentType(0xc2)),
+        substream2: None,
+        substream3: None,
+        additional_info: vec![0xaa, 0xbb],
+      }
+    );
+    let component_type = desc.component_type.unwrap();
+    assert!(component_type.enhanced());
+    assert_eq!(component_type.channels(), Ac3Channels::MultichannelOver5_1);
+    assert_eq!(desc.substream1.unwrap().channels(), Ac3Channels::Stereo);
+  }
+
+  // ES_info of the video stream of a DVB HD service.
+  const AVC_ES_INFO: &[u8] = &[
+    0x52, 0x01, 0x01, // stream_identifier_descriptor
+    0x28, 0x04, 0x64, 0x00, 0x28, 0x3f, // AVC_video_descriptor
+    0x06, 0x01, 0x02, // data_stream_alignment_descriptor
+    0x0e, 0x03, 0xc0, 0x61, 0xa8, // maximum_bitrate_descriptor
+  ];
+
+  #[test]
+  fn avc_es_info() {
+    let descs = parse_descs(AVC_ES_INFO).unwrap();
+    assert_eq!(
+      descs,
+      vec![
+        StreamDesc::StreamIdentifier(StreamIdentifierDesc { component_tag: 1 }),
+        StreamDesc::AvcVideo(AvcVideoDesc {
+          profile_idc: 100,
+          constraint_flags: 0x00,
+          level_idc: 40,
+          still_present: false,
+          picture_24_hour: false,
+          frame_packing_sei_not_present: true,
+        }),
+        StreamDesc::DataStreamAlignment(DataStreamAlignmentDesc {
+          alignment_type: 2,
+        }),
+        StreamDesc::MaximumBitrate(MaximumBitrateDesc {
+          maximum_bitrate: 25000,
+        }),
+      ]
+    );
+    match descs[3] {
+      StreamDesc::MaximumBitrate(ref desc) => {
+        assert_eq!(desc.bits_per_second(), 10_000_000)
+      }
+      _ => unreachable!(),
+    }
+  }
+
+  #[test]
+  fn hevc_video() {
+    // Main 10, Main tier, level 5.1, progressive, no temporal subset.
+    let descs = parse_descs(&[
+      0x38, 0x0d, 0x02, 0x20, 0x00, 0x00, 0x00, 0x90, 0x00, 0x00, 0x00, 0x00,
+      0x00, 0x99, 0x3c,
+    ])
+    .unwrap();
+    assert_eq!(
+      descs,
+      vec![StreamDesc::HevcVideo(HevcVideoDesc {
+        profile_space: 0,
+        tier: false,
+        profile_idc: 2,
+        profile_compatibility_indication: 0x20000000,
+        progressive_source: true,
+        interlaced_source: false,
+        non_packed_constraint: false,
+        frame_only_constraint: true,
+        level_idc: 153,
+        still_present: false,
+        picture_24_hour: true,
+        sub_pic_hrd_params_not_present: true,
+        hdr_wcg_idc: 0,
+        temporal_id_range: None,
+      })]
+    );
+
+    // temporal_layer_subset_flag set, but temporal ids missing.
+    let desc = parse_stream_desc(
+      HEVC_VIDEO_DESC_TAG,
+      &[
+        0x02, 0x20, 0x00, 0x00, 0x00, 0x90, 0x00, 0x00, 0x00, 0x00, 0x00, 0x99,
+        0xbc,
+      ],
+    );
+    assert_eq!(desc, None);
+  }
+
+  // ES_info of an SD MPEG-2 video stream and its MPEG-1 layer II audio.
+  const MPEG2_ES_INFO: &[u8] = &[
+    0x02, 0x03, 0x18, 0x48, 0x5f, // video_stream_descriptor
+    0x03, 0x01, 0x67, // audio_stream_descriptor
+    0x0a, 0x08, 0x64, 0x65, 0x75, 0x00, 0x65, 0x6e, 0x67,
+    0x03, // ISO_639_language_descriptor
+  ];
+
+  #[test]
+  fn mpeg2_es_info() {
+    let descs = parse_descs(MPEG2_ES_INFO).unwrap();
+    assert_eq!(
+      descs,
+      vec![
+        StreamDesc::VideoStream(VideoStreamDesc {
+          multiple_frame_rate: false,
+          frame_rate_code: 3,
+          mpeg1_only: false,
+          constrained_parameter: false,
+          still_picture: false,
+          profile_and_level_indication: Some(0x48),
+          chroma_format: Some(1),
+          frame_rate_extension: Some(false),
+        }),
+        StreamDesc::AudioStream(AudioStreamDesc {
+          free_format: false,
+          id: 1,
+          layer: 2,
+          variable_rate_audio: false,
+        }),
+        StreamDesc::Iso639Language(Iso639LanguageDesc {
+          languages: vec![
+            Iso639Language {
+              language: "deu".to_string(),
+              audio_type: 0,
+            },
+            Iso639Language {
+              language: "eng".to_string(),
+              audio_type: 3,
+            },
+          ],
+        }),
+      ]
+    );
+  }
+
+  #[test]
+  fn aac_audio() {
+    let descs = parse_descs(&[
+      0x1c, 0x01, 0x58, // MPEG-4_audio_descriptor
+      0x2b, 0x03, 0x01, 0x02, 0x00, // MPEG-2_AAC_audio_descriptor
+    ])
+    .unwrap();
+    assert_eq!(
+      descs,
+      vec![
+        StreamDesc::Mpeg4Audio(Mpeg4AudioDesc {
+          profile_and_level: 0x58,
+        }),
+        StreamDesc::Mpeg2AacAudio(Mpeg2AacAudioDesc {
+          profile: 1,
+          channel_configuration: 2,
+          additional_information: 0,
+        }),
+      ]
+    );
+  }
+
+  // Descriptors of an ID3 timed metadata stream, as found in HLS segments.
+  const ID3_PROGRAM_INFO: &[u8] = &[
+    0x25, 0x0f, 0xff, 0xff, 0x49, 0x44, 0x33, 0x20, 0xff, 0x49, 0x44, 0x33,
+    0x20, 0x00, 0x1f, 0x00, 0x01, // metadata_pointer_descriptor
+  ];
+  const ID3_ES_INFO: &[u8] = &[
+    0x26, 0x0d, 0xff, 0xff, 0x49, 0x44, 0x33, 0x20, 0xff, 0x49, 0x44, 0x33,
+    0x20, 0x00, 0x0f, // metadata_descriptor
+    0x27, 0x09, 0xc0, 0x00, 0x00, 0xc0, 0x00, 0x00, 0xc0, 0x00,
+    0x00, // metadata_STD_descriptor
+  ];
+
+  #[test]
+  fn id3_metadata() {
+    let descs = parse_descs(ID3_PROGRAM_INFO).unwrap();
+    assert_eq!(
+      descs,
+      vec![StreamDesc::MetadataPointer(MetadataPointerDesc {
+        metadata_application_format: 0xffff,
+        app_format_id: Some(0x49443320),
+        metadata_format: 0xff,
+        format_id: Some(0x49443320),
+        metadata_service_id: 0,
+        metadata_locator: None,
+        mpeg_carriage: 0,
+        program_number: Some(1),
+        transport_stream_location: None,
+        transport_stream_id: None,
+        private_data: Vec::new(),
+      })]
+    );
+
+    let descs = parse_descs(ID3_ES_INFO).unwrap();
+    assert_eq!(
+      descs,
+      vec![
+        StreamDesc::Metadata(MetadataDesc {
+          app_format_id: Some(0x49443320),
+        }),
+        StreamDesc::MetadataStd(MetadataStdDesc {
+          input_leak_rate: 0,
+          buffer_size: 0,
+          output_leak_rate: 0,
+        }),
+      ]
+    );
+  }
+
+  #[test]
+  fn hierarchy_and_extension() {
+    let descs = parse_descs(&[
+      0x04, 0x04, 0xf3, 0xc1, 0xc0, 0xc2, // hierarchy_descriptor
+      0x3f, 0x03, 0x0a, 0x01, 0x02, // extension_descriptor
+    ])
+    .unwrap();
+    assert_eq!(
+      descs,
+      vec![
+        StreamDesc::Hierarchy(HierarchyDesc {
+          no_view_scalability: true,
+          no_temporal_scalability: true,
+          no_spatial_scalability: true,
+          no_quality_scalability: true,
+          hierarchy_type: 3,
+          layer_index: 1,
+          tref_present: true,
+          embedded_layer_index: 0,
+          channel: 2,
+        }),
+        StreamDesc::Extension(ExtensionDesc {
+          extension_desc_tag: 0x0a,
+          data: vec![0x01, 0x02],
+        }),
+      ]
+    );
+  }
+
+  // ES_info of a DVB subtitles stream, and of a teletext stream with a subtitle
+  // page.
+  const DVB_SUBTITLES_ES_INFO: &[u8] = &[
+    0x52, 0x01, 0x05, // stream_identifier_descriptor
+    0x59, 0x10, 0x66, 0x72, 0x61, 0x10, 0x00, 0x02, 0x00, 0x02, 0x66, 0x72,
+    0x61, 0x20, 0x00, 0x03, 0x00, 0x03, // subtitling_descriptor
+  ];
+  const TELETEXT_ES_INFO: &[u8] = &[
+    0x56, 0x0a, 0x64, 0x65, 0x75, 0x09, 0x00, 0x64, 0x65, 0x75, 0x17,
+    0x50, // teletext_descriptor
+  ];
+
+  #[test]
+  fn subtitles() {
+    let descs = parse_descs(DVB_SUBTITLES_ES_INFO).unwrap();
+    assert_eq!(
+      descs,
+      vec![
+        StreamDesc::StreamIdentifier(StreamIdentifierDesc { component_tag: 5 }),
+        StreamDesc::Subtitling(SubtitlingDesc {
+          subtitles: vec![
+            Subtitling {
+              language: "fra".to_string(),
+              subtitling_type: 0x10,
+              composition_page_id: 2,
+              ancillary_page_id: 2,
+            },
+            Subtitling {
+              language: "fra".to_string(),
+              subtitling_type: 0x20,
+              composition_page_id: 3,
+              ancillary_page_id: 3,
+            },
+          ],
+        }),
+      ]
+    );
+
+    let descs = parse_descs(TELETEXT_ES_INFO).unwrap();
+    let pages = match descs[0] {
+      StreamDesc::Teletext(ref desc) => &desc.pages,
+      ref d => panic!("unexpected desc {:?}", d),
+    };
+    assert_eq!(
+      pages,
+      &vec![
+        TeletextPage {
+          language: "deu".to_string(),
+          teletext_type: 1,
+          magazine_number: 1,
+          page_number: 0x00,
+        },
+        TeletextPage {
+          language: "deu".to_string(),
+          teletext_type: 2,
+          magazine_number: 7,
+          page_number: 0x50,
+        },
+      ]
+    );
+    assert_eq!(pages[0].page(), 100);
+    assert_eq!(pages[1].page(), 750);
+  }
+
+  #[test]
+  fn component_and_service() {
+    let descs = parse_descs(&[
+      0x50, 0x08, 0x05, 0x0b, 0x01, 0x65, 0x6e, 0x67, 0x48,
+      0x44, // component_descriptor
+      0x48, 0x0b, 0x19, 0x03, 0x41, 0x42, 0x43, 0x05, 0x41, 0x42, 0x43, 0x20,
+      0x31, // service_descriptor
+    ])
+    .unwrap();
+    assert_eq!(
+      descs,
+      vec![
+        StreamDesc::Component(ComponentDesc {
+          stream_content_ext: 0,
+          stream_content: 5,
+          component_type: 0x0b,
+          component_tag: 1,
+          language: "eng".to_string(),
+          text: b"HD".to_vec(),
+        }),
+        StreamDesc::Service(ServiceDesc {
+          service_type: 0x19,
+          provider_name: b"ABC".to_vec(),
+          service_name: b"ABC 1".to_vec(),
+        }),
+      ]
+    );
+
+    // service_name_length overruns the descriptor.
+    let desc = parse_stream_desc(
+      SERVICE_DESC_TAG,
+      &[
+        0x19, 0x03, 0x41, 0x42, 0x43, 0x06, 0x41, 0x42, 0x43, 0x20, 0x31,
+      ],
+    );
+    assert_eq!(desc, None);
+  }
+
+  #[test]
+  fn dvb_audio() {
+    let descs = parse_descs(&[
+      0x7c, 0x01, 0x58, // AAC_descriptor, profile_and_level only
+      0x7c, 0x03, 0x51, 0x80, 0x03, // AAC_descriptor with AAC_type
+      0x7b, 0x05, 0xd3, 0xc7, 0x8f, 0xfe, 0x4c, // DTS_descriptor
+      0x7f, 0x05, 0x06, 0x85, 0x65, 0x6e, 0x67, // supplementary_audio
+    ])
+    .unwrap();
+    assert_eq!(
+      descs,
+      vec![
+        StreamDesc::Aac(AacDesc {
+          profile_and_level: 0x58,
+          saoc_de: false,
+          aac_type: None,
+          additional_info: Vec::new(),
+        }),
+        StreamDesc::Aac(AacDesc {
+          profile_and_level: 0x51,
+          saoc_de: false,
+          aac_type: Some(3),
+          additional_info: Vec::new(),
+        }),
+        StreamDesc::Dts(DtsDesc {
+          sample_rate_code: 13,
+          bit_rate_code: 15,
+          nblks: 15,
+          fsize: 2047,
+          surround_mode: 9,
+          lfe: true,
+          extended_surround: 0,
+          additional_info: Vec::new(),
+        }),
+        StreamDesc::SupplementaryAudio(SupplementaryAudioDesc {
+          mix_type: true,
+          editorial_classification: 1,
+          language: Some("eng".to_string()),
+          private_data: Vec::new(),
+        }),
+      ]
+    );
+  }
+
+  #[test]
+  fn delivery_systems() {
+    let descs = parse_descs(&[
+      // satellite_delivery_system_descriptor: 11.36325 GHz, 19.2E,
+      // horizontal, DVB-S2 8PSK, 22000 ksym/s, FEC 2/3.
+      0x43, 0x0b, 0x01, 0x13, 0x63, 0x25, 0x01, 0x92, 0x86, 0x02, 0x20, 0x00,
+      0x02, //
+      // cable_delivery_system_descriptor: 346 MHz, 256-QAM, 6900 ksym/s.
+      0x44, 0x0b, 0x03, 0x46, 0x00, 0x00, 0xff, 0xf2, 0x05, 0x00, 0x69, 0x00,
+      0x0f, //
+      // terrestrial_delivery_system_descriptor: 474 MHz, 8 MHz, 64-QAM, code
+      // rate 2/3, guard interval 1/4, 8k.
+      0x5a, 0x0b, 0x02, 0xd3, 0x44, 0x40, 0x1f, 0x81, 0x1b, 0xff, 0xff, 0xff,
+      0xff,
+    ])
+    .unwrap();
+    assert_eq!(
+      descs,
+      vec![
+        StreamDesc::SatelliteDelivery(SatelliteDeliveryDesc {
+          frequency: 1136325,
+          orbital_position: 192,
+          east: true,
+          polarization: 0,
+          roll_off: 0,
+          s2: true,
+          modulation_type: 2,
+          symbol_rate: 220000,
+          fec_inner: 2,
+        }),
+        StreamDesc::CableDelivery(CableDeliveryDesc {
+          frequency: 3460000,
+          fec_outer: 2,
+          modulation: 5,
+          symbol_rate: 69000,
+          fec_inner: 15,
+        }),
+        StreamDesc::TerrestrialDelivery(TerrestrialDeliveryDesc {
+          centre_frequency: 47_400_000,
+          bandwidth: 0,
+          priority: true,
+          time_slicing: false,
+          mpe_fec: false,
+          constellation: 2,
+          hierarchy_information: 0,
+          code_rate_hp: 1,
+          code_rate_lp: 0,
+          guard_interval: 3,
+          transmission_mode: 1,
+          other_frequency: true,
+        }),
+      ]
+    );
+  }
+
+  #[test]
+  fn network_descs() {
+    let descs = parse_descs(&[
+      0x40, 0x03, 0x4e, 0x45, 0x54, // network_name_descriptor
+      0x41, 0x06, 0x00, 0x01, 0x01, 0x00, 0x02, 0x02, // service_list
+      0x83, 0x08, 0x00, 0x01, 0xfc, 0x01, 0x00, 0x02, 0x7c,
+      0x0c, // logical_channel_descriptor
+    ])
+    .unwrap();
+    assert_eq!(
+      descs,
+      vec![
+        StreamDesc::NetworkName(NetworkNameDesc {
+          name: b"NET".to_vec(),
+        }),
+        StreamDesc::ServiceList(ServiceListDesc {
+          services: vec![(1, 1), (2, 2)],
+        }),
+        StreamDesc::LogicalChannel(LogicalChannelDesc {
+          channels: vec![
+            LogicalChannel {
+              service_id: 1,
+              visible: true,
+              logical_channel_number: 1,
+            },
+            LogicalChannel {
+              service_id: 2,
+              visible: false,
+              logical_channel_number: 12,
+            },
+          ],
+        }),
+      ]
+    );
+  }
+
+  #[test]
+  fn event_descs() {
+    let descs = parse_descs(&[
+      0x4d, 0x0c, 0x65, 0x6e, 0x67, 0x04, 0x4e, 0x65, 0x77, 0x73, 0x03, 0x41,
+      0x6c, 0x6c, // short_event_descriptor
+      0x4e, 0x12, 0x01, 0x65, 0x6e, 0x67, 0x0a, 0x04, 0x48, 0x6f, 0x73, 0x74,
+      0x04, 0x4a, 0x61, 0x6e, 0x65, 0x02, 0x2e, 0x2e, // extended_event
+      0x54, 0x04, 0x20, 0x00, 0xf1, 0x02, // content_descriptor
+    ])
+    .unwrap();
+    assert_eq!(
+      descs,
+      vec![
+        StreamDesc::ShortEvent(ShortEventDesc {
+          language: "eng".to_string(),
+          event_name: b"News".to_vec(),
+          text: b"All".to_vec(),
+        }),
+        StreamDesc::ExtendedEvent(ExtendedEventDesc {
+          descriptor_number: 0,
+          last_descriptor_number: 1,
+          language: "eng".to_string(),
+          items: vec![(b"Host".to_vec(), b"Jane".to_vec())],
+          text: b"..".to_vec(),
+        }),
+        StreamDesc::Content(ContentDesc {
+          contents: vec![
+            Content {
+              content_nibbles: 0x20,
+              user_byte: 0,
+            },
+            Content {
+              content_nibbles: 0xf1,
+              user_byte: 2,
+            },
+          ],
+        }),
+      ]
+    );
+    match descs[2] {
+      StreamDesc::Content(ref desc) => {
+        assert_eq!(desc.contents[0].genre(), Some("News/Current affairs"));
+        assert_eq!(desc.contents[1].genre(), None);
+      }
+      _ => unreachable!(),
+    }
+  }
+
+  #[test]
+  fn local_time_offset_desc() {
+    let descs = parse_descs(&[
+      0x58, 0x1a, // local_time_offset_descriptor
+      0x45, 0x53, 0x50, 0x02, 0x01, 0x00, 0xe1, 0xee, 0x01, 0x00, 0x00, 0x02,
+      0x00, // ESP, +01:00, then +02:00 from 2017/03/26 01:00:00
+      0x42, 0x52, 0x41, 0x07, 0x03, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0x03,
+      0x00, // BRA region 1, -03:00
+    ])
+    .unwrap();
+    assert_eq!(
+      descs,
+      vec![StreamDesc::LocalTimeOffset(LocalTimeOffsetDesc {
+        offsets: vec![
+          LocalTimeOffset {
+            country_code: "ESP".to_string(),
+            country_region_id: 0,
+            local_time_offset: 3600,
+            time_of_change: Some(1_490_490_000),
+            next_time_offset: 7200,
+          },
+          LocalTimeOffset {
+            country_code: "BRA".to_string(),
+            country_region_id: 1,
+            local_time_offset: -10800,
+            time_of_change: None,
+            next_time_offset: -10800,
+          },
+        ],
+      })]
+    );
+  }
+}
@@ -0,0 +1,480 @@
+//! Decoding of the text fields of DVB SI (service names, event names, ...)
+//! as specified in ETSI EN 300 468 Annex A.
+
+// Control codes (ETSI EN 300 468 Table A.1). In the two-byte tables they are
+// preceded by 0xE0.
+const CR_LF: u8 = 0x8A;
+const FIRST_CONTROL_CODE: u8 = 0x80;
+const LAST_CONTROL_CODE: u8 = 0x9F;
+
+// ISO/IEC 6937 combining diacritical marks, which precede the letter they
+// apply to.
+const FIRST_DIACRITIC: u8 = 0xC1;
+const LAST_DIACRITIC: u8 = 0xCF;
+
+/// Decodes a DVB string to UTF-8. The character table is selected by the first
+/// byte, as specified in ETSI EN 300 468 Table A.3. The multi-byte CJK tables
+/// (KS X 1001, GB-2312 and Big5) are not supported: their non-ASCII characters
+/// are replaced with U+FFFD.
+pub fn decode(buf: &[u8]) -> String {
+  if buf.is_empty() {
+    return String::new();
+  }
+
+  match buf[0] {
+    // ISO/IEC 8859-5 to ISO/IEC 8859-15, with no 8859-12.
+    0x01..=0x0B => match iso_8859_table(buf[0] + 4) {
+      Some(table) => decode_8859(table, &buf[1..]),
+      None => decode_unknown(&buf[1..]),
+    },
+
+    0x10 => {
+      if buf.len() < 3 {
+        return String::new();
+      }
+      match iso_8859_table(buf[2]) {
+        Some(table) if buf[1] == 0 => decode_8859(table, &buf[3..]),
+        _ if buf[1] == 0 && buf[2] == 1 => decode_8859_1(&buf[3..]),
+        _ => decode_unknown(&buf[3..]),
+      }
+    }
+
+    0x11 => decode_ucs2(&buf[1..]),
+
+    0x15 => String::from_utf8_lossy(&buf[1..])
+      .chars()
+      .filter_map(utf8_control)
+      .collect(),
+
+    // 0x1F is followed by an encoding_type_id.
+    0x1F => decode_unknown(buf.get(2..).unwrap_or(&[])),
+
+    0x00..=0x1F => decode_unknown(&buf[1..]),
+
+    _ => decode_6937(buf),
+  }
+}
+
+// Maps a control code to the character it stands for, if any. Emphasis and
+// reserved control codes are dropped.
+fn control(code: u8) -> Option<char> {
+  match code {
+    CR_LF => Some('\n'),
+    _ => None,
+  }
+}
+
+fn utf8_control(c: char) -> Option<char> {
+  match c as u32 {
+    0xE080..=0xE09F => control((c as u32 - 0xE000) as u8),
+    _ => Some(c),
+  }
+}
+
+// Decodes the default character table: ISO/IEC 6937 as amended by ETSI EN 300
+// 468 Figure A.1.
+fn decode_6937(buf: &[u8]) -> String {
+  let mut s = String::with_capacity(buf.len());
+  let mut diacritic: Option<u8> = None;
+
+  for &b in buf {
+    match b {
+      FIRST_CONTROL_CODE..=LAST_CONTROL_CODE => {
+        s.extend(control(b));
+        continue;
+      }
+      FIRST_DIACRITIC..=LAST_DIACRITIC => {
+        diacritic = Some(b);
+        continue;
+      }
+      _ => (),
+    }
+
+    let c = if b < 0x80 {
+      b as char
+    } else {
+      ISO_6937[(b - 0xA0) as usize]
+    };
+
+    match diacritic.take() {
+      Some(d) => s.extend(compose(d, c)),
+      None => s.push(c),
+    }
+  }
+
+  s
+}
+
+// Applies an ISO/IEC 6937 diacritical mark to a letter. Uses the precomposed
+// character if there is one, and a combining character otherwise.
+fn compose(diacritic: u8, c: char) -> Vec<char> {
+  let (mark, letters, composed) =
+    DIACRITICS[(diacritic - FIRST_DIACRITIC) as usize];
+  match letters.chars().position(|l| l == c) {
+    Some(i) => vec![composed.chars().nth(i).unwrap()],
+    None if mark == '\u{FFFD}' => vec![c],
+    None => vec![c, mark],
+  }
+}
+
+fn decode_8859_1(buf: &[u8]) -> String {
+  buf
+    .iter()
+    .filter_map(|&b| match b {
+      FIRST_CONTROL_CODE..=LAST_CONTROL_CODE => control(b),
+      _ => Some(b as char),
+    })
+    .collect()
+}
+
+fn decode_8859(table: &[u16; 96], buf: &[u8]) -> String {
+  buf
+    .iter()
+    .filter_map(|&b| match b {
+      0x00..=0x7F => Some(b as char),
+      FIRST_CONTROL_CODE..=LAST_CONTROL_CODE => control(b),
+      _ => std::char::from_u32(table[(b - 0xA0) as usize] as u32),
+    })
+    .collect()
+}
+
+// Decodes the Basic Multilingual Plane of ISO/IEC 10646 (UCS-2, big endian).
+fn decode_ucs2(buf: &[u8]) -> String {
+  buf
+    .chunks_exact(2)
+    .map(|c| ((c[0] as u32) << 8) | c[1] as u32)
+    .filter_map(|c| match c {
+      0xE080..=0xE09F => control((c - 0xE000) as u8),
+      _ => Some(std::char::from_u32(c).unwrap_or('\u{FFFD}')),
+    })
+    .collect()
+}
+
+// Decodes a string in an unsupported character table. Only ASCII can be
+// trusted.
+fn decode_unknown(buf: &[u8]) -> String {
+  buf
+    .iter()
+    .map(|&b| if b < 0x80 { b as char } else { '\u{FFFD}' })
+    .collect()
+}
+
+fn iso_8859_table(part: u8) -> Option<&'static [u16; 96]> {
+  match part {
+    2 => Some(&ISO_8859_2),
+    3 => Some(&ISO_8859_3),
+    4 => Some(&ISO_8859_4),
+    5 => Some(&ISO_8859_5),
+    6 => Some(&ISO_8859_6),
+    7 => Some(&ISO_8859_7),
+    8 => Some(&ISO_8859_8),
+    9 => Some(&ISO_8859_9),
+    10 => Some(&ISO_8859_10),
+    11 => Some(&ISO_8859_11),
+    13 => Some(&ISO_8859_13),
+    14 => Some(&ISO_8859_14),
+    15 => Some(&ISO_8859_15),
+    16 => Some(&ISO_8859_16),
+    _ => None,
+  }
+}
+
+// ISO/IEC 6937 from 0xA0 to 0xFF. The diacritical marks (0xC1 to 0xCF) are
+// handled separately, and unassigned positions map to U+FFFD.
+const ISO_6937: [char; 96] = [
+  '\u{A0}', '¡', '¢', '£', '$', '¥', '#', '§', '¤', '‘', '“', '«', '←', '↑',
+  '→', '↓', '°', '±', '²', '³', '×', 'µ', '¶', '·', '÷', '’', '”', '»', '¼',
+  '½', '¾', '¿', '\u{FFFD}', '\u{FFFD}', '\u{FFFD}', '\u{FFFD}', '\u{FFFD}',
+  '\u{FFFD}', '\u{FFFD}', '\u{FFFD}', '\u{FFFD}', '\u{FFFD}', '\u{FFFD}',
+  '\u{FFFD}', '\u{FFFD}', '\u{FFFD}', '\u{FFFD}', '\u{FFFD}', '―', '¹', '®',
+  '©', '™', '♪', '¬', '¦', '\u{FFFD}', '\u{FFFD}', '\u{FFFD}', '\u{FFFD}', '⅛',
+  '⅜', '⅝', '⅞', 'Ω', 'Æ', 'Đ', 'ª', 'Ħ', '\u{FFFD}', 'Ĳ', 'Ŀ', 'Ł', 'Ø', 'Œ',
+  'º', 'Þ', 'Ŧ', 'Ŋ', 'ŉ', 'ĸ', 'æ', 'đ', 'ð', 'ħ', 'ı', 'ĳ', 'ŀ', 'ł', 'ø',
+  'œ', 'ß', 'þ', 'ŧ', 'ŋ', '\u{AD}',
+];
+
+// The ISO/IEC 6937 diacritical marks from 0xC1 to 0xCF: the combining
+// character, and the letters that have a precomposed form with it.
+const DIACRITICS: [(char, &str, &str); 15] = [
+  ('\u{300}', "aeinouwyAEINOUWY", "àèìǹòùẁỳÀÈÌǸÒÙẀỲ"),
+  (
+    '\u{301}',
+    "acegiklmnoprsuwyzACEGIKLMNOPRSUWYZ",
+    "áćéǵíḱĺḿńóṕŕśúẃýźÁĆÉǴÍḰĹḾŃÓṔŔŚÚẂÝŹ",
+  ),
+  (
+    '\u{302}',
+    "aceghijosuwyzACEGHIJOSUWYZ",
+    "âĉêĝĥîĵôŝûŵŷẑÂĈÊĜĤÎĴÔŜÛŴŶẐ",
+  ),
+  ('\u{303}', "aeinouvyAEINOUVY", "ãẽĩñõũṽỹÃẼĨÑÕŨṼỸ"),
+  ('\u{304}', "aegiouyAEGIOUY", "āēḡīōūȳĀĒḠĪŌŪȲ"),
+  ('\u{306}', "aegiouAEGIOU", "ăĕğĭŏŭĂĔĞĬŎŬ"),
+  (
+    '\u{307}',
+    "abcdefghmnoprstwxyzABCDEFGHIMNOPRSTWXYZ",
+    "ȧḃċḋėḟġḣṁṅȯṗṙṡṫẇẋẏżȦḂĊḊĖḞĠḢİṀṄȮṖṘṠṪẆẊẎŻ",
+  ),
+  ('\u{308}', "aehiotuwxyAEHIOUWXY", "äëḧïöẗüẅẍÿÄËḦÏÖÜẄẌŸ"),
+  // 0xC9 is unassigned.
+  ('\u{FFFD}', "", ""),
+  ('\u{30A}', "auwyAU", "åůẘẙÅŮ"),
+  (
+    '\u{327}',
+    "cdeghklnrstCDEGHKLNRST",
+    "çḑȩģḩķļņŗşţÇḐȨĢḨĶĻŅŖŞŢ",
+  ),
+  // 0xCC is unassigned.
+  ('\u{FFFD}', "", ""),
+  ('\u{30B}', "ouOU", "őűŐŰ"),
+  ('\u{328}', "aeiouAEIOU", "ąęįǫųĄĘĮǪŲ"),
+  (
+    '\u{30C}',
+    "acdeghijklnorstuzACDEGHIKLNORSTUZ",
+    "ǎčďěǧȟǐǰǩľňǒřšťǔžǍČĎĚǦȞǏǨĽŇǑŘŠŤǓŽ",
+  ),
+];
+
+// ISO/IEC 8859-2, from 0xA0 to 0xFF.
+const ISO_8859_2: [u16; 96] = [
+  0x00a0, 0x0104, 0x02d8, 0x0141, 0x00a4, 0x013d, 0x015a, 0x00a7, 0x00a8,
+  0x0160, 0x015e, 0x0164, 0x0179, 0x00ad, 0x017d, 0x017b, 0x00b0, 0x0105,
+  0x02db, 0x0142, 0x00b4, 0x013e, 0x015b, 0x02c7, 0x00b8, 0x0161, 0x015f,
+  0x0165, 0x017a, 0x02dd, 0x017e, 0x017c, 0x0154, 0x00c1, 0x00c2, 0x0102,
+  0x00c4, 0x0139, 0x0106, 0x00c7, 0x010c, 0x00c9, 0x0118, 0x00cb, 0x011a,
+  0x00cd, 0x00ce, 0x010e, 0x0110, 0x0143, 0x0147, 0x00d3, 0x00d4, 0x0150,
+  0x00d6, 0x00d7, 0x0158, 0x016e, 0x00da, 0x0170, 0x00dc, 0x00dd, 0x0162,
+  0x00df, 0x0155, 0x00e1, 0x00e2, 0x0103, 0x00e4, 0x013a, 0x0107, 0x00e7,
+  0x010d, 0x00e9, 0x0119, 0x00eb, 0x011b, 0x00ed, 0x00ee, 0x010f, 0x0111,
+  0x0144, 0x0148, 0x00f3, 0x00f4, 0x0151, 0x00f6, 0x00f7, 0x0159, 0x016f,
+  0x00fa, 0x0171, 0x00fc, 0x00fd, 0x0163, 0x02d9,
+];
+
+// ISO/IEC 8859-3, from 0xA0 to 0xFF.
+const ISO_8859_3: [u16; 96] = [
+  0x00a0, 0x0126, 0x02d8, 0x00a3, 0x00a4, 0xfffd, 0x0124, 0x00a7, 0x00a8,
+  0x0130, 0x015e, 0x011e, 0x0134, 0x00ad, 0xfffd, 0x017b, 0x00b0, 0x0127,
+  0x00b2, 0x00b3, 0x00b4, 0x00b5, 0x0125, 0x00b7, 0x00b8, 0x0131, 0x015f,
+  0x011f, 0x0135, 0x00bd, 0xfffd, 0x017c, 0x00c0, 0x00c1, 0x00c2, 0xfffd,
+  0x00c4, 0x010a, 0x0108, 0x00c7, 0x00c8, 0x00c9, 0x00ca, 0x00cb, 0x00cc,
+  0x00cd, 0x00ce, 0x00cf, 0xfffd, 0x00d1, 0x00d2, 0x00d3, 0x00d4, 0x0120,
+  0x00d6, 0x00d7, 0x011c, 0x00d9, 0x00da, 0x00db, 0x00dc, 0x016c, 0x015c,
+  0x00df, 0x00e0, 0x00e1, 0x00e2, 0xfffd, 0x00e4, 0x010b, 0x0109, 0x00e7,
+  0x00e8, 0x00e9, 0x00ea, 0x00eb, 0x00ec, 0x00ed, 0x00ee, 0x00ef, 0xfffd,
+  0x00f1, 0x00f2, 0x00f3, 0x00f4, 0x0121, 0x00f6, 0x00f7, 0x011d, 0x00f9,
+  0x00fa, 0x00fb, 0x00fc, 0x016d, 0x015d, 0x02d9,
+];
+
+// ISO/IEC 8859-4, from 0xA0 to 0xFF.
+const ISO_8859_4: [u16; 96] = [
+  0x00a0, 0x0104, 0x0138, 0x0156, 0x00a4, 0x0128, 0x013b, 0x00a7, 0x00a8,
+  0x0160, 0x0112, 0x0122, 0x0166, 0x00ad, 0x017d, 0x00af, 0x00b0, 0x0105,
+  0x02db, 0x0157, 0x00b4, 0x0129, 0x013c, 0x02c7, 0x00b8, 0x0161, 0x0113,
+  0x0123, 0x0167, 0x014a, 0x017e, 0x014b, 0x0100, 0x00c1, 0x00c2, 0x00c3,
+  0x00c4, 0x00c5, 0x00c6, 0x012e, 0x010c, 0x00c9, 0x0118, 0x00cb, 0x0116,
+  0x00cd, 0x00ce, 0x012a, 0x0110, 0x0145, 0x014c, 0x0136, 0x00d4, 0x00d5,
+  0x00d6, 0x00d7, 0x00d8, 0x0172, 0x00da, 0x00db, 0x00dc, 0x0168, 0x016a,
+  0x00df, 0x0101, 0x00e1, 0x00e2, 0x00e3, 0x00e4, 0x00e5, 0x00e6, 0x012f,
+  0x010d, 0x00e9, 0x0119, 0x00eb, 0x0117, 0x00ed, 0x00ee, 0x012b, 0x0111,
+  0x0146, 0x014d, 0x0137, 0x00f4, 0x00f5, 0x00f6, 0x00f7, 0x00f8, 0x0173,
+  0x00fa, 0x00fb, 0x00fc, 0x0169, 0x016b, 0x02d9,
+];
+
+// ISO/IEC 8859-5, from 0xA0 to 0xFF.
+const ISO_8859_5: [u16; 96] = [
+  0x00a0, 0x0401, 0x0402, 0x0403, 0x0404, 0x0405, 0x0406, 0x0407, 0x0408,
+  0x0409, 0x040a, 0x040b, 0x040c, 0x00ad, 0x040e, 0x040f, 0x0410, 0x0411,
+  0x0412, 0x0413, 0x0414, 0x0415, 0x0416, 0x0417, 0x0418, 0x0419, 0x041a,
+  0x041b, 0x041c, 0x041d, 0x041e, 0x041f, 0x0420, 0x0421, 0x0422, 0x0423,
+  0x0424, 0x0425, 0x0426, 0x0427, 0x0428, 0x0429, 0x042a, 0x042b, 0x042c,
+  0x042d, 0x042e, 0x042f, 0x0430, 0x0431, 0x0432, 0x0433, 0x0434, 0x0435,
+  0x0436, 0x0437, 0x0438, 0x0439, 0x043a, 0x043b, 0x043c, 0x043d, 0x043e,
+  0x043f, 0x0440, 0x0441, 0x0442, 0x0443, 0x0444, 0x0445, 0x0446, 0x0447,
+  0x0448, 0x0449, 0x044a, 0x044b, 0x044c, 0x044d, 0x044e, 0x044f, 0x2116,
+  0x0451, 0x0452, 0x0453, 0x0454, 0x0455, 0x0456, 0x0457, 0x0458, 0x0459,
+  0x045a, 0x045b, 0x045c, 0x00a7, 0x045e, 0x045f,
+];
+
+// ISO/IEC 8859-6, from 0xA0 to 0xFF.
+const ISO_8859_6: [u16; 96] = [
+  0x00a0, 0xfffd, 0xfffd, 0xfffd, 0x00a4, 0xfffd, 0xfffd, 0xfffd, 0xfffd,
+  0xfffd, 0xfffd, 0xfffd, 0x060c, 0x00ad, 0xfffd, 0xfffd, 0xfffd, 0xfffd,
+  0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd,
+  0x061b, 0xfffd, 0xfffd, 0xfffd, 0x061f, 0xfffd, 0x0621, 0x0622, 0x0623,
+  0x0624, 0x0625, 0x0626, 0x0627, 0x0628, 0x0629, 0x062a, 0x062b, 0x062c,
+  0x062d, 0x062e, 0x062f, 0x0630, 0x0631, 0x0632, 0x0633, 0x0634, 0x0635,
+  0x0636, 0x0637, 0x0638, 0x0639, 0x063a, 0xfffd, 0xfffd, 0xfffd, 0xfffd,
+  0xfffd, 0x0640, 0x0641, 0x0642, 0x0643, 0x0644, 0x0645, 0x0646, 0x0647,
+  0x0648, 0x0649, 0x064a, 0x064b, 0x064c, 0x064d, 0x064e, 0x064f, 0x0650,
+  0x0651, 0x0652, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd,
+  0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd,
+];
+
+// ISO/IEC 8859-7, from 0xA0 to 0xFF.
+const ISO_8859_7: [u16; 96] = [
+  0x00a0, 0x2018, 0x2019, 0x00a3, 0x20ac, 0x20af, 0x00a6, 0x00a7, 0x00a8,
+  0x00a9, 0x037a, 0x00ab, 0x00ac, 0x00ad, 0xfffd, 0x2015, 0x00b0, 0x00b1,
+  0x00b2, 0x00b3, 0x0384, 0x0385, 0x0386, 0x00b7, 0x0388, 0x0389, 0x038a,
+  0x00bb, 0x038c, 0x00bd, 0x038e, 0x038f, 0x0390, 0x0391, 0x0392, 0x0393,
+  0x0394, 0x0395, 0x0396, 0x0397, 0x0398, 0x0399, 0x039a, 0x039b, 0x039c,
+  0x039d, 0x039e, 0x039f, 0x03a0, 0x03a1, 0xfffd, 0x03a3, 0x03a4, 0x03a5,
+  0x03a6, 0x03a7, 0x03a8, 0x03a9, 0x03aa, 0x03ab, 0x03ac, 0x03ad, 0x03ae,
+  0x03af, 0x03b0, 0x03b1, 0x03b2, 0x03b3, 0x03b4, 0x03b5, 0x03b6, 0x03b7,
+  0x03b8, 0x03b9, 0x03ba, 0x03bb, 0x03bc, 0x03bd, 0x03be, 0x03bf, 0x03c0,
+  0x03c1, 0x03c2, 0x03c3, 0x03c4, 0x03c5, 0x03c6, 0x03c7, 0x03c8, 0x03c9,
+  0x03ca, 0x03cb, 0x03cc, 0x03cd, 0x03ce, 0xfffd,
+];
+
+// ISO/IEC 8859-8, from 0xA0 to 0xFF.
+const ISO_8859_8: [u16; 96] = [
+  0x00a0, 0xfffd, 0x00a2, 0x00a3, 0x00a4, 0x00a5, 0x00a6, 0x00a7, 0x00a8,
+  0x00a9, 0x00d7, 0x00ab, 0x00ac, 0x00ad, 0x00ae, 0x00af, 0x00b0, 0x00b1,
+  0x00b2, 0x00b3, 0x00b4, 0x00b5, 0x00b6, 0x00b7, 0x00b8, 0x00b9, 0x00f7,
+  0x00bb, 0x00bc, 0x00bd, 0x00be, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd,
+  0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd,
+  0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd,
+  0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd, 0xfffd,
+  0x2017, 0x05d0, 0x05d1, 0x05d2, 0x05d3, 0x05d4, 0x05d5, 0x05d6, 0x05d7,
+  0x05d8, 0x05d9, 0x05da, 0x05db, 0x05dc, 0x05dd, 0x05de, 0x05df, 0x05e0,
+  0x05e1, 0x05e2, 0x05e3, 0x05e4, 0x05e5, 0x05e6, 0x05e7, 0x05e8, 0x05e9,
+  0x05ea, 0xfffd, 0xfffd, 0x200e, 0x200f, 0xfffd,
+];
+
+// ISO/IEC 8859-9, from 0xA0 to 0xFF.
+const ISO_8859_9: [u16; 96] = [
+  0x00a0, 0x00a1, 0x00a2, 0x00a3, 0x00a4, 0x00a5, 0x00a6, 0x00a7, 0x00a8,
+  0x00a9, 0x00aa, 0x00ab, 0x00ac, 0x00ad, 0x00ae, 0x00af, 0x00b0, 0x00b1,
+  0x00b2, 0x00b3, 0x00b4, 0x00b5, 0x00b6, 0x00b7, 0x00b8, 0x00b9, 0x00ba,
+  0x00bb, 0x00bc, 0x00bd, 0x00be, 0x00bf, 0x00c0, 0x00c1, 0x00c2, 0x00c3,
+  0x00c4, 0x00c5, 0x00c6, 0x00c7, 0x00c8, 0x00c9, 0x00ca, 0x00cb, 0x00cc,
+  0x00cd, 0x00ce, 0x00cf, 0x011e, 0x00d1, 0x00d2, 0x00d3, 0x00d4, 0x00d5,
+  0x00d6, 0x00d7, 0x00d8, 0x00d9, 0x00da, 0x00db, 0x00dc, 0x0130, 0x015e,
+  0x00df, 0x00e0, 0x00e1, 0x00e2, 0x00e3, 0x00e4, 0x00e5, 0x00e6, 0x00e7,
+  0x00e8, 0x00e9, 0x00ea, 0x00eb, 0x00ec, 0x00ed, 0x00ee, 0x00ef, 0x011f,
+  0x00f1, 0x00f2, 0x00f3, 0x00f4, 0x00f5, 0x00f6, 0x00f7, 0x00f8, 0x00f9,
+  0x00fa, 0x00fb, 0x00fc, 0x0131, 0x015f, 0x00ff,
+];
+
+// ISO/IEC 8859-10, from 0xA0 to 0xFF.
+const ISO_8859_10: [u16; 96] = [
+  0x00a0, 0x0104, 0x0112, 0x0122, 0x012a, 0x0128, 0x0136, 0x00a7, 0x013b,
+  0x0110, 0x0160, 0x0166, 0x017d, 0x00ad, 0x016a, 0x014a, 0x00b0, 0x0105,
+  0x0113, 0x0123, 0x012b, 0x0129, 0x0137, 0x00b7, 0x013c, 0x0111, 0x0161,
+  0x0167, 0x017e, 0x2015, 0x016b, 0x014b, 0x0100, 0x00c1, 0x00c2, 0x00c3,
+  0x00c4, 0x00c5, 0x00c6, 0x012e, 0x010c, 0x00c9, 0x0118, 0x00cb, 0x0116,
+  0x00cd, 0x00ce, 0x00cf, 0x00d0, 0x0145, 0x014c, 0x00d3, 0x00d4, 0x00d5,
+  0x00d6, 0x0168, 0x00d8, 0x0172, 0x00da, 0x00db, 0x00dc, 0x00dd, 0x00de,
+  0x00df, 0x0101, 0x00e1, 0x00e2, 0x00e3, 0x00e4, 0x00e5, 0x00e6, 0x012f,
+  0x010d, 0x00e9, 0x0119, 0x00eb, 0x0117, 0x00ed, 0x00ee, 0x00ef, 0x00f0,
+  0x0146, 0x014d, 0x00f3, 0x00f4, 0x00f5, 0x00f6, 0x0169, 0x00f8, 0x0173,
+  0x00fa, 0x00fb, 0x00fc, 0x00fd, 0x00fe, 0x0138,
+];
+
+// ISO/IEC 8859-11, from 0xA0 to 0xFF.
+const ISO_8859_11: [u16; 96] = [
+  0x00a0, 0x0e01, 0x0e02, 0x0e03, 0x0e04, 0x0e05, 0x0e06, 0x0e07, 0x0e08,
+  0x0e09, 0x0e0a, 0x0e0b, 0x0e0c, 0x0e0d, 0x0e0e, 0x0e0f, 0x0e10, 0x0e11,
+  0x0e12, 0x0e13, 0x0e14, 0x0e15, 0x0e16, 0x0e17, 0x0e18, 0x0e19, 0x0e1a,
+  0x0e1b, 0x0e1c, 0x0e1d, 0x0e1e, 0x0e1f, 0x0e20, 0x0e21, 0x0e22, 0x0e23,
+  0x0e24, 0x0e25, 0x0e26, 0x0e27, 0x0e28, 0x0e29, 0x0e2a, 0x0e2b, 0x0e2c,
+  0x0e2d, 0x0e2e, 0x0e2f, 0x0e30, 0x0e31, 0x0e32, 0x0e33, 0x0e34, 0x0e35,
+  0x0e36, 0x0e37, 0x0e38, 0x0e39, 0x0e3a, 0xfffd, 0xfffd, 0xfffd, 0xfffd,
+  0x0e3f, 0x0e40, 0x0e41, 0x0e42, 0x0e43, 0x0e44, 0x0e45, 0x0e46, 0x0e47,
+  0x0e48, 0x0e49, 0x0e4a, 0x0e4b, 0x0e4c, 0x0e4d, 0x0e4e, 0x0e4f, 0x0e50,
+  0x0e51, 0x0e52, 0x0e53, 0x0e54, 0x0e55, 0x0e56, 0x0e57, 0x0e58, 0x0e59,
+  0x0e5a, 0x0e5b, 0xfffd, 0xfffd, 0xfffd, 0xfffd,
+];
+
+// ISO/IEC 8859-13, from 0xA0 to 0xFF.
+const ISO_8859_13: [u16; 96] = [
+  0x00a0, 0x201d, 0x00a2, 0x00a3, 0x00a4, 0x201e, 0x00a6, 0x00a7, 0x00d8,
+  0x00a9, 0x0156, 0x00ab, 0x00ac, 0x00ad, 0x00ae, 0x00c6, 0x00b0, 0x00b1,
+  0x00b2, 0x00b3, 0x201c, 0x00b5, 0x00b6, 0x00b7, 0x00f8, 0x00b9, 0x0157,
+  0x00bb, 0x00bc, 0x00bd, 0x00be, 0x00e6, 0x0104, 0x012e, 0x0100, 0x0106,
+  0x00c4, 0x00c5, 0x0118, 0x0112, 0x010c, 0x00c9, 0x0179, 0x0116, 0x0122,
+  0x0136, 0x012a, 0x013b, 0x0160, 0x0143, 0x0145, 0x00d3, 0x014c, 0x00d5,
+  0x00d6, 0x00d7, 0x0172, 0x0141, 0x015a, 0x016a, 0x00dc, 0x017b, 0x017d,
+  0x00df, 0x0105, 0x012f, 0x0101, 0x0107, 0x00e4, 0x00e5, 0x0119, 0x0113,
+  0x010d, 0x00e9, 0x017a, 0x0117, 0x0123, 0x0137, 0x012b, 0x013c, 0x0161,
+  0x0144, 0x0146, 0x00f3, 0x014d, 0x00f5, 0x00f6, 0x00f7, 0x0173, 0x0142,
+  0x015b, 0x016b, 0x00fc, 0x017c, 0x017e, 0x2019,
+];
+
+// ISO/IEC 8859-14, from 0xA0 to 0xFF.
+const ISO_8859_14: [u16; 96] = [
+  0x00a0, 0x1e02, 0x1e03, 0x00a3, 0x010a, 0x010b, 0x1e0a, 0x00a7, 0x1e80,
+  0x00a9, 0x1e82, 0x1e0b, 0x1ef2, 0x00ad, 0x00ae, 0x0178, 0x1e1e, 0x1e1f,
+  0x0120, 0x0121, 0x1e40, 0x1e41, 0x00b6, 0x1e56, 0x1e81, 0x1e57, 0x1e83,
+  0x1e60, 0x1ef3, 0x1e84, 0x1e85, 0x1e61, 0x00c0, 0x00c1, 0x00c2, 0x00c3,
+  0x00c4, 0x00c5, 0x00c6, 0x00c7, 0x00c8, 0x00c9, 0x00ca, 0x00cb, 0x00cc,
+  0x00cd, 0x00ce, 0x00cf, 0x0174, 0x00d1, 0x00d2, 0x00d3, 0x00d4, 0x00d5,
+  0x00d6, 0x1e6a, 0x00d8, 0x00d9, 0x00da, 0x00db, 0x00dc, 0x00dd, 0x0176,
+  0x00df, 0x00e0, 0x00e1, 0x00e2, 0x00e3, 0x00e4, 0x00e5, 0x00e6, 0x00e7,
+  0x00e8, 0x00e9, 0x00ea, 0x00eb, 0x00ec, 0x00ed, 0x00ee, 0x00ef, 0x0175,
+  0x00f1, 0x00f2, 0x00f3, 0x00f4, 0x00f5, 0x00f6, 0x1e6b, 0x00f8, 0x00f9,
+  0x00fa, 0x00fb, 0x00fc, 0x00fd, 0x0177, 0x00ff,
+];
+
+// ISO/IEC 8859-15, from 0xA0 to 0xFF.
+const ISO_8859_15: [u16; 96] = [
+  0x00a0, 0x00a1, 0x00a2, 0x00a3, 0x20ac, 0x00a5, 0x0160, 0x00a7, 0x0161,
+  0x00a9, 0x00aa, 0x00ab, 0x00ac, 0x00ad, 0x00ae, 0x00af, 0x00b0, 0x00b1,
+  0x00b2, 0x00b3, 0x017d, 0x00b5, 0x00b6, 0x00b7, 0x017e, 0x00b9, 0x00ba,
+  0x00bb, 0x0152, 0x0153, 0x0178, 0x00bf, 0x00c0, 0x00c1, 0x00c2, 0x00c3,
+  0x00c4, 0x00c5, 0x00c6, 0x00c7, 0x00c8, 0x00c9, 0x00ca, 0x00cb, 0x00cc,
+  0x00cd, 0x00ce, 0x00cf, 0x00d0, 0x00d1, 0x00d2, 0x00d3, 0x00d4, 0x00d5,
+  0x00d6, 0x00d7, 0x00d8, 0x00d9, 0x00da, 0x00db, 0x00dc, 0x00dd, 0x00de,
+  0x00df, 0x00e0, 0x00e1, 0x00e2, 0x00e3, 0x00e4, 0x00e5, 0x00e6, 0x00e7,
+  0x00e8, 0x00e9, 0x00ea, 0x00eb, 0x00ec, 0x00ed, 0x00ee, 0x00ef, 0x00f0,
+  0x00f1, 0x00f2, 0x00f3, 0x00f4, 0x00f5, 0x00f6, 0x00f7, 0x00f8, 0x00f9,
+  0x00fa, 0x00fb, 0x00fc, 0x00fd, 0x00fe, 0x00ff,
+];
+
+// ISO/IEC 8859-16, from 0xA0 to 0xFF.
+const ISO_8859_16: [u16; 96] = [
+  0x00a0, 0x0104, 0x0105, 0x0141, 0x20ac, 0x201e, 0x0160, 0x00a7, 0x0161,
+  0x00a9, 0x0218, 0x00ab, 0x0179, 0x00ad, 0x017a, 0x017b, 0x00b0, 0x00b1,
+  0x010c, 0x0142, 0x017d, 0x201d, 0x00b6, 0x00b7, 0x017e, 0x010d, 0x0219,
+  0x00bb, 0x0152, 0x0153, 0x0178, 0x017c, 0x00c0, 0x00c1, 0x00c2, 0x0102,
+  0x00c4, 0x0106, 0x00c6, 0x00c7, 0x00c8, 0x00c9, 0x00ca, 0x00cb, 0x00cc,
+  0x00cd, 0x00ce, 0x00cf, 0x0110, 0x0143, 0x00d2, 0x00d3, 0x00d4, 0x0150,
+  0x00d6, 0x015a, 0x0170, 0x00d9, 0x00da, 0x00db, 0x00dc, 0x0118, 0x021a,
+  0x00df, 0x00e0, 0x00e1, 0x00e2, 0x0103, 0x00e4, 0x0107, 0x00e6, 0x00e7,
+  0x00e8, 0x00e9, 0x00ea, 0x00eb, 0x00ec, 0x00ed, 0x00ee, 0x00ef, 0x0111,
+  0x0144, 0x00f2, 0x00f3, 0x00f4, 0x0151, 0x00f6, 0x015b, 0x0171, 0x00f9,
+  0x00fa, 0x00fb, 0x00fc, 0x0119, 0x021b, 0x00ff,
+];
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn default_table() {
+    assert_eq!(decode(b"BBC One"), "BBC One");
+    // Diacritical marks precede the letter.
+    assert_eq!(decode(b"Fran\xcbcais \xc2e\xc8e"), "Français éë");
+    assert_eq!(decode(b"\xc2q"), "q\u{301}");
+    // Emphasis control codes are dropped.
+    assert_eq!(decode(b"\x86News\x87\x8aToday"), "News\nToday");
+    assert_eq!(decode(b"Stra\xfbe \xa8"), "Straße ¤");
+  }
+
+  #[test]
+  fn iso_8859() {
+    // ISO/IEC 8859-5 (Cyrillic), selected by 0x01.
+    assert_eq!(decode(b"\x01\xbf\xd5\xe0\xd2\xeb\xd9"), "Первый");
+    // ISO/IEC 8859-7 (Greek), selected by 0x10 0x00 0x07.
+    assert_eq!(decode(b"\x10\x00\x07\xc5\xd1\xd4 1"), "ΕΡΤ 1");
+    assert_eq!(decode(b"\x10\x00\x01caf\xe9"), "café");
+    assert_eq!(decode(b"\x10\x00\x0fprix 5\xa4"), "prix 5€");
+  }
+
+  #[test]
+  fn multi_byte() {
+    assert_eq!(decode(b"\x11\x00A\x04\x1f\xe0\x8a\x00B"), "AП\nB");
+    assert_eq!(decode(b"\x15caf\xc3\xa9"), "café");
+    assert_eq!(decode(b"\x13AB\xb0\xa1"), "AB\u{FFFD}\u{FFFD}");
+    assert_eq!(decode(b""), "");
+  }
+}
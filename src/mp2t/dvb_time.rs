@@ -0,0 +1,124 @@
+//! Decoding of the times and durations of DVB SI, as specified in ETSI EN 300
+//! 468 Annex C.
+
+use twiddle::Twiddle;
+
+// The Modified Julian Date of 1970-01-01.
+const MJD_UNIX_EPOCH: i64 = 40587;
+
+/// Decodes a UTC time coded as the 16 LSBs of the Modified Julian Date,
+/// followed by hours, minutes and seconds as 6 BCD digits. Returns the number
+/// of seconds since the Unix epoch, or `None` if the time is undefined (all
+/// bits set) or malformed.
+pub fn utc_time(v: u64) -> Option<i64> {
+  if v.bits(39..=0) == 0xFF_FFFF_FFFF {
+    return None;
+  }
+  let mjd = v.bits(39..=24) as i64;
+  let secs = bcd_time(v.bits(23..=0) as u32)?;
+  Some((mjd - MJD_UNIX_EPOCH) * 86400 + secs as i64)
+}
+
+/// Decodes a duration coded as hours, minutes and seconds in 6 BCD digits.
+/// Returns the number of seconds, or `None` if the duration is undefined (all
+/// bits set) or malformed.
+pub fn duration(v: u32) -> Option<u32> {
+  if v.bits(23..=0) == 0xFF_FFFF {
+    return None;
+  }
+  bcd_time(v)
+}
+
+fn bcd_time(v: u32) -> Option<u32> {
+  let h = bcd_byte(v.bits(23..=16) as u8)?;
+  let m = bcd_byte(v.bits(15..=8) as u8)?;
+  let s = bcd_byte(v.bits(7..=0) as u8)?;
+  Some(h * 3600 + m * 60 + s)
+}
+
+fn bcd_byte(b: u8) -> Option<u32> {
+  let (hi, lo) = (b.bits(7..=4) as u32, b.bits(3..=0) as u32);
+  if hi > 9 || lo > 9 {
+    return None;
+  }
+  Some(hi * 10 + lo)
+}
+
+/// A UTC time broken down into its calendar date and time of day.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CivilTime {
+  pub year: i64,
+  pub month: u32,
+  pub day: u32,
+  pub hour: u32,
+  pub minute: u32,
+  pub second: u32,
+}
+
+/// Converts a number of seconds since the Unix epoch to a calendar date and
+/// time of day, in the proleptic Gregorian calendar.
+pub fn civil_time(t: i64) -> CivilTime {
+  let days = t.div_euclid(86400);
+  let secs = t.rem_euclid(86400) as u32;
+
+  // From Howard Hinnant's civil_from_days.
+  let z = days + 719468;
+  let era = z.div_euclid(146097);
+  let doe = z.rem_euclid(146097);
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+  let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+  CivilTime {
+    year,
+    month,
+    day,
+    hour: secs / 3600,
+    minute: secs / 60 % 60,
+    second: secs % 60,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn mjd() {
+    // The example of ETSI EN 300 468 Annex C: 93/10/13 12:45:00.
+    let t = utc_time(0xC079_124500).unwrap();
+    assert_eq!(t, 750_516_300);
+    assert_eq!(
+      civil_time(t),
+      CivilTime {
+        year: 1993,
+        month: 10,
+        day: 13,
+        hour: 12,
+        minute: 45,
+        second: 0,
+      }
+    );
+
+    assert_eq!(utc_time(0xFF_FFFF_FFFF), None);
+    assert_eq!(utc_time(0xC079_12A500), None);
+  }
+
+  #[test]
+  fn durations() {
+    assert_eq!(duration(0x01_45_30), Some(6330));
+    assert_eq!(duration(0xFF_FFFF), None);
+  }
+
+  #[test]
+  fn civil() {
+    assert_eq!(civil_time(0).year, 1970);
+    let t = civil_time(951_782_400); // 2000-02-29
+    assert_eq!((t.year, t.month, t.day), (2000, 2, 29));
+    let t = civil_time(-1);
+    assert_eq!((t.year, t.month, t.day, t.second), (1969, 12, 31, 59));
+  }
+}
@@ -0,0 +1,255 @@
+use crate::mp2t::demuxer::{Context, Event};
+use crate::mp2t::desc;
+use crate::mp2t::dvb_text;
+use crate::mp2t::dvb_time;
+use crate::mp2t::psi_parser::SectionHandler;
+use crate::mp2t::{Eit, EitEvent, StreamDesc};
+use bytes::Buf;
+use std::collections::hash_map::HashMap;
+use std::ops::RangeInclusive;
+use twiddle::Twiddle;
+
+// ETSI EN 300 468 Table 2: EIT present/following (actual and other) and EIT
+// schedule (actual and other).
+const EIT_TABLE_IDS: RangeInclusive<u8> = 0x4E..=0x6F;
+
+// Identifies a section of an EIT: table_id, service_id, transport_stream_id,
+// original_network_id and section_number.
+type SectionKey = (u8, u16, u16, u16, u8);
+
+/// Parses the sections of the EIT. Unlike other tables, EIT sections are
+/// reported as they arrive rather than collected into tables: schedules span
+/// many sections sent at a slow pace, and are only ever partially received.
+pub struct EitParser {
+  // The version_number of the sections already reported.
+  versions: HashMap<SectionKey, u8>,
+}
+
+impl EitParser {
+  pub fn new() -> EitParser {
+    EitParser {
+      versions: HashMap::new(),
+    }
+  }
+
+  fn parse(&mut self, ctx: &mut Context, section: &[u8]) -> bool {
+    // Skip table_id and section_length, and drop the CRC.
+    if section.len() < 3 + 11 + 4 {
+      return false;
+    }
+    let table_id = section[0];
+    let mut buf = &section[3..section.len() - 4];
+
+    let mut eit = Eit {
+      table_id,
+      service_id: buf.get_u16(),
+      ..Default::default()
+    };
+    let b = buf.get_u8();
+    eit.version = b.bits(5..=1);
+    eit.current_next = b.bit(0);
+    eit.section_number = buf.get_u8();
+    eit.last_section_number = buf.get_u8();
+    eit.transport_stream_id = buf.get_u16();
+    eit.original_network_id = buf.get_u16();
+    eit.segment_last_section_number = buf.get_u8();
+    eit.last_table_id = buf.get_u8();
+
+    if !eit.current_next {
+      return true;
+    }
+    let key = (
+      table_id,
+      eit.service_id,
+      eit.transport_stream_id,
+      eit.original_network_id,
+      eit.section_number,
+    );
+    if self.versions.get(&key) == Some(&eit.version) {
+      return true;
+    }
+
+    // Each event is made of event_id (16 bits), start_time (40 bits),
+    // duration (24 bits), then:
+    //
+    //  1          0
+    // 54321098 76543210
+    // rrrfllll llllllll
+    //
+    // r: running_status
+    // f: free_CA_mode
+    // l: descriptors_loop_length
+    while buf.len() >= 12 {
+      let event_id = buf.get_u16();
+      let start_time = buf.get_uint(5);
+      let duration = buf.get_uint(3) as u32;
+      let flags = buf.get_u16();
+      let descs_len = flags.bits(11..=0) as usize;
+      if descs_len > buf.len() {
+        return false;
+      }
+      let descs = match desc::parse_descs(&buf[..descs_len]) {
+        Some(descs) => descs,
+        None => return false,
+      };
+      buf.advance(descs_len);
+
+      let mut event = EitEvent {
+        event_id,
+        start_time: dvb_time::utc_time(start_time),
+        duration: dvb_time::duration(duration),
+        running_status: flags.bits(15..=13) as u8,
+        free_ca_mode: flags.bit(12),
+        ..Default::default()
+      };
+      describe_event(&mut event, &descs);
+      event.descs = descs;
+      eit.events.push(event);
+    }
+
+    self.versions.insert(key, eit.version);
+    ctx.events.push_back(Event::Eit(eit));
+    true
+  }
+}
+
+// Fills the title, texts and contents of an event from its descriptors.
+fn describe_event(event: &mut EitEvent, descs: &[StreamDesc]) {
+  let mut extended_text = String::new();
+  for desc in descs {
+    match desc {
+      StreamDesc::ShortEvent(ref short) if event.title.is_none() => {
+        event.language = Some(short.language.clone());
+        event.title = Some(dvb_text::decode(&short.event_name));
+        event.short_text = Some(dvb_text::decode(&short.text));
+      }
+      StreamDesc::Content(ref content) => {
+        event.contents.extend_from_slice(&content.contents)
+      }
+      _ => (),
+    }
+  }
+
+  // The extended_event_descriptors are ordered by descriptor_number. Those in
+  // other languages than the title's are left out.
+  for desc in descs {
+    if let StreamDesc::ExtendedEvent(ref extended) = desc {
+      if event.language.is_none()
+        || event.language.as_ref() == Some(&extended.language)
+      {
+        extended_text.push_str(&dvb_text::decode(&extended.text));
+      }
+    }
+  }
+  if !extended_text.is_empty() {
+    event.extended_text = Some(extended_text);
+  }
+}
+
+impl SectionHandler for EitParser {
+  fn accepts(&self, table_id: u8) -> bool {
+    EIT_TABLE_IDS.contains(&table_id)
+  }
+
+  // EIT sections are long-form, and always end with a CRC_32.
+  fn has_crc(&self, _table_id: u8, _section_syntax_indicator: bool) -> bool {
+    true
+  }
+
+  fn on_section(&mut self, ctx: &mut Context, section: &[u8]) -> bool {
+    self.parse(ctx, section)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mp2t::Content;
+
+  // EIT present/following actual for service 1 with one event, starting on
+  // 1993/10/13 at 12:45:00 and lasting 1:45:30.
+  static EIT: &[u8] = &[
+    0x4e, 0xf0, 0x3a, // table_id, section_length
+    0x00, 0x01, 0xc3, 0x00, 0x01, // section header
+    0x00, 0x04, 0x30, 0x01, 0x00, 0x4e, // transport_stream_id, ...
+    0x00, 0x10, // event_id
+    0xc0, 0x79, 0x12, 0x45, 0x00, // start_time
+    0x01, 0x45, 0x30, // duration
+    0x80, 0x1f, // running_status, free_CA_mode, descriptors_loop_length
+    0x4d, 0x0c, 0x65, 0x6e, 0x67, 0x04, 0x4e, 0x65, 0x77, 0x73, 0x03, 0x41,
+    0x6c, 0x6c, // short_event_descriptor
+    0x4e, 0x0b, 0x00, 0x65, 0x6e, 0x67, 0x00, 0x05, 0x4c, 0x6f, 0x6e, 0x67,
+    0x2e, // extended_event_descriptor
+    0x54, 0x02, 0x20, 0x00, // content_descriptor
+    0x00, 0x00, 0x00, 0x00, // CRC_32 (not checked)
+  ];
+
+  #[test]
+  fn present_following() {
+    let mut ctx = Context::new();
+    let mut parser = EitParser::new();
+    assert!(parser.on_section(&mut ctx, EIT));
+    assert!(parser.on_section(&mut ctx, EIT));
+    assert_eq!(ctx.events.len(), 1);
+
+    let eit = match ctx.events[0] {
+      Event::Eit(ref eit) => eit,
+      ref e => panic!("unexpected event {:?}", e),
+    };
+    assert!(eit.actual());
+    assert!(eit.present_following());
+    assert_eq!(eit.service_id, 1);
+    assert_eq!(eit.version, 1);
+    assert_eq!(eit.section_number, 0);
+    assert_eq!(eit.last_section_number, 1);
+    assert_eq!(eit.transport_stream_id, 4);
+    assert_eq!(eit.original_network_id, 0x3001);
+    assert_eq!(eit.events.len(), 1);
+
+    let event = &eit.events[0];
+    assert_eq!(event.event_id, 0x10);
+    assert_eq!(event.start_time, Some(750_516_300));
+    assert_eq!(event.duration, Some(6330));
+    assert_eq!(event.running_status, 4);
+    assert!(!event.free_ca_mode);
+    assert_eq!(event.language.as_deref(), Some("eng"));
+    assert_eq!(event.title.as_deref(), Some("News"));
+    assert_eq!(event.short_text.as_deref(), Some("All"));
+    assert_eq!(event.extended_text.as_deref(), Some("Long."));
+    assert_eq!(
+      event.contents,
+      vec![Content {
+        content_nibbles: 0x20,
+        user_byte: 0,
+      }]
+    );
+  }
+
+  #[test]
+  fn version_change() {
+    let mut ctx = Context::new();
+    let mut parser = EitParser::new();
+    parser.on_section(&mut ctx, EIT);
+
+    let mut eit = EIT.to_vec();
+    eit[5] = 0xc5;
+    parser.on_section(&mut ctx, &eit);
+    assert_eq!(ctx.events.len(), 2);
+
+    // Other sections of the same version are reported too.
+    eit[6] = 0x01;
+    parser.on_section(&mut ctx, &eit);
+    assert_eq!(ctx.events.len(), 3);
+  }
+
+  #[test]
+  fn descriptors_overrun() {
+    let mut eit = EIT.to_vec();
+    eit[25] = 0x28;
+
+    let mut ctx = Context::new();
+    let mut parser = EitParser::new();
+    assert!(!parser.on_section(&mut ctx, &eit));
+    assert!(ctx.events.is_empty());
+  }
+}
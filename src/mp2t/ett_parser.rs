@@ -0,0 +1,112 @@
+use crate::mp2t::atsc_text;
+use crate::mp2t::demuxer::{Context, Event};
+use crate::mp2t::psi_parser::PsiHandler;
+use crate::mp2t::Ett;
+use bytes::Buf;
+use std::collections::hash_map::HashMap;
+use twiddle::Twiddle;
+
+// ATSC A/65 Table 4.2
+const ETT_TABLE_ID: u8 = 0xCC;
+
+/// Parses the ETTs found on a PID. Each ETT is a single section, and is
+/// reported once per version.
+pub struct EttParser {
+  // The version_number of the ETTs already reported, by ETM_id.
+  versions: HashMap<u32, u8>,
+}
+
+impl EttParser {
+  pub fn new() -> EttParser {
+    EttParser {
+      versions: HashMap::new(),
+    }
+  }
+
+  fn parse_psi(&mut self, ctx: &mut Context, sections: &[&[u8]]) -> bool {
+    let mut buf = sections[0];
+    if buf.len() < 10 {
+      return false;
+    }
+
+    let _ett_table_id_extension = buf.get_u16();
+    let b = buf.get_u8();
+    let _section_number = buf.get_u8();
+    let _last_section_number = buf.get_u8();
+    let mut ett = Ett {
+      version: b.bits(5..=1),
+      current_next: b.bit(0),
+      protocol_version: buf.get_u8(),
+      etm_id: buf.get_u32(),
+      ..Default::default()
+    };
+
+    if !ett.current_next || self.versions.get(&ett.etm_id) == Some(&ett.version)
+    {
+      return true;
+    }
+
+    ett.text = match atsc_text::parse_multiple_string(buf) {
+      Some(text) => text,
+      None => return false,
+    };
+
+    self.versions.insert(ett.etm_id, ett.version);
+    ctx.events.push_back(Event::Ett(ett));
+    true
+  }
+}
+
+impl PsiHandler for EttParser {
+  const TABLE_ID: u8 = ETT_TABLE_ID;
+
+  fn on_psi(&mut self, ctx: &mut Context, _table_id: u8, sections: &[&[u8]]) {
+    if !self.parse_psi(ctx, sections) {
+      ctx.stats.invalid_psi += 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // ETT of event 0x10 of source_id 4.
+  static ETT: &[u8] = &[
+    0x00, 0x00, 0xc1, 0x00, 0x00, // section header
+    0x00, // protocol_version
+    0x00, 0x04, 0x00, 0x42, // ETM_id
+    0x01, 0x65, 0x6e, 0x67, 0x01, 0x00, 0x00, 0x05, 0x4c, 0x6f, 0x6e, 0x67,
+    0x2e, // extended_text_message
+  ];
+
+  #[test]
+  fn basic() {
+    let mut ctx = Context::new();
+    let mut parser = EttParser::new();
+    parser.on_psi(&mut ctx, ETT_TABLE_ID, &[ETT]);
+    parser.on_psi(&mut ctx, ETT_TABLE_ID, &[ETT]);
+    assert_eq!(ctx.stats.invalid_psi, 0);
+    assert_eq!(ctx.events.len(), 1);
+
+    let ett = match ctx.events[0] {
+      Event::Ett(ref ett) => ett,
+      ref e => panic!("unexpected event {:?}", e),
+    };
+    assert_eq!(ett.source_id(), 4);
+    assert_eq!(ett.event_id(), Some(0x10));
+    assert_eq!(ett.text.text(), Some("Long."));
+  }
+
+  #[test]
+  fn text_overrun() {
+    let mut ett = ETT.to_vec();
+    ett[17] = 0x06;
+
+    let mut ctx = Context::new();
+    let mut parser = EttParser::new();
+    parser.on_psi(&mut ctx, ETT_TABLE_ID, &[&ett]);
+    assert!(ctx.events.is_empty());
+    assert_eq!(ctx.stats.invalid_psi, 1);
+  }
+}
@@ -0,0 +1,299 @@
+use crate::mp2t::dvb_time::civil_time;
+use crate::mp2t::{Eit, EitEvent, Sdt};
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Write;
+
+/// Identifies a DVB service across networks.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct ServiceRef {
+  pub original_network_id: u16,
+  pub transport_stream_id: u16,
+  pub service_id: u16,
+}
+
+#[derive(Default, Debug, Clone)]
+struct GuideService {
+  name: Option<String>,
+  // The events of the latest version of each EIT section, by table_id and
+  // section_number.
+  sections: BTreeMap<(u8, u8), Vec<EitEvent>>,
+}
+
+/// Program guide data of the services of the actual and other transport
+/// streams, collected from the EITs. Service names are taken from the SDTs.
+#[derive(Default, Debug, Clone)]
+pub struct Guide {
+  services: BTreeMap<ServiceRef, GuideService>,
+}
+
+impl Guide {
+  pub fn new() -> Guide {
+    Default::default()
+  }
+
+  pub fn on_eit(&mut self, eit: &Eit) {
+    let service = self
+      .services
+      .entry(ServiceRef {
+        original_network_id: eit.original_network_id,
+        transport_stream_id: eit.transport_stream_id,
+        service_id: eit.service_id,
+      })
+      .or_default();
+    // The events of the section replace those of its previous version, so that
+    // events dropped from the EIT, such as past present events, go away.
+    service
+      .sections
+      .insert((eit.table_id, eit.section_number), eit.events.clone());
+  }
+
+  pub fn on_sdt(&mut self, sdt: &Sdt) {
+    for s in &sdt.services {
+      let service = self
+        .services
+        .entry(ServiceRef {
+          original_network_id: sdt.original_network_id,
+          transport_stream_id: sdt.transport_stream_id,
+          service_id: s.service_id,
+        })
+        .or_default();
+      service.name = s.service_name.clone();
+    }
+  }
+
+  /// Returns the services that have guide data or a name.
+  pub fn services(&self) -> impl Iterator<Item = &ServiceRef> {
+    self.services.keys()
+  }
+
+  pub fn service_name(&self, service: &ServiceRef) -> Option<&str> {
+    self.services.get(service)?.name.as_deref()
+  }
+
+  /// Returns the events of a service, ordered by start time. An event found
+  /// in both the present/following and schedule EITs is taken from the
+  /// former.
+  pub fn events(&self, service: &ServiceRef) -> Vec<&EitEvent> {
+    let service = match self.services.get(service) {
+      Some(service) => service,
+      None => return Vec::new(),
+    };
+    // The present/following table_ids come first, so they are inserted last.
+    let mut by_id: BTreeMap<u16, &EitEvent> = BTreeMap::new();
+    for events in service.sections.values().rev() {
+      for event in events {
+        by_id.insert(event.event_id, event);
+      }
+    }
+    let mut events: Vec<&EitEvent> = by_id.into_values().collect();
+    events.sort_by_key(|e| (e.start_time, e.event_id));
+    events
+  }
+
+  /// Writes the guide in the XMLTV format. Events without a start time are
+  /// left out.
+  pub fn write_xmltv<W: Write>(&self, w: &mut W) -> io::Result<()> {
+    writeln!(w, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(w, "<!DOCTYPE tv SYSTEM \"xmltv.dtd\">")?;
+    writeln!(w, "<tv generator-info-name=\"transmux\">")?;
+
+    for (service_ref, service) in &self.services {
+      writeln!(w, "  <channel id=\"{}\">", channel_id(service_ref))?;
+      let name = match service.name {
+        Some(ref name) => name.clone(),
+        None => service_ref.service_id.to_string(),
+      };
+      writeln!(w, "    <display-name>{}</display-name>", escape(&name))?;
+      writeln!(w, "  </channel>")?;
+    }
+
+    for service_ref in self.services.keys() {
+      for event in self.events(service_ref) {
+        write_programme(w, service_ref, event)?;
+      }
+    }
+
+    writeln!(w, "</tv>")
+  }
+}
+
+fn write_programme<W: Write>(
+  w: &mut W,
+  service_ref: &ServiceRef,
+  event: &EitEvent,
+) -> io::Result<()> {
+  let start = match event.start_time {
+    Some(start) => start,
+    None => return Ok(()),
+  };
+  write!(w, "  <programme start=\"{}\"", xmltv_time(start))?;
+  if let Some(duration) = event.duration {
+    write!(w, " stop=\"{}\"", xmltv_time(start + duration as i64))?;
+  }
+  writeln!(w, " channel=\"{}\">", channel_id(service_ref))?;
+
+  let lang = match event.language {
+    Some(ref lang) => format!(" lang=\"{}\"", escape(lang)),
+    None => String::new(),
+  };
+  if let Some(ref title) = event.title {
+    writeln!(w, "    <title{}>{}</title>", lang, escape(title))?;
+  }
+  if let Some(ref text) = event.short_text {
+    if !text.is_empty() {
+      writeln!(w, "    <sub-title{}>{}</sub-title>", lang, escape(text))?;
+    }
+  }
+  if let Some(ref text) = event.extended_text {
+    writeln!(w, "    <desc{}>{}</desc>", lang, escape(text))?;
+  }
+  for content in &event.contents {
+    if let Some(genre) = content.genre() {
+      writeln!(w, "    <category lang=\"en\">{}</category>", escape(genre))?;
+    }
+  }
+
+  writeln!(w, "  </programme>")
+}
+
+fn channel_id(service: &ServiceRef) -> String {
+  format!(
+    "{}.{}.{}.dvb",
+    service.service_id,
+    service.transport_stream_id,
+    service.original_network_id
+  )
+}
+
+fn xmltv_time(t: i64) -> String {
+  let t = civil_time(t);
+  format!(
+    "{:04}{:02}{:02}{:02}{:02}{:02} +0000",
+    t.year, t.month, t.day, t.hour, t.minute, t.second
+  )
+}
+
+fn escape(s: &str) -> String {
+  let mut escaped = String::with_capacity(s.len());
+  for c in s.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      // Control characters are not allowed in XML 1.0.
+      c if c.is_control() && c != '\n' && c != '\t' => (),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mp2t::{Content, Service};
+
+  fn event(event_id: u16, start_time: i64, title: &str) -> EitEvent {
+    EitEvent {
+      event_id,
+      start_time: Some(start_time),
+      duration: Some(1800),
+      language: Some("eng".to_string()),
+      title: Some(title.to_string()),
+      short_text: Some(String::new()),
+      ..Default::default()
+    }
+  }
+
+  fn eit(table_id: u8, section_number: u8, events: Vec<EitEvent>) -> Eit {
+    Eit {
+      table_id,
+      section_number,
+      service_id: 1,
+      transport_stream_id: 4,
+      original_network_id: 0x3001,
+      events,
+      ..Default::default()
+    }
+  }
+
+  const SERVICE: ServiceRef = ServiceRef {
+    original_network_id: 0x3001,
+    transport_stream_id: 4,
+    service_id: 1,
+  };
+
+  #[test]
+  fn events() {
+    let mut guide = Guide::new();
+    guide.on_eit(&eit(0x50, 0, vec![event(2, 1800, "B"), event(1, 0, "A")]));
+    guide.on_eit(&eit(0x50, 8, vec![event(3, 3600, "C")]));
+    let titles = |guide: &Guide| -> Vec<String> {
+      guide
+        .events(&SERVICE)
+        .iter()
+        .map(|e| e.title.clone().unwrap())
+        .collect()
+    };
+    assert_eq!(titles(&guide), vec!["A", "B", "C"]);
+
+    // A new version of a section replaces its events: A has ended.
+    guide.on_eit(&eit(0x50, 0, vec![event(2, 1800, "B'")]));
+    assert_eq!(titles(&guide), vec!["B'", "C"]);
+
+    // The present/following EIT takes precedence over the schedule.
+    guide.on_eit(&eit(0x4e, 0, vec![event(2, 1800, "B''")]));
+    assert_eq!(titles(&guide), vec!["B''", "C"]);
+
+    assert!(guide
+      .events(&ServiceRef {
+        service_id: 2,
+        ..SERVICE
+      })
+      .is_empty());
+  }
+
+  #[test]
+  fn xmltv() {
+    let mut guide = Guide::new();
+    let mut e = event(1, 750_516_300, "News & Weather");
+    e.extended_text = Some("<Live>".to_string());
+    e.contents.push(Content {
+      content_nibbles: 0x20,
+      user_byte: 0,
+    });
+    guide.on_eit(&eit(0x50, 0, vec![e]));
+    guide.on_sdt(&Sdt {
+      transport_stream_id: 4,
+      original_network_id: 0x3001,
+      services: vec![Service {
+        service_id: 1,
+        service_name: Some("ABC 1".to_string()),
+        ..Default::default()
+      }],
+      ..Default::default()
+    });
+    assert_eq!(guide.service_name(&SERVICE), Some("ABC 1"));
+
+    let mut out = Vec::new();
+    guide.write_xmltv(&mut out).unwrap();
+    assert_eq!(
+      String::from_utf8(out).unwrap(),
+      "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+       <!DOCTYPE tv SYSTEM \"xmltv.dtd\">\n\
+       <tv generator-info-name=\"transmux\">\n  \
+       <channel id=\"1.4.12289.dvb\">\n    \
+       <display-name>ABC 1</display-name>\n  \
+       </channel>\n  \
+       <programme start=\"19931013124500 +0000\" \
+       stop=\"19931013131500 +0000\" channel=\"1.4.12289.dvb\">\n    \
+       <title lang=\"eng\">News &amp; Weather</title>\n    \
+       <desc lang=\"eng\">&lt;Live&gt;</desc>\n    \
+       <category lang=\"en\">News/Current affairs</category>\n  \
+       </programme>\n\
+       </tv>\n"
+    );
+  }
+}
@@ -0,0 +1,183 @@
+use crate::mp2t::demuxer::{Context, Event};
+use crate::mp2t::desc;
+use crate::mp2t::psi_parser::{PsiHandler, TableVersions};
+use crate::mp2t::{Mgt, MgtTable};
+use bytes::Buf;
+use twiddle::Twiddle;
+
+// ATSC A/65 Table 4.2
+pub const MGT_TABLE_ID: u8 = 0xC7;
+
+pub struct MgtParser {
+  versions: TableVersions<Mgt>,
+}
+
+impl MgtParser {
+  pub fn new() -> MgtParser {
+    MgtParser {
+      versions: TableVersions::new(),
+    }
+  }
+
+  fn parse_psi(&mut self, ctx: &mut Context, sections: &[&[u8]]) -> bool {
+    // The MGT is always a single section.
+    let mut buf = sections[0];
+
+    if buf.len() < 8 {
+      return false;
+    }
+
+    let _table_id_extension = buf.get_u16();
+    let b = buf.get_u8();
+    let _section_number = buf.get_u8();
+    let _last_section_number = buf.get_u8();
+    let mut mgt = Mgt {
+      version: b.bits(5..=1),
+      current_next: b.bit(0),
+      protocol_version: buf.get_u8(),
+      ..Default::default()
+    };
+
+    //  1          0
+    // 54321098 76543210
+    // ...ppppp pppppppp table_type_PID
+    // ...vvvvv          table_type_version_number
+    // ....llll llllllll table_type_descriptors_length
+    let tables_defined = buf.get_u16();
+    for _ in 0..tables_defined {
+      if buf.len() < 11 {
+        return false;
+      }
+      let table_type = buf.get_u16();
+      let pid = buf.get_u16().bits(12..=0);
+      let version = buf.get_u8().bits(4..=0);
+      let number_bytes = buf.get_u32();
+      let descs_len = buf.get_u16().bits(11..=0) as usize;
+      if descs_len > buf.len() {
+        return false;
+      }
+      let descs = match desc::parse_descs(&buf[..descs_len]) {
+        Some(descs) => descs,
+        None => return false,
+      };
+      buf.advance(descs_len);
+
+      mgt.tables.push(MgtTable {
+        table_type,
+        pid,
+        version,
+        number_bytes,
+        descs,
+      });
+    }
+
+    if buf.len() < 2 {
+      return false;
+    }
+    let descs_len = buf.get_u16().bits(11..=0) as usize;
+    if descs_len > buf.len() {
+      return false;
+    }
+    match desc::parse_descs(&buf[..descs_len]) {
+      Some(descs) => mgt.descs = descs,
+      None => return false,
+    }
+
+    let (version, current_next) = (mgt.version, mgt.current_next);
+    if let Some(update) = self.versions.update(mgt, version, current_next) {
+      ctx.events.push_back(Event::Mgt {
+        new: update.new,
+        old: update.old,
+        versions: update.versions,
+      });
+    }
+
+    true
+  }
+}
+
+impl PsiHandler for MgtParser {
+  const TABLE_ID: u8 = MGT_TABLE_ID;
+
+  fn on_psi(&mut self, ctx: &mut Context, _table_id: u8, sections: &[&[u8]]) {
+    if !self.parse_psi(ctx, sections) {
+      ctx.stats.invalid_psi += 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // MGT listing the TVCT, the EIT-0 on PID 0x1D00 and the channel ETT on PID
+  // 0x1E00.
+  static MGT: &[u8] = &[
+    0x00, 0x00, 0xc3, 0x00, 0x00, // section header
+    0x00, // protocol_version
+    0x00, 0x03, // tables_defined
+    0x00, 0x00, 0xff, 0xfb, 0xe1, 0x00, 0x00, 0x00, 0x40, 0xf0,
+    0x00, // TVCT
+    0x01, 0x00, 0xfd, 0x00, 0xe2, 0x00, 0x00, 0x02, 0x00, 0xf0,
+    0x00, // EIT-0
+    0x00, 0x04, 0xfe, 0x00, 0xe0, 0x00, 0x00, 0x00, 0x80, 0xf0,
+    0x00, // channel ETT
+    0xf0, 0x00, // descriptors_length
+  ];
+
+  #[test]
+  fn basic() {
+    let mut ctx = Context::new();
+    let mut parser = MgtParser::new();
+    parser.on_psi(&mut ctx, MGT_TABLE_ID, &[MGT]);
+    parser.on_psi(&mut ctx, MGT_TABLE_ID, &[MGT]);
+    assert_eq!(ctx.stats.invalid_psi, 0);
+    assert_eq!(ctx.events.len(), 1);
+
+    let mgt = match ctx.events[0] {
+      Event::Mgt { ref new, .. } => new,
+      ref e => panic!("unexpected event {:?}", e),
+    };
+    assert_eq!(mgt.version, 1);
+    assert_eq!(
+      mgt.tables,
+      vec![
+        MgtTable {
+          table_type: 0x0000,
+          pid: 0x1FFB,
+          version: 1,
+          number_bytes: 0x40,
+          descs: Vec::new(),
+        },
+        MgtTable {
+          table_type: 0x0100,
+          pid: 0x1D00,
+          version: 2,
+          number_bytes: 0x200,
+          descs: Vec::new(),
+        },
+        MgtTable {
+          table_type: 0x0004,
+          pid: 0x1E00,
+          version: 0,
+          number_bytes: 0x80,
+          descs: Vec::new(),
+        },
+      ]
+    );
+    assert!(mgt.tables[1].is_eit());
+    assert!(mgt.tables[2].is_ett());
+  }
+
+  #[test]
+  fn table_overrun() {
+    let mut mgt = MGT.to_vec();
+    mgt[7] = 0x04;
+
+    let mut ctx = Context::new();
+    let mut parser = MgtParser::new();
+    parser.on_psi(&mut ctx, MGT_TABLE_ID, &[&mgt]);
+    assert!(ctx.events.is_empty());
+    assert_eq!(ctx.stats.invalid_psi, 1);
+  }
+}
@@ -1,15 +1,34 @@
 use std::fmt;
 
+mod atsc_eit_parser;
+mod atsc_text;
+mod cat_parser;
 mod desc;
+mod dvb_text;
+mod dvb_time;
+mod eit_parser;
+mod ett_parser;
+mod guide;
+mod mgt_parser;
+mod nit_parser;
 mod pat_parser;
+mod pes_parser;
 mod pid_control;
 mod pmt_parser;
+mod psi_demux;
 mod psi_parser;
+mod sdt_parser;
+mod stt_parser;
+mod time_parser;
 mod ts_parser;
+mod vct_parser;
 
 pub mod demuxer;
 
 pub use desc::*;
+pub use dvb_time::{civil_time, CivilTime};
+pub use guide::{Guide, ServiceRef};
+pub use ts_parser::SyncConfig;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct StreamType(u32);
@@ -26,6 +45,14 @@ impl fmt::Debug for StreamType {
   }
 }
 
+impl StreamType {
+  /// Returns whether the elementary stream is carried in PES packets, as
+  /// opposed to private sections.
+  pub fn is_pes(&self) -> bool {
+    !matches!(*self, PRIVATE_SECTIONS | SCTE35)
+  }
+}
+
 macro_rules! stream_type {
   ( $( $n:ident = $v:expr ),* $(,)? ) => {
     $(
@@ -48,6 +75,7 @@ stream_type![
   MPEG2_VIDEO = 0x02,
   MPEG1_AUDIO = 0x03,
   MPEG2_AUDIO = 0x04,
+  PRIVATE_SECTIONS = 0x05,
   PES_PRIVATE_DATA = 0x06,
   ADTS_AAC = 0x0F,
   METADATA = 0x15,
@@ -84,19 +112,379 @@ pub struct ProgramInfo {
   pub pid: u16,
 }
 
+/// The Conditional Access Table, as specified in ISO/IEC 13818-1 2.4.4.6.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct Cat {
+  pub version: u8,
+  pub current_next: bool,
+  /// The CA descriptors of the CAT point to the EMM PIDs.
+  pub descs: Vec<StreamDesc>,
+}
+
+/// The Service Description Table, as specified in ETSI EN 300 468 5.2.3.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct Sdt {
+  /// Whether the SDT describes the transport stream it is carried in
+  /// (table_id 0x42), rather than another one (table_id 0x46).
+  pub actual: bool,
+  pub transport_stream_id: u16,
+  pub original_network_id: u16,
+  pub version: u8,
+  pub current_next: bool,
+  pub services: Vec<Service>,
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct Service {
+  /// Same as the program_number of the service in the PAT.
+  pub service_id: u16,
+  pub eit_schedule: bool,
+  pub eit_present_following: bool,
+  /// 1: not running, 2: starts in a few seconds, 3: pausing, 4: running, 5:
+  /// service off-air.
+  pub running_status: u8,
+  /// Whether some of the components of the service are scrambled.
+  pub free_ca_mode: bool,
+  /// From the service_descriptor, if any.
+  pub service_type: Option<u8>,
+  pub provider_name: Option<String>,
+  pub service_name: Option<String>,
+  pub descs: Vec<StreamDesc>,
+}
+
+/// The Network Information Table, as specified in ETSI EN 300 468 5.2.1.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct Nit {
+  /// Whether the NIT describes the network the transport stream belongs to
+  /// (table_id 0x40), rather than another one (table_id 0x41).
+  pub actual: bool,
+  pub network_id: u16,
+  pub version: u8,
+  pub current_next: bool,
+  /// From the network_name_descriptor, if any.
+  pub network_name: Option<String>,
+  pub descs: Vec<StreamDesc>,
+  pub transport_streams: Vec<NitTransportStream>,
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct NitTransportStream {
+  pub transport_stream_id: u16,
+  pub original_network_id: u16,
+  /// The delivery system, service list and logical channel descriptors of the
+  /// transport stream, among others.
+  pub descs: Vec<StreamDesc>,
+}
+
+impl NitTransportStream {
+  /// Returns the logical channel numbers of the services of the transport
+  /// stream.
+  pub fn logical_channels(&self) -> impl Iterator<Item = &LogicalChannel> {
+    self.descs.iter().flat_map(|desc| match desc {
+      StreamDesc::LogicalChannel(lcn) => lcn.channels.iter(),
+      _ => [].iter(),
+    })
+  }
+}
+
+/// A section of an Event Information Table, as specified in ETSI EN 300 468
+/// 5.2.4. EIT schedules are large and sent in segments, so each section is
+/// reported on its own.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct Eit {
+  pub table_id: u8,
+  pub service_id: u16,
+  pub transport_stream_id: u16,
+  pub original_network_id: u16,
+  pub version: u8,
+  pub current_next: bool,
+  pub section_number: u8,
+  pub last_section_number: u8,
+  pub segment_last_section_number: u8,
+  pub last_table_id: u8,
+  pub events: Vec<EitEvent>,
+}
+
+impl Eit {
+  /// Whether the EIT describes the actual transport stream, rather than
+  /// another one.
+  pub fn actual(&self) -> bool {
+    matches!(self.table_id, 0x4E | 0x50..=0x5F)
+  }
+
+  /// Whether the EIT carries the present and following events, rather than
+  /// the schedule.
+  pub fn present_following(&self) -> bool {
+    matches!(self.table_id, 0x4E | 0x4F)
+  }
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct EitEvent {
+  pub event_id: u16,
+  /// In seconds since the Unix epoch, if defined.
+  pub start_time: Option<i64>,
+  /// In seconds, if defined.
+  pub duration: Option<u32>,
+  /// 1: not running, 2: starts in a few seconds, 3: pausing, 4: running, 5:
+  /// service off-air.
+  pub running_status: u8,
+  pub free_ca_mode: bool,
+  /// From the first short_event_descriptor, if any.
+  pub language: Option<String>,
+  pub title: Option<String>,
+  pub short_text: Option<String>,
+  /// The concatenated texts of the extended_event_descriptors in the same
+  /// language as the short_event_descriptor.
+  pub extended_text: Option<String>,
+  /// From the content_descriptors.
+  pub contents: Vec<Content>,
+  pub descs: Vec<StreamDesc>,
+}
+
+/// The current UTC time, as signaled by the Time and Date Table (TDT) or by
+/// the Time Offset Table (TOT), as specified in ETSI EN 300 468 5.2.5 and
+/// 5.2.6.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct Time {
+  /// Whether the time comes from a TOT rather than a TDT.
+  pub tot: bool,
+  /// In seconds since the Unix epoch.
+  pub utc_time: i64,
+  /// The descriptors of the TOT.
+  pub descs: Vec<StreamDesc>,
+}
+
+impl Time {
+  /// Returns the local time offsets of the TOT.
+  pub fn local_time_offsets(&self) -> impl Iterator<Item = &LocalTimeOffset> {
+    self.descs.iter().flat_map(|desc| match desc {
+      StreamDesc::LocalTimeOffset(desc) => desc.offsets.iter(),
+      _ => [].iter(),
+    })
+  }
+}
+
+/// A Master Guide Table, as specified in ATSC A/65 6.2. It lists the other
+/// PSIP tables and the PIDs that carry them.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct Mgt {
+  pub version: u8,
+  pub current_next: bool,
+  pub protocol_version: u8,
+  pub tables: Vec<MgtTable>,
+  pub descs: Vec<StreamDesc>,
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct MgtTable {
+  /// ATSC A/65 Table 6.3, e.g. 0x0000 for the TVCT or 0x0100 for the EIT-0.
+  pub table_type: u16,
+  pub pid: u16,
+  pub version: u8,
+  pub number_bytes: u32,
+  pub descs: Vec<StreamDesc>,
+}
+
+impl MgtTable {
+  /// Whether the table is one of the EIT-0 to EIT-127.
+  pub fn is_eit(&self) -> bool {
+    matches!(self.table_type, 0x0100..=0x017F)
+  }
+
+  /// Whether the table is the channel ETT or one of the event ETT-0 to
+  /// ETT-127.
+  pub fn is_ett(&self) -> bool {
+    matches!(self.table_type, 0x0004 | 0x0200..=0x027F)
+  }
+}
+
+/// A terrestrial or cable Virtual Channel Table, as specified in ATSC A/65
+/// 6.3.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct Vct {
+  /// Whether this is a CVCT rather than a TVCT.
+  pub cable: bool,
+  pub transport_stream_id: u16,
+  pub version: u8,
+  pub current_next: bool,
+  pub protocol_version: u8,
+  pub channels: Vec<VirtualChannel>,
+  pub descs: Vec<StreamDesc>,
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct VirtualChannel {
+  pub short_name: String,
+  pub major_channel_number: u16,
+  pub minor_channel_number: u16,
+  pub modulation_mode: u8,
+  pub carrier_frequency: u32,
+  pub channel_tsid: u16,
+  pub program_number: u16,
+  pub etm_location: u8,
+  pub access_controlled: bool,
+  pub hidden: bool,
+  /// Only defined in the CVCT.
+  pub path_select: bool,
+  /// Only defined in the CVCT.
+  pub out_of_band: bool,
+  pub hide_guide: bool,
+  pub service_type: u8,
+  /// Identifies the channel in the EITs and ETTs.
+  pub source_id: u16,
+  pub descs: Vec<StreamDesc>,
+}
+
+/// A System Time Table, as specified in ATSC A/65 6.1.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct Stt {
+  pub protocol_version: u8,
+  /// In GPS seconds, since 1980-01-06 00:00:00 UTC.
+  pub system_time: u32,
+  /// The number of leap seconds between GPS and UTC.
+  pub gps_utc_offset: u8,
+  pub ds_status: bool,
+  pub ds_day_of_month: u8,
+  pub ds_hour: u8,
+  pub descs: Vec<StreamDesc>,
+}
+
+// The Unix time of the GPS epoch, 1980-01-06 00:00:00 UTC.
+const GPS_UNIX_EPOCH: i64 = 315_964_800;
+
+impl Stt {
+  /// Returns the system time, in seconds since the Unix epoch.
+  pub fn utc_time(&self) -> i64 {
+    self.to_utc(self.system_time)
+  }
+
+  /// Converts a GPS time, such as the start time of an ATSC event, to seconds
+  /// since the Unix epoch.
+  pub fn to_utc(&self, gps_time: u32) -> i64 {
+    GPS_UNIX_EPOCH + gps_time as i64 - self.gps_utc_offset as i64
+  }
+}
+
+/// The instance of an ATSC Event Information Table for one source, as
+/// specified in ATSC A/65 6.5. Each EIT-k covers 3 hours of the guide, and is
+/// carried on the PID listed in the MGT.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct AtscEit {
+  pub source_id: u16,
+  pub version: u8,
+  pub current_next: bool,
+  pub protocol_version: u8,
+  pub events: Vec<AtscEvent>,
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct AtscEvent {
+  pub event_id: u16,
+  /// In GPS seconds. See `Stt::to_utc`.
+  pub start_time: u32,
+  pub etm_location: u8,
+  pub length_in_seconds: u32,
+  pub title: MultipleString,
+  pub descs: Vec<StreamDesc>,
+}
+
+/// An Extended Text Table, as specified in ATSC A/65 6.6. It holds the
+/// description of a channel or of an event.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct Ett {
+  pub version: u8,
+  pub current_next: bool,
+  pub protocol_version: u8,
+  pub etm_id: u32,
+  pub text: MultipleString,
+}
+
+impl Ett {
+  pub fn source_id(&self) -> u16 {
+    (self.etm_id >> 16) as u16
+  }
+
+  /// Returns the event described by the ETT, or `None` if it describes the
+  /// channel itself.
+  pub fn event_id(&self) -> Option<u16> {
+    if self.etm_id & 0x3 == 0x2 {
+      Some((self.etm_id >> 2) as u16 & 0x3FFF)
+    } else {
+      None
+    }
+  }
+}
+
+/// A multiple_string_structure, as specified in ATSC A/65 6.10: the same text
+/// in several languages.
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct MultipleString {
+  pub strings: Vec<LanguageString>,
+}
+
+impl MultipleString {
+  /// Returns the text of the first language.
+  pub fn text(&self) -> Option<&str> {
+    self.strings.first().map(|s| s.text.as_str())
+  }
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct LanguageString {
+  pub language: String,
+  pub text: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CaPidKind {
+  Ecm,
+  Emm,
+}
+
+/// A PID carrying the conditional access messages (ECMs or EMMs) of a
+/// program, for a given CA system.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CaPid {
+  pub kind: CaPidKind,
+  pub ca_system_id: u16,
+  pub pid: u16,
+}
+
 #[derive(Default, Debug, PartialEq, Eq, Clone)]
 pub struct Pmt {
   pub program_number: u16,
   pub version: u8,
   pub current_next: bool,
   pub pcr_pid: u16,
+  /// Program-level descriptors, from the program_info loop.
+  pub descs: Vec<StreamDesc>,
+  /// The raw program_info descriptor loop that `descs` was parsed from.
+  pub program_info: Vec<u8>,
   pub streams: Vec<StreamInfo>,
 }
 
+impl Pmt {
+  /// Returns the CA descriptors of the program, from both the program_info
+  /// and the ES_info loops. A program without any is not scrambled.
+  pub fn ca_descs(&self) -> impl Iterator<Item = &CaDesc> {
+    self
+      .descs
+      .iter()
+      .chain(self.streams.iter().flat_map(|s| s.descs.iter()))
+      .filter_map(|desc| match desc {
+        StreamDesc::Ca(ca) => Some(ca),
+        _ => None,
+      })
+  }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct StreamInfo {
   pub pid: u16,
   pub stream_type: StreamType,
   pub index: usize,
   pub descs: Vec<StreamDesc>,
+  /// The raw ES_info descriptor loop that `descs` was parsed from.
+  pub es_info: Vec<u8>,
 }
@@ -0,0 +1,208 @@
+use crate::mp2t::demuxer::{Context, Event};
+use crate::mp2t::desc;
+use crate::mp2t::dvb_text;
+use crate::mp2t::psi_parser::{PsiHandler, TableVersions};
+use crate::mp2t::{Nit, NitTransportStream, StreamDesc};
+use bytes::Buf;
+use std::collections::hash_map::HashMap;
+use twiddle::Twiddle;
+
+// ETSI EN 300 468 Table 2
+pub const NIT_ACTUAL_TABLE_ID: u8 = 0x40;
+pub const NIT_OTHER_TABLE_ID: u8 = 0x41;
+
+/// Parses the NITs of the actual network and of other networks.
+pub struct NitParser {
+  // NIT other describes many networks, so versions are tracked per table_id
+  // and network_id.
+  versions: HashMap<(u8, u16), TableVersions<Nit>>,
+}
+
+impl NitParser {
+  pub fn new() -> NitParser {
+    NitParser {
+      versions: HashMap::new(),
+    }
+  }
+
+  fn parse_psi(
+    &mut self,
+    ctx: &mut Context,
+    table_id: u8,
+    sections: &[&[u8]],
+  ) -> bool {
+    let mut nit = Nit {
+      actual: table_id == NIT_ACTUAL_TABLE_ID,
+      ..Default::default()
+    };
+
+    for (i, section) in sections.iter().enumerate() {
+      let mut buf = *section;
+
+      if buf.len() < 7 {
+        return false;
+      }
+
+      let network_id = buf.get_u16();
+      let b = buf.get_u8();
+      let _section_number = buf.get_u8();
+      let _last_section_number = buf.get_u8();
+
+      // All sections share the same header: take it from the first one.
+      if i == 0 {
+        nit.network_id = network_id;
+        nit.version = b.bits(5..=1);
+        nit.current_next = b.bit(0);
+      }
+
+      // Parse network descriptors.
+      let descs_len = buf.get_u16().bits(11..=0) as usize;
+      if descs_len > buf.len() {
+        return false;
+      }
+      match desc::parse_descs(&buf[..descs_len]) {
+        Some(descs) => nit.descs.extend(descs),
+        None => return false,
+      }
+      buf.advance(descs_len);
+
+      if buf.len() < 2 {
+        return false;
+      }
+      let ts_loop_len = buf.get_u16().bits(11..=0) as usize;
+      if ts_loop_len > buf.len() {
+        return false;
+      }
+      buf = &buf[..ts_loop_len];
+
+      while buf.len() >= 6 {
+        let transport_stream_id = buf.get_u16();
+        let original_network_id = buf.get_u16();
+        let descs_len = buf.get_u16().bits(11..=0) as usize;
+        if descs_len > buf.len() {
+          return false;
+        }
+        let descs = match desc::parse_descs(&buf[..descs_len]) {
+          Some(descs) => descs,
+          None => return false,
+        };
+        buf.advance(descs_len);
+
+        nit.transport_streams.push(NitTransportStream {
+          transport_stream_id,
+          original_network_id,
+          descs,
+        });
+      }
+    }
+
+    for desc in &nit.descs {
+      if let StreamDesc::NetworkName(ref name) = desc {
+        nit.network_name = Some(dvb_text::decode(&name.name));
+      }
+    }
+
+    let (version, current_next) = (nit.version, nit.current_next);
+    let update = self
+      .versions
+      .entry((table_id, nit.network_id))
+      .or_insert_with(TableVersions::new)
+      .update(nit, version, current_next);
+    if let Some(update) = update {
+      ctx.events.push_back(Event::Nit {
+        new: update.new,
+        old: update.old,
+        versions: update.versions,
+      });
+    }
+
+    true
+  }
+}
+
+impl PsiHandler for NitParser {
+  const TABLE_ID: u8 = NIT_ACTUAL_TABLE_ID;
+
+  fn on_psi(&mut self, ctx: &mut Context, table_id: u8, sections: &[&[u8]]) {
+    if !self.parse_psi(ctx, table_id, sections) {
+      ctx.stats.invalid_psi += 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mp2t::{LogicalChannel, ServiceListDesc};
+
+  // NIT actual of network 0x3001 ("DTT") with one transport stream, carrying
+  // two services with logical channel numbers 1 and 12.
+  static NIT: &[u8] = &[
+    0x30, 0x01, 0xc3, 0x00, 0x00, // section header
+    0xf0, 0x05, // network_descriptors_length
+    0x40, 0x03, 0x44, 0x54, 0x54, // network_name_descriptor
+    0xf0, 0x18, // transport_stream_loop_length
+    0x00, 0x04, 0x30, 0x01, // transport_stream_id, original_network_id
+    0xf0, 0x12, // transport_descriptors_length
+    0x41, 0x06, 0x00, 0x01, 0x01, 0x00, 0x02, 0x02, // service_list
+    0x83, 0x08, 0x00, 0x01, 0xfc, 0x01, 0x00, 0x02, 0xfc,
+    0x0c, // logical_channel_descriptor
+  ];
+
+  #[test]
+  fn basic() {
+    let mut ctx = Context::new();
+    let mut parser = NitParser::new();
+    parser.on_psi(&mut ctx, NIT_ACTUAL_TABLE_ID, &[NIT]);
+    parser.on_psi(&mut ctx, NIT_ACTUAL_TABLE_ID, &[NIT]);
+    assert_eq!(ctx.stats.invalid_psi, 0);
+    assert_eq!(ctx.events.len(), 1);
+
+    let nit = match ctx.events[0] {
+      Event::Nit { ref new, .. } => new,
+      ref e => panic!("unexpected event {:?}", e),
+    };
+    assert!(nit.actual);
+    assert_eq!(nit.network_id, 0x3001);
+    assert_eq!(nit.version, 1);
+    assert_eq!(nit.network_name.as_deref(), Some("DTT"));
+    assert_eq!(nit.transport_streams.len(), 1);
+
+    let ts = &nit.transport_streams[0];
+    assert_eq!(ts.transport_stream_id, 4);
+    assert_eq!(ts.original_network_id, 0x3001);
+    assert_eq!(
+      ts.descs[0],
+      StreamDesc::ServiceList(ServiceListDesc {
+        services: vec![(1, 1), (2, 2)],
+      })
+    );
+    assert_eq!(
+      ts.logical_channels().collect::<Vec<_>>(),
+      vec![
+        &LogicalChannel {
+          service_id: 1,
+          visible: true,
+          logical_channel_number: 1,
+        },
+        &LogicalChannel {
+          service_id: 2,
+          visible: true,
+          logical_channel_number: 12,
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn transport_stream_loop_overrun() {
+    let mut nit = NIT.to_vec();
+    nit[13] = 0x19;
+
+    let mut ctx = Context::new();
+    let mut parser = NitParser::new();
+    parser.on_psi(&mut ctx, NIT_ACTUAL_TABLE_ID, &[&nit]);
+    assert!(ctx.events.is_empty());
+    assert_eq!(ctx.stats.invalid_psi, 1);
+  }
+}
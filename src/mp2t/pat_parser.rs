@@ -1,59 +1,72 @@
 use crate::mp2t::demuxer::{Context, Event};
-use crate::mp2t::psi_parser::PsiHandler;
+use crate::mp2t::psi_parser::{PsiHandler, TableVersions};
 use crate::mp2t::{Pat, ProgramInfo};
 use bytes::Buf;
 use twiddle::Twiddle;
 
 pub struct PatParser {
-  current: Option<Pat>,
+  versions: TableVersions<Pat>,
 }
 
 impl PatParser {
   pub fn new() -> PatParser {
-    PatParser { current: None }
+    PatParser {
+      versions: TableVersions::new(),
+    }
   }
 
-  fn parse_psi(&mut self, ctx: &mut Context, psi: &[u8]) -> bool {
-    let mut buf = psi;
+  fn parse_psi(&mut self, ctx: &mut Context, sections: &[&[u8]]) -> bool {
+    let mut pat: Pat = Default::default();
 
-    if buf.len() < 5 {
-      return false;
-    }
+    for (i, section) in sections.iter().enumerate() {
+      let mut buf = *section;
 
-    let mut pat: Pat = Default::default();
+      if buf.len() < 5 {
+        return false;
+      }
 
-    pat.transport_stream_id = buf.get_u16();
-    let b = buf.get_u8();
-    pat.version = b.bits(5..=1);
-    pat.current_next = b.bit(0);
-    pat.section = buf.get_u8();
-    pat.last_section = buf.get_u8();
-
-    while buf.len() >= 4 {
-      let program_number = buf.get_u16();
-      let pid = buf.get_u16().bits(12..=0);
-
-      if program_number == 0 {
-        pat.network_pid = Some(pid);
-      } else {
-        pat.programs.push(ProgramInfo {
-          number: program_number,
-          pid: pid,
-        });
+      let transport_stream_id = buf.get_u16();
+      let b = buf.get_u8();
+      let section_number = buf.get_u8();
+      let last_section_number = buf.get_u8();
+
+      // All sections share the same header: take it from the first one.
+      if i == 0 {
+        pat.transport_stream_id = transport_stream_id;
+        pat.version = b.bits(5..=1);
+        pat.current_next = b.bit(0);
+        pat.section = section_number;
+        pat.last_section = last_section_number;
       }
-    }
 
-    let changed = match self.current {
-      Some(ref current) => pat != *current,
-      None => true,
-    };
+      while buf.len() >= 4 {
+        let program_number = buf.get_u16();
+        let pid = buf.get_u16().bits(12..=0);
+
+        if program_number == 0 {
+          pat.network_pid = Some(pid);
+        } else {
+          pat.programs.push(ProgramInfo {
+            number: program_number,
+            pid,
+          });
+        }
+      }
+    }
 
-    if changed {
+    let (version, current_next) = (pat.version, pat.current_next);
+    if !current_next {
+      if self.versions.announce(version) {
+        ctx.events.push_back(Event::NextPat(pat));
+      }
+      return true;
+    }
+    if let Some(update) = self.versions.update(pat, version, current_next) {
       ctx.events.push_back(Event::Pat {
-        new: pat.clone(),
-        old: self.current.clone(),
+        new: update.new,
+        old: update.old,
+        versions: update.versions,
       });
-      self.current = Some(pat);
     }
 
     true
@@ -63,8 +76,8 @@ impl PatParser {
 impl PsiHandler for PatParser {
   const TABLE_ID: u8 = 0; // From ISO/IEC 13818-1: Table 2-31
 
-  fn on_psi(&mut self, ctx: &mut Context, psi: &[u8]) {
-    if !self.parse_psi(ctx, psi) {
+  fn on_psi(&mut self, ctx: &mut Context, _table_id: u8, sections: &[&[u8]]) {
+    if !self.parse_psi(ctx, sections) {
       ctx.stats.invalid_psi += 1;
     }
   }
@@ -73,12 +86,23 @@ impl PsiHandler for PatParser {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::crc;
+  use crate::mp2t::psi_parser::PsiParser;
+  use crate::mp2t::ts_parser::{TsHandler, TsPacket};
 
-  static PAT: &'static [u8] = &[
+  static PAT: &[u8] = &[
     0x00, 0x01, 0xc1, 0x00, 0x00, 0x00, 0x00, 0xe0, 0xa, 0x00, 0x01, 0xe0,
     0x64, 0x04, 0xd2, 0xe3, 0xe9,
   ];
 
+  fn pkt<'a>(payload: &'a [u8]) -> TsPacket<'a> {
+    TsPacket {
+      payload,
+      payload_start: true,
+      ..Default::default()
+    }
+  }
+
   macro_rules! assert_pattern {
     ( $input:expr, $pat:pat, $then:expr ) => {
       match $input {
@@ -92,13 +116,13 @@ mod tests {
   fn basic() {
     let mut ctx = Context::new();
     let mut parser = PatParser::new();
-    parser.parse_psi(&mut ctx, PAT);
-    parser.parse_psi(&mut ctx, PAT);
+    parser.parse_psi(&mut ctx, &[PAT]);
+    parser.parse_psi(&mut ctx, &[PAT]);
 
     assert_eq!(ctx.events.len(), 1);
     assert_pattern!(
       ctx.events[0],
-      Event::Pat{new: ref pat, old: None},
+      Event::Pat{new: ref pat, old: None, ..},
       assert_eq!(
         pat,
         &Pat {
@@ -122,4 +146,101 @@ mod tests {
       )
     );
   }
+
+  #[test]
+  fn multiple_sections() {
+    let mut ctx = Context::new();
+    let mut parser = PatParser::new();
+    parser.parse_psi(
+      &mut ctx,
+      &[
+        &[0x00, 0x01, 0xc3, 0x00, 0x01, 0x00, 0x01, 0xe0, 0x64],
+        &[0x00, 0x01, 0xc3, 0x01, 0x01, 0x00, 0x02, 0xe0, 0xc8],
+      ],
+    );
+
+    assert_eq!(ctx.events.len(), 1);
+    assert_pattern!(
+      ctx.events[0],
+      Event::Pat{new: ref pat, old: None, ..},
+      assert_eq!(
+        pat,
+        &Pat {
+          transport_stream_id: 1,
+          version: 1,
+          current_next: true,
+          section: 0,
+          last_section: 1,
+          network_pid: None,
+          programs: vec![
+            ProgramInfo {
+              number: 1,
+              pid: 100,
+            },
+            ProgramInfo {
+              number: 2,
+              pid: 200,
+            },
+          ],
+        }
+      )
+    );
+  }
+
+  #[test]
+  fn next_version() {
+    let mut ctx = Context::new();
+    let mut parser = PatParser::new();
+    parser.parse_psi(&mut ctx, &[PAT]);
+
+    // Version 1 is announced with current_next_indicator = 0.
+    let mut next = PAT.to_vec();
+    next[2] = 0xc2;
+    next[13] = 0x00;
+    next[14] = 0x02;
+    parser.parse_psi(&mut ctx, &[&next]);
+    parser.parse_psi(&mut ctx, &[&next]);
+    assert_eq!(ctx.events.len(), 2);
+    assert_pattern!(ctx.events[1], Event::NextPat(ref pat), {
+      assert_eq!(pat.version, 1);
+      assert_eq!(pat.programs[1].number, 2);
+    });
+
+    next[2] = 0xc3;
+    parser.parse_psi(&mut ctx, &[&next]);
+    assert_eq!(ctx.events.len(), 3);
+    assert_pattern!(
+      ctx.events[2],
+      Event::Pat{new: ref pat, old: Some(ref old), ref versions},
+      {
+        assert_eq!((old.version, pat.version), (0, 1));
+        assert_eq!(pat.programs[1].number, 2);
+        assert_eq!(versions, &vec![0, 1]);
+      }
+    );
+  }
+
+  #[test]
+  fn crc_checked_without_section_syntax_indicator() {
+    let mut section = vec![0x00, 0xb0, PAT.len() as u8 + 4];
+    section.extend_from_slice(PAT);
+    let crc = crc::mpeg2(&section);
+    section.extend_from_slice(&crc.to_be_bytes());
+    let mut payload = vec![0x00];
+    payload.extend_from_slice(&section);
+
+    let mut ctx = Context::new();
+    let mut parser = PsiParser::new(PatParser::new());
+
+    // A corrupted section_syntax_indicator does not bypass the CRC.
+    payload[2] &= 0x7f;
+    parser.on_pkt(&mut ctx, &pkt(&payload));
+    assert_eq!(ctx.stats.psi_crc_errors, 1);
+    assert!(ctx.events.is_empty());
+
+    payload[2] |= 0x80;
+    parser.on_pkt(&mut ctx, &pkt(&payload));
+    assert_eq!(ctx.stats.psi_crc_errors, 1);
+    assert_eq!(ctx.events.len(), 1);
+  }
 }
@@ -0,0 +1,351 @@
+use crate::mp2t::demuxer::{Context, Event, PesPacket};
+use crate::mp2t::ts_parser::{TsHandler, TsPacket};
+use bytes::Buf;
+use twiddle::Twiddle;
+
+const PES_START_CODE_PREFIX: u32 = 0x000001;
+const PES_FIXED_HEADER_LEN: usize = 6;
+
+// ISO/IEC 13818-1 Table 2-22
+const PROGRAM_STREAM_MAP: u8 = 0xBC;
+const PADDING_STREAM: u8 = 0xBE;
+const PRIVATE_STREAM_2: u8 = 0xBF;
+const ECM_STREAM: u8 = 0xF0;
+const EMM_STREAM: u8 = 0xF1;
+const DSMCC_STREAM: u8 = 0xF2;
+const H222_1_TYPE_E_STREAM: u8 = 0xF8;
+const PROGRAM_STREAM_DIRECTORY: u8 = 0xFF;
+
+/// Implements reassembly of PES packets as specified in ISO/IEC 13818-1
+/// 2.4.3.6 for a single elementary stream.
+pub struct PesParser {
+  stream_index: usize,
+  data: Vec<u8>,
+  pos: i64,
+  started: bool,
+}
+
+impl PesParser {
+  pub fn new(stream_index: usize) -> PesParser {
+    PesParser {
+      stream_index,
+      data: Vec::new(),
+      pos: 0,
+      started: false,
+    }
+  }
+
+  // Returns the total length of the PES packet being reassembled, if known.
+  // A PES_packet_length of 0 means that the length is unbounded, and the packet
+  // only ends when the next one starts.
+  fn expected_len(&self) -> Option<usize> {
+    if self.data.len() < PES_FIXED_HEADER_LEN {
+      return None;
+    }
+    let pes_packet_len = (&self.data[4..6]).get_u16() as usize;
+    if pes_packet_len == 0 {
+      return None;
+    }
+    Some(PES_FIXED_HEADER_LEN + pes_packet_len)
+  }
+
+  fn emit(&mut self, ctx: &mut Context) {
+    if self.started {
+      match parse_pes(&self.data) {
+        Some(Pes::Packet(mut pes)) => {
+          pes.pos = self.pos;
+          pes.stream_index = self.stream_index;
+          ctx.events.push_back(Event::Pes(pes));
+        }
+        Some(Pes::Padding) => (),
+        None => ctx.stats.invalid_pes += 1,
+      }
+    }
+    self.data.clear();
+    self.started = false;
+  }
+}
+
+impl TsHandler for PesParser {
+  fn on_pkt<'p>(&mut self, ctx: &mut Context, pkt: &TsPacket<'p>) {
+    if pkt.payload_start {
+      // The start of a new PES packet terminates the previous one.
+      self.emit(ctx);
+      self.started = true;
+      self.pos = pkt.pos;
+    } else if !self.started {
+      // This is not an error: it is likely that we started the stream in the
+      // middle of a PES packet.
+      ctx.stats.skipped_unstarted_pes_pkts += 1;
+      return;
+    }
+
+    self.data.extend_from_slice(pkt.payload);
+
+    if let Some(len) = self.expected_len() {
+      if self.data.len() >= len {
+        self.emit(ctx);
+      }
+    }
+  }
+
+  fn on_reset(&mut self, _ctx: &mut Context) {
+    self.data.clear();
+    self.started = false;
+  }
+
+  fn on_eos(&mut self, ctx: &mut Context) {
+    self.emit(ctx);
+  }
+}
+
+enum Pes {
+  Packet(PesPacket),
+  Padding,
+}
+
+fn parse_pes(data: &[u8]) -> Option<Pes> {
+  let mut buf = data;
+
+  if buf.len() < PES_FIXED_HEADER_LEN {
+    return None;
+  }
+
+  if buf.get_uint(3) as u32 != PES_START_CODE_PREFIX {
+    return None;
+  }
+
+  let stream_id = buf.get_u8();
+  let pes_packet_len = buf.get_u16() as usize;
+  if pes_packet_len != 0 {
+    if pes_packet_len > buf.len() {
+      return None;
+    }
+    buf = &buf[..pes_packet_len];
+  }
+
+  let mut pes = PesPacket {
+    stream_id,
+    ..Default::default()
+  };
+
+  match stream_id {
+    PADDING_STREAM => return Some(Pes::Padding),
+
+    PROGRAM_STREAM_MAP
+    | PRIVATE_STREAM_2
+    | ECM_STREAM
+    | EMM_STREAM
+    | DSMCC_STREAM
+    | H222_1_TYPE_E_STREAM
+    | PROGRAM_STREAM_DIRECTORY => {
+      // These streams have no optional PES header: the rest is all payload.
+      pes.data = buf.to_vec();
+      return Some(Pes::Packet(pes));
+    }
+
+    _ => (),
+  }
+
+  //  2          1          0
+  // 32109876 54321098 76543210
+  // 10aabcde ffghijkl mmmmmmmm
+  //
+  // a: PES_scrambling_control
+  // b: PES_priority
+  // c: data_alignment_indicator
+  // d: copyright
+  // e: original_or_copy
+  // f: PTS_DTS_flags
+  // g: ESCR_flag
+  // h: ES_rate_flag
+  // i: DSM_trick_mode_flag
+  // j: additional_copy_info_flag
+  // k: PES_CRC_flag
+  // l: PES_extension_flag
+  // m: PES_header_data_length
+
+  if buf.len() < 3 {
+    return None;
+  }
+  let header = buf.get_uint(3);
+  if header.bits(23..=22) != 0b10 {
+    return None;
+  }
+  let pts_dts_flags = header.bits(15..=14);
+  let header_data_len = header.bits(7..=0) as usize;
+  if header_data_len > buf.len() {
+    return None;
+  }
+
+  let mut header_data = &buf[..header_data_len];
+  buf.advance(header_data_len);
+
+  match pts_dts_flags {
+    0b10 => {
+      if header_data.len() < 5 {
+        return None;
+      }
+      pes.pts = Some(parse_timestamp(&mut header_data));
+    }
+    0b11 => {
+      if header_data.len() < 10 {
+        return None;
+      }
+      pes.pts = Some(parse_timestamp(&mut header_data));
+      pes.dts = Some(parse_timestamp(&mut header_data));
+    }
+    0b00 => (),
+    _ => return None, // '01' is forbidden.
+  }
+
+  pes.data = buf.to_vec();
+  Some(Pes::Packet(pes))
+}
+
+// Parses a 33-bit PTS or DTS as specified in ISO/IEC 13818-1 2.4.3.7.
+//
+//  4          3          2          1          0
+// 98765432 10987654 32109876 54321098 76543210
+// ....aaa. bbbbbbbb bbbbbbb. cccccccc ccccccc.
+//
+// a: timestamp[32..30]
+// b: timestamp[29..15]
+// c: timestamp[14..0]
+fn parse_timestamp(buf: &mut &[u8]) -> u64 {
+  let t = buf.get_uint(5);
+  (t.bits(35..=33) << 30) | (t.bits(31..=17) << 15) | t.bits(15..=1)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // PES header with PTS=0x1_2345_6789 and DTS=0x0_1234_5678, followed by 4
+  // bytes of payload.
+  const PES_PTS_DTS: &[u8] = &[
+    0x00, 0x00, 0x01, 0xe0, 0x00, 0x11, // start code, stream_id, length
+    0x84, 0xc0, 0x0a, // flags, header_data_length
+    0x39, 0x8d, 0x15, 0xcf, 0x13, // PTS
+    0x11, 0x48, 0xd1, 0xac, 0xf1, // DTS
+    0xde, 0xad, 0xbe, 0xef, // payload
+  ];
+
+  // Video PES with unbounded length and a PTS of 0.
+  const PES_UNBOUNDED: &[u8] = &[
+    0x00, 0x00, 0x01, 0xe0, 0x00, 0x00, // start code, stream_id, length
+    0x84, 0x80, 0x05, // flags, header_data_length
+    0x21, 0x00, 0x01, 0x00, 0x01, // PTS
+    0x00, 0x00, 0x00, 0x01, 0x09, 0x10, // payload
+  ];
+
+  fn pkt<'a>(payload: &'a [u8], payload_start: bool, pos: i64) -> TsPacket<'a> {
+    TsPacket {
+      payload,
+      payload_start,
+      pos,
+      ..Default::default()
+    }
+  }
+
+  fn pes(ctx: &Context, i: usize) -> &PesPacket {
+    match ctx.events[i] {
+      Event::Pes(ref pes) => pes,
+      _ => panic!("not a PES event"),
+    }
+  }
+
+  #[test]
+  fn pts_dts() {
+    let mut ctx = Context::new();
+    let mut parser = PesParser::new(3);
+    parser.on_pkt(&mut ctx, &pkt(PES_PTS_DTS, true, 376));
+
+    assert_eq!(ctx.events.len(), 1);
+    assert_eq!(
+      pes(&ctx, 0),
+      &PesPacket {
+        pos: 376,
+        stream_index: 3,
+        stream_id: 0xe0,
+        pts: Some(0x1_2345_6789),
+        dts: Some(0x0_1234_5678),
+        data: vec![0xde, 0xad, 0xbe, 0xef],
+      }
+    );
+  }
+
+  #[test]
+  fn bounded_across_packets() {
+    let mut ctx = Context::new();
+    let mut parser = PesParser::new(0);
+    parser.on_pkt(&mut ctx, &pkt(&PES_PTS_DTS[..7], true, 0));
+    parser.on_pkt(&mut ctx, &pkt(&PES_PTS_DTS[7..20], false, 188));
+    assert!(ctx.events.is_empty());
+    parser.on_pkt(&mut ctx, &pkt(&PES_PTS_DTS[20..], false, 376));
+
+    assert_eq!(ctx.events.len(), 1);
+    assert_eq!(pes(&ctx, 0).pos, 0);
+    assert_eq!(pes(&ctx, 0).data, vec![0xde, 0xad, 0xbe, 0xef]);
+  }
+
+  #[test]
+  fn unbounded() {
+    let mut ctx = Context::new();
+    let mut parser = PesParser::new(0);
+    parser.on_pkt(&mut ctx, &pkt(PES_UNBOUNDED, true, 0));
+    parser.on_pkt(&mut ctx, &pkt(&[0xaa, 0xbb], false, 188));
+    assert!(ctx.events.is_empty());
+
+    parser.on_pkt(&mut ctx, &pkt(PES_UNBOUNDED, true, 376));
+    assert_eq!(ctx.events.len(), 1);
+    assert_eq!(pes(&ctx, 0).pts, Some(0));
+    assert_eq!(pes(&ctx, 0).dts, None);
+    assert_eq!(
+      pes(&ctx, 0).data,
+      vec![0x00, 0x00, 0x00, 0x01, 0x09, 0x10, 0xaa, 0xbb]
+    );
+
+    parser.on_eos(&mut ctx);
+    assert_eq!(ctx.events.len(), 2);
+    assert_eq!(pes(&ctx, 1).pos, 376);
+    assert_eq!(pes(&ctx, 1).data, &PES_UNBOUNDED[14..]);
+  }
+
+  #[test]
+  fn not_started() {
+    let mut ctx = Context::new();
+    let mut parser = PesParser::new(0);
+    parser.on_pkt(&mut ctx, &pkt(&[0xaa, 0xbb], false, 0));
+    parser.on_pkt(&mut ctx, &pkt(PES_PTS_DTS, true, 188));
+
+    assert_eq!(ctx.events.len(), 1);
+    assert_eq!(pes(&ctx, 0).pos, 188);
+    assert_eq!(ctx.stats.skipped_unstarted_pes_pkts, 1);
+  }
+
+  #[test]
+  fn reset() {
+    let mut ctx = Context::new();
+    let mut parser = PesParser::new(0);
+    parser.on_pkt(&mut ctx, &pkt(PES_UNBOUNDED, true, 0));
+    parser.on_reset(&mut ctx);
+    parser.on_pkt(&mut ctx, &pkt(&[0xaa, 0xbb], false, 188));
+    parser.on_eos(&mut ctx);
+
+    assert!(ctx.events.is_empty());
+    assert_eq!(ctx.stats.skipped_unstarted_pes_pkts, 1);
+  }
+
+  #[test]
+  fn invalid_start_code() {
+    let mut ctx = Context::new();
+    let mut parser = PesParser::new(0);
+    let mut data = PES_PTS_DTS.to_vec();
+    data[2] = 0x02;
+    parser.on_pkt(&mut ctx, &pkt(&data, true, 0));
+
+    assert!(ctx.events.is_empty());
+    assert_eq!(ctx.stats.invalid_pes, 1);
+  }
+}
@@ -1,11 +1,9 @@
 use crate::mp2t::demuxer::Context;
-use crate::mp2t::ts_parser::TsPacket;
-
-pub enum Event<'a> {
-  TsPacket(&'a TsPacket<'a>),
-  Reset,
-}
+use crate::mp2t::ts_parser::{TsHandler, TsPacket};
 
+/// Wraps the handler of a PID, and implements continuity_counter semantics on
+/// its behalf. Duplicate packets are dropped, and the handler is reset when
+/// packets are lost, so that it can discard any partially accumulated unit.
 pub struct PidControl<H> {
   continuity_counter: Option<u8>,
   handler: H,
@@ -13,7 +11,7 @@ pub struct PidControl<H> {
 
 impl<H> PidControl<H>
 where
-  H: for<'a> FnMut(&mut Context, Event<'a>),
+  H: TsHandler,
 {
   pub fn new(handler: H) -> PidControl<H> {
     PidControl {
@@ -22,27 +20,55 @@ where
     }
   }
 
-  pub fn parse_pkt<'a>(&mut self, ctx: &mut Context, pkt: &TsPacket<'a>) {
+  pub fn handler(&self) -> &H {
+    &self.handler
+  }
+
+  pub fn mut_handler(&mut self) -> &mut H {
+    &mut self.handler
+  }
+}
+
+impl<H> TsHandler for PidControl<H>
+where
+  H: TsHandler,
+{
+  fn on_pkt<'a>(&mut self, ctx: &mut Context, pkt: &TsPacket<'a>) {
     // Implement continuity_counter semantics as specified in
     // ISO/IEC 13818-1 2.4.3.3.
     // N.B. the empty `Payload` indicates that the adaptation_field_control was
     // '10' or '00'.
-    if pkt.payload.len() > 0 {
+    if !pkt.payload.is_empty() {
       if let Some(cc) = self.continuity_counter {
         let expected_cc = (cc + 1) % 16;
         if pkt.continuity_counter != expected_cc {
-          if pkt.continuity_counter == cc {
-            ctx.stats.duplicate_ts_packets += 1;
-            return;
+          // When the discontinuity_indicator is set, the continuity_counter
+          // may take any value (ISO/IEC 13818-1 2.4.3.5), even the previous
+          // one. This is not an error, but whatever came before can't be
+          // joined with what comes next.
+          if !pkt.discontinuity {
+            if pkt.continuity_counter == cc {
+              ctx.stats.duplicate_ts_packets += 1;
+              return;
+            }
+            ctx.stats.continuity_counter_errors += 1;
           }
-          ctx.stats.continuity_counter_errors += 1;
-          (self.handler)(ctx, Event::Reset {});
+          self.handler.on_reset(ctx);
         }
       }
       self.continuity_counter = Some(pkt.continuity_counter);
     }
 
-    (self.handler)(ctx, Event::TsPacket(pkt));
+    self.handler.on_pkt(ctx, pkt);
+  }
+
+  fn on_reset(&mut self, ctx: &mut Context) {
+    self.continuity_counter = None;
+    self.handler.on_reset(ctx);
+  }
+
+  fn on_eos(&mut self, ctx: &mut Context) {
+    self.handler.on_eos(ctx);
   }
 }
 
@@ -50,18 +76,22 @@ where
 mod tests {
   use super::*;
 
-  fn new_handler<'a>(
-    res: &'a mut Vec<String>,
-  ) -> impl for<'b> FnMut(&mut Context, Event<'b>) + 'a {
-    move |_: &mut Context, e: Event| match e {
-      Event::TsPacket(&TsPacket {
-        continuity_counter, ..
-      }) => res.push(format!("{}", continuity_counter)),
-      Event::Reset => res.push("R".to_string()),
+  #[derive(Default)]
+  struct Recorder {
+    res: Vec<String>,
+  }
+
+  impl TsHandler for Recorder {
+    fn on_pkt<'a>(&mut self, _ctx: &mut Context, pkt: &TsPacket<'a>) {
+      self.res.push(format!("{}", pkt.continuity_counter));
+    }
+
+    fn on_reset(&mut self, _ctx: &mut Context) {
+      self.res.push("R".to_string());
     }
   }
 
-  static PKT_PAYLOAD: &'static [u8] = &[1, 2, 3];
+  static PKT_PAYLOAD: &[u8] = &[1, 2, 3];
 
   fn pkt<'a>(continuity_counter: u8) -> TsPacket<'a> {
     TsPacket {
@@ -73,60 +103,112 @@ mod tests {
 
   #[test]
   fn wrap() {
-    let mut res = Vec::<String>::new();
-    let handler = new_handler(&mut res);
-
     let mut ctx = Context::new();
-    let mut pid_control = PidControl::new(handler);
+    let mut pid_control = PidControl::new(Recorder::default());
 
-    pid_control.parse_pkt(&mut ctx, &pkt(14));
-    pid_control.parse_pkt(&mut ctx, &pkt(15));
-    pid_control.parse_pkt(&mut ctx, &pkt(0));
-    pid_control.parse_pkt(&mut ctx, &pkt(1));
-    drop(pid_control);
+    pid_control.on_pkt(&mut ctx, &pkt(14));
+    pid_control.on_pkt(&mut ctx, &pkt(15));
+    pid_control.on_pkt(&mut ctx, &pkt(0));
+    pid_control.on_pkt(&mut ctx, &pkt(1));
 
-    assert_eq!(res, vec!["14", "15", "0", "1"]);
+    assert_eq!(pid_control.handler().res, vec!["14", "15", "0", "1"]);
     assert_eq!(ctx.stats.continuity_counter_errors, 0);
     assert_eq!(ctx.stats.duplicate_ts_packets, 0);
   }
 
   #[test]
   fn dup() {
-    let mut res = Vec::<String>::new();
-    let handler = new_handler(&mut res);
-
     let mut ctx = Context::new();
-    let mut pid_control = PidControl::new(handler);
+    let mut pid_control = PidControl::new(Recorder::default());
 
-    pid_control.parse_pkt(&mut ctx, &pkt(14));
-    pid_control.parse_pkt(&mut ctx, &pkt(15));
-    pid_control.parse_pkt(&mut ctx, &pkt(15));
-    pid_control.parse_pkt(&mut ctx, &pkt(0));
-    pid_control.parse_pkt(&mut ctx, &pkt(1));
-    drop(pid_control);
+    pid_control.on_pkt(&mut ctx, &pkt(14));
+    pid_control.on_pkt(&mut ctx, &pkt(15));
+    pid_control.on_pkt(&mut ctx, &pkt(15));
+    pid_control.on_pkt(&mut ctx, &pkt(0));
+    pid_control.on_pkt(&mut ctx, &pkt(1));
 
-    assert_eq!(res, vec!["14", "15", "0", "1"]);
+    assert_eq!(pid_control.handler().res, vec!["14", "15", "0", "1"]);
     assert_eq!(ctx.stats.continuity_counter_errors, 0);
     assert_eq!(ctx.stats.duplicate_ts_packets, 1);
   }
 
   #[test]
   fn discontinuity() {
-    let mut res = Vec::<String>::new();
-    let handler = new_handler(&mut res);
+    let mut ctx = Context::new();
+    let mut pid_control = PidControl::new(Recorder::default());
+
+    pid_control.on_pkt(&mut ctx, &pkt(5));
+    pid_control.on_pkt(&mut ctx, &pkt(6));
+    pid_control.on_pkt(&mut ctx, &pkt(3));
+    pid_control.on_pkt(&mut ctx, &pkt(4));
+    pid_control.on_pkt(&mut ctx, &pkt(5));
+
+    assert_eq!(
+      pid_control.handler().res,
+      vec!["5", "6", "R", "3", "4", "5"]
+    );
+    assert_eq!(ctx.stats.continuity_counter_errors, 1);
+    assert_eq!(ctx.stats.duplicate_ts_packets, 0);
+  }
 
+  #[test]
+  fn discontinuity_indicator() {
     let mut ctx = Context::new();
-    let mut pid_control = PidControl::new(handler);
+    let mut pid_control = PidControl::new(Recorder::default());
+
+    pid_control.on_pkt(&mut ctx, &pkt(5));
+    pid_control.on_pkt(&mut ctx, &pkt(6));
+    pid_control.on_pkt(
+      &mut ctx,
+      &TsPacket {
+        discontinuity: true,
+        ..pkt(3)
+      },
+    );
+    pid_control.on_pkt(&mut ctx, &pkt(4));
+
+    assert_eq!(pid_control.handler().res, vec!["5", "6", "R", "3", "4"]);
+    assert_eq!(ctx.stats.continuity_counter_errors, 0);
+    assert_eq!(ctx.stats.duplicate_ts_packets, 0);
+  }
 
-    pid_control.parse_pkt(&mut ctx, &pkt(5));
-    pid_control.parse_pkt(&mut ctx, &pkt(6));
-    pid_control.parse_pkt(&mut ctx, &pkt(3));
-    pid_control.parse_pkt(&mut ctx, &pkt(4));
-    pid_control.parse_pkt(&mut ctx, &pkt(5));
-    drop(pid_control);
+  #[test]
+  fn discontinuity_indicator_same_cc() {
+    let mut ctx = Context::new();
+    let mut pid_control = PidControl::new(Recorder::default());
+
+    pid_control.on_pkt(&mut ctx, &pkt(5));
+    pid_control.on_pkt(
+      &mut ctx,
+      &TsPacket {
+        discontinuity: true,
+        ..pkt(5)
+      },
+    );
+    pid_control.on_pkt(&mut ctx, &pkt(6));
+
+    assert_eq!(pid_control.handler().res, vec!["5", "R", "5", "6"]);
+    assert_eq!(ctx.stats.continuity_counter_errors, 0);
+    assert_eq!(ctx.stats.duplicate_ts_packets, 0);
+  }
 
-    assert_eq!(res, vec!["5", "6", "R", "3", "4", "5"]);
-    assert_eq!(ctx.stats.continuity_counter_errors, 1);
+  #[test]
+  fn no_payload() {
+    let mut ctx = Context::new();
+    let mut pid_control = PidControl::new(Recorder::default());
+
+    pid_control.on_pkt(&mut ctx, &pkt(5));
+    pid_control.on_pkt(
+      &mut ctx,
+      &TsPacket {
+        continuity_counter: 5,
+        ..Default::default()
+      },
+    );
+    pid_control.on_pkt(&mut ctx, &pkt(6));
+
+    assert_eq!(pid_control.handler().res, vec!["5", "5", "6"]);
+    assert_eq!(ctx.stats.continuity_counter_errors, 0);
     assert_eq!(ctx.stats.duplicate_ts_packets, 0);
   }
 }
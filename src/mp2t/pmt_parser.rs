@@ -1,8 +1,9 @@
 use crate::mp2t::demuxer::{Context, Event};
-use crate::mp2t::desc::{self, StreamDesc};
-use crate::mp2t::psi_parser::PsiHandler;
+use crate::mp2t::desc;
+use crate::mp2t::psi_parser::{PsiHandler, TableVersions};
 use crate::mp2t::{Pmt, StreamInfo, StreamType};
 use bytes::Buf;
+use std::collections::hash_map::HashMap;
 use twiddle::Twiddle;
 
 // ISO/IEC 13818-1 Table 2-45
@@ -16,95 +17,106 @@ const AC3_DESCRIPTOR: u8 = 106;
 const EAC3_DESCRIPTOR: u8 = 122;
 
 pub struct PmtParser {
-  current: Option<Pmt>,
+  // The PMTs of several programs may be carried on the same PID, so their
+  // versions are tracked per program_number.
+  versions: HashMap<u16, TableVersions<Pmt>>,
 }
 
 impl PmtParser {
   pub fn new() -> PmtParser {
-    PmtParser { current: None }
-  }
-
-  fn parse(&mut self, ctx: &mut Context, psi: &[u8]) -> bool {
-    let mut buf = psi;
-    if buf.len() < 9 {
-      return false;
+    PmtParser {
+      versions: HashMap::new(),
     }
+  }
 
-    let program_number = buf.get_u16();
-    let b = buf.get_u8();
-    let version = b.bits(5..=1);
-    let current_next = b.bit(0);
-    let section = buf.get_u8();
-    let last_section = buf.get_u8();
-    let pcr_pid = buf.get_u16().bits(12..=0);
+  fn parse(&mut self, ctx: &mut Context, sections: &[&[u8]]) -> bool {
+    let mut pmt = Pmt::default();
+    let mut index: usize = 0;
 
-    if section != 0 || last_section != 0 {
-      return false;
-    }
+    // A PMT is normally carried in a single section, but nothing prevents
+    // the stream loop from continuing in further sections.
+    for (i, section) in sections.iter().enumerate() {
+      let mut buf = *section;
+      if buf.len() < 9 {
+        return false;
+      }
 
-    let mut pmt = Pmt {
-      program_number,
-      version,
-      current_next,
-      pcr_pid,
-      streams: Vec::new(),
-    };
-
-    let program_info_len = buf.get_u16().bits(11..=0) as usize;
-    if program_info_len > buf.len() {
-      return false;
-    }
-    buf.advance(program_info_len);
+      let program_number = buf.get_u16();
+      let b = buf.get_u8();
+      let _section = buf.get_u8();
+      let _last_section = buf.get_u8();
+      let pcr_pid = buf.get_u16().bits(12..=0);
+
+      // All sections share the same header: take it from the first one.
+      if i == 0 {
+        pmt.program_number = program_number;
+        pmt.version = b.bits(5..=1);
+        pmt.current_next = b.bit(0);
+        pmt.pcr_pid = pcr_pid;
+      }
 
-    let mut index: usize = 0;
-    while buf.len() >= 5 {
-      let raw_stream_type = StreamType(buf.get_u8() as u32);
-      let stream_type = raw_stream_type;
-      let pid = buf.get_u16().bits(12..=0);
-      let es_info_len = buf.get_u16().bits(11..=0) as usize;
-      if es_info_len > buf.len() {
+      let program_info_len = buf.get_u16().bits(11..=0) as usize;
+      if program_info_len > buf.len() {
         return false;
       }
 
-      // Parse stream descriptors.
-      let mut es_info = &buf[..es_info_len];
-      buf.advance(es_info_len);
-
-      let mut descs = Vec::<StreamDesc>::new();
-      while es_info.len() >= 2 {
-        let desc_tag = es_info.get_u8();
-        let desc_len = es_info.get_u8() as usize;
-        if desc_len > es_info.len() {
+      // Parse program descriptors.
+      let program_info = &buf[..program_info_len];
+      match desc::parse_descs(program_info) {
+        Some(descs) => pmt.descs.extend(descs),
+        None => return false,
+      }
+      pmt.program_info.extend_from_slice(program_info);
+      buf.advance(program_info_len);
+
+      while buf.len() >= 5 {
+        let raw_stream_type = StreamType(buf.get_u8() as u32);
+        let stream_type = raw_stream_type;
+        let pid = buf.get_u16().bits(12..=0);
+        let es_info_len = buf.get_u16().bits(11..=0) as usize;
+        if es_info_len > buf.len() {
           return false;
         }
-        let desc_buf = &es_info[..desc_len];
-        if let Some(desc) = desc::parse_stream_desc(desc_tag, desc_buf) {
-          descs.push(desc);
-        }
-        es_info.advance(desc_len);
-      }
-
-      pmt.streams.push(StreamInfo {
-        pid,
-        stream_type,
-        index,
-        descs,
-      });
 
-      index += 1;
+        // Parse stream descriptors.
+        let es_info = &buf[..es_info_len];
+        let descs = match desc::parse_descs(es_info) {
+          Some(descs) => descs,
+          None => return false,
+        };
+        let es_info = es_info.to_vec();
+        buf.advance(es_info_len);
+
+        pmt.streams.push(StreamInfo {
+          pid,
+          stream_type,
+          index,
+          descs,
+          es_info,
+        });
+
+        index += 1;
+      }
     }
 
-    let changed = match self.current {
-      Some(ref current) => pmt != *current,
-      None => true,
-    };
-
-    if changed {
+    let (version, current_next) = (pmt.version, pmt.current_next);
+    let versions = self
+      .versions
+      .entry(pmt.program_number)
+      .or_insert_with(TableVersions::new);
+    if !current_next {
+      if versions.announce(version) {
+        ctx.events.push_back(Event::NextPmt(pmt));
+      }
+      return true;
+    }
+    let update = versions.update(pmt, version, current_next);
+    if let Some(update) = update {
       ctx.events.push_back(Event::Pmt {
-        new: pmt.clone(),
-        old: self.current.clone(),
+        new: update.new,
+        old: update.old,
+        versions: update.versions,
       });
-      self.current = Some(pmt);
     }
 
     true
@@ -114,9 +126,97 @@ impl PmtParser {
 impl PsiHandler for PmtParser {
   const TABLE_ID: u8 = 2; // From ISO/IEC 13818-1: Table 2-31
 
-  fn on_psi(&mut self, ctx: &mut Context, psi: &[u8]) {
-    if !self.parse(ctx, psi) {
+  fn on_psi(&mut self, ctx: &mut Context, _table_id: u8, sections: &[&[u8]]) {
+    if !self.parse(ctx, sections) {
       ctx.stats.invalid_pmt += 1;
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mp2t::{RegistrationDesc, StreamDesc};
+
+  // PMT for program 1 with a program-level registration descriptor ("CUEI"),
+  // and an AVC stream on PID 0x101 with a registration descriptor ("HDMV").
+  static PMT: &[u8] = &[
+    0x00, 0x01, 0xc1, 0x00, 0x00, // section header
+    0xe1, 0x01, // PCR_PID
+    0xf0, 0x06, // program_info_length
+    0x05, 0x04, 0x43, 0x55, 0x45, 0x49, // registration_descriptor
+    0x1b, 0xe1, 0x01, // stream_type, elementary_PID
+    0xf0, 0x06, // ES_info_length
+    0x05, 0x04, 0x48, 0x44, 0x4d, 0x56, // registration_descriptor
+  ];
+
+  fn parse(psi: &[u8]) -> Option<Pmt> {
+    let mut ctx = Context::new();
+    let mut parser = PmtParser::new();
+    parser.on_psi(&mut ctx, 2, &[psi]);
+    assert_eq!(ctx.stats.invalid_pmt, 0);
+    match ctx.events.pop_front() {
+      Some(Event::Pmt { new, .. }) => Some(new),
+      _ => None,
+    }
+  }
+
+  #[test]
+  fn program_descs() {
+    let pmt = parse(PMT).unwrap();
+    assert_eq!(pmt.program_number, 1);
+    assert_eq!(pmt.pcr_pid, 0x101);
+    assert_eq!(
+      pmt.descs,
+      vec![StreamDesc::Registration(RegistrationDesc {
+        format_id: 0x43554549,
+      })]
+    );
+    assert_eq!(pmt.streams.len(), 1);
+    assert_eq!(
+      pmt.streams[0].descs,
+      vec![StreamDesc::Registration(RegistrationDesc {
+        format_id: 0x48444d56,
+      })]
+    );
+  }
+
+  #[test]
+  fn program_descs_overrun() {
+    let mut psi = PMT.to_vec();
+    psi[10] = 0x05;
+
+    let mut ctx = Context::new();
+    let mut parser = PmtParser::new();
+    parser.on_psi(&mut ctx, 2, &[&psi]);
+    assert!(ctx.events.is_empty());
+    assert_eq!(ctx.stats.invalid_pmt, 1);
+  }
+
+  #[test]
+  fn unknown_descs() {
+    // Replace the stream's registration_descriptor with a user private one, and
+    // truncate the program's registration_descriptor so it can't be parsed.
+    let mut psi = PMT.to_vec();
+    psi[10] = 0x03;
+    psi[20] = 0xf0;
+
+    let pmt = parse(&psi).unwrap();
+    assert_eq!(
+      pmt.descs,
+      vec![StreamDesc::Unknown {
+        tag: 0x05,
+        data: vec![0x43, 0x55, 0x45],
+      }]
+    );
+    assert_eq!(pmt.program_info, &psi[9..15]);
+    assert_eq!(
+      pmt.streams[0].descs,
+      vec![StreamDesc::Unknown {
+        tag: 0xf0,
+        data: vec![0x48, 0x44, 0x4d, 0x56],
+      }]
+    );
+    assert_eq!(pmt.streams[0].es_info, &psi[20..]);
+  }
+}
@@ -0,0 +1,152 @@
+use crate::mp2t::demuxer::Context;
+use crate::mp2t::psi_parser::SectionHandler;
+
+/// Routes the sections found on a single PID to several handlers, by
+/// table_id. This allows e.g. the SDT and BAT to share PID 0x11.
+pub struct PsiDemux {
+  handlers: Vec<Box<dyn SectionHandler>>,
+}
+
+impl PsiDemux {
+  pub fn new() -> PsiDemux {
+    PsiDemux {
+      handlers: Vec::new(),
+    }
+  }
+
+  /// Adds a handler. Sections are sent to the first added handler that accepts
+  /// their table_id.
+  pub fn add<S>(&mut self, handler: S)
+  where
+    S: SectionHandler + 'static,
+  {
+    self.handlers.push(Box::new(handler));
+  }
+}
+
+impl SectionHandler for PsiDemux {
+  fn accepts(&self, table_id: u8) -> bool {
+    self.handlers.iter().any(|h| h.accepts(table_id))
+  }
+
+  fn has_crc(&self, table_id: u8, section_syntax_indicator: bool) -> bool {
+    match self.handlers.iter().find(|h| h.accepts(table_id)) {
+      Some(handler) => handler.has_crc(table_id, section_syntax_indicator),
+      None => section_syntax_indicator,
+    }
+  }
+
+  fn on_section(&mut self, ctx: &mut Context, section: &[u8]) -> bool {
+    let table_id = section[0];
+    match self.handlers.iter_mut().find(|h| h.accepts(table_id)) {
+      Some(handler) => handler.on_section(ctx, section),
+      None => true,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mp2t::psi_parser::{PsiHandler, PsiParser, TableCollector};
+  use crate::mp2t::ts_parser::{TsHandler, TsPacket};
+  use std::cell::RefCell;
+  use std::rc::Rc;
+
+  // Three single-section tables with table_ids 0x42, 0x4a and 0x46, followed by
+  // stuffing.
+  const PSI: &[u8] = &[
+    0x00, // pointer_field
+    0x42, 0xf0, 0x0a, 0x00, 0x01, 0xc1, 0x00, 0x00, 0xaa, 0xef, 0xec, 0x36,
+    0x62, // table_id 0x42
+    0x4a, 0xf0, 0x0a, 0x00, 0x02, 0xc1, 0x00, 0x00, 0xbb, 0xf2, 0x69, 0x5b,
+    0x07, // table_id 0x4a
+    0x46, 0xf0, 0x0a, 0x00, 0x03, 0xc1, 0x00, 0x00, 0xcc, 0x0e, 0x94, 0xfd,
+    0x1c, // table_id 0x46
+    0xff, 0xff, 0xff, // stuffing
+  ];
+
+  // Records the tables it receives as "<name>:<first psi specific byte>".
+  struct Recorder {
+    name: &'static str,
+    res: Rc<RefCell<Vec<String>>>,
+  }
+
+  impl PsiHandler for Recorder {
+    const TABLE_ID: u8 = 0x42;
+
+    fn on_psi(&mut self, _ctx: &mut Context, table_id: u8, sections: &[&[u8]]) {
+      self
+        .res
+        .borrow_mut()
+        .push(format!("{}:{:x}:{:x}", self.name, table_id, sections[0][5]));
+    }
+  }
+
+  #[test]
+  fn route_by_table_id() {
+    let res = Rc::new(RefCell::new(Vec::new()));
+
+    let mut demux = PsiDemux::new();
+    demux.add(TableCollector::new(Recorder {
+      name: "sdt",
+      res: res.clone(),
+    }));
+    demux.add(TableCollector::with_table_ids(
+      Recorder {
+        name: "other",
+        res: res.clone(),
+      },
+      0x46..=0x46,
+    ));
+
+    let mut ctx = Context::new();
+    let mut parser = PsiParser::with_section_handler(demux);
+    parser.on_pkt(
+      &mut ctx,
+      &TsPacket {
+        payload: PSI,
+        payload_start: true,
+        ..Default::default()
+      },
+    );
+
+    assert_eq!(*res.borrow(), vec!["sdt:42:aa", "other:46:cc"]);
+    assert_eq!(ctx.stats.ignored_psi_sections, 1);
+    assert_eq!(ctx.stats.invalid_psi, 0);
+    assert_eq!(ctx.stats.psi_crc_errors, 0);
+  }
+
+  #[test]
+  fn has_crc_of_handler() {
+    let res = Rc::new(RefCell::new(Vec::new()));
+
+    let mut demux = PsiDemux::new();
+    demux.add(TableCollector::with_table_ids(
+      Recorder {
+        name: "sdt",
+        res: res.clone(),
+      },
+      0x42..=0x46,
+    ));
+
+    // The section_syntax_indicator of the first section is cleared: its CRC
+    // is still checked, and no longer matches.
+    let mut payload = PSI.to_vec();
+    payload[2] &= 0x7f;
+
+    let mut ctx = Context::new();
+    let mut parser = PsiParser::with_section_handler(demux);
+    parser.on_pkt(
+      &mut ctx,
+      &TsPacket {
+        payload: &payload,
+        payload_start: true,
+        ..Default::default()
+      },
+    );
+
+    assert_eq!(*res.borrow(), vec!["sdt:46:cc"]);
+    assert_eq!(ctx.stats.psi_crc_errors, 1);
+  }
+}
@@ -2,28 +2,111 @@ use crate::crc;
 use crate::mp2t::demuxer::Context;
 use crate::mp2t::ts_parser::{TsHandler, TsPacket};
 use bytes::Buf;
+use std::collections::hash_map::HashMap;
+use std::ops::RangeInclusive;
+use twiddle::Twiddle;
 
+// Maximum section_length of the sections defined in ISO/IEC 13818-1 (PAT, CAT,
+// PMT, ...) and of private sections, respectively.
 const MAX_SECTION_LEN: usize = 1021;
+const MAX_PRIVATE_SECTION_LEN: usize = 4093;
+const FIRST_PRIVATE_TABLE_ID: u8 = 0x40;
+const STUFFING_BYTE: u8 = 0xFF;
+
+// Length of the section header that follows section_length in long-form
+// sections: table_id_extension, version_number, current_next_indicator,
+// section_number and last_section_number.
+const SECTION_HEADER_LEN: usize = 5;
 
 pub trait PsiHandler {
   const TABLE_ID: u8;
 
-  fn on_psi(&mut self, ctx: &mut Context, psi: &[u8]);
+  /// Called with the table_id and every section of a complete table, ordered
+  /// by section_number. Each section starts after section_length (i.e. at
+  /// table_id_extension), and excludes the CRC.
+  fn on_psi(&mut self, ctx: &mut Context, table_id: u8, sections: &[&[u8]]);
+}
+
+/// Handles the sections found on a PID.
+pub trait SectionHandler {
+  /// Returns whether the handler takes sections with the given table_id.
+  /// Sections of other tables are skipped.
+  fn accepts(&self, table_id: u8) -> bool;
+
+  /// Returns whether the sections of a table end with a CRC_32. Only
+  /// long-form sections (section_syntax_indicator = 1) do, save for
+  /// exceptions such as the DVB TOT.
+  fn has_crc(&self, _table_id: u8, section_syntax_indicator: bool) -> bool {
+    section_syntax_indicator
+  }
+
+  /// Called with every valid section of an accepted table, from table_id to
+  /// CRC_32 inclusive, if the section has one.
+  fn on_section(&mut self, ctx: &mut Context, section: &[u8]) -> bool;
+}
+
+// A multi-section table in the process of being collected.
+struct PendingTable {
+  version: u8,
+  sections: Vec<Option<Vec<u8>>>,
 }
 
-pub struct PsiParser<H> {
+/// Collects sections 0..=last_section_number of a table, and hands the table
+/// to a `PsiHandler` once it is complete.
+pub struct TableCollector<H> {
   psi_handler: H,
+  table_ids: RangeInclusive<u8>,
+  // Tables being collected, by table_id, table_id_extension and
+  // current_next_indicator.
+  tables: HashMap<(u8, u16, bool), PendingTable>,
+}
+
+impl<H> TableCollector<H>
+where
+  H: PsiHandler,
+{
+  pub fn new(handler: H) -> TableCollector<H> {
+    TableCollector::with_table_ids(handler, H::TABLE_ID..=H::TABLE_ID)
+  }
+
+  /// Creates a collector that takes all the tables in the `table_ids` range,
+  /// instead of just `H::TABLE_ID`.
+  pub fn with_table_ids(
+    handler: H,
+    table_ids: RangeInclusive<u8>,
+  ) -> TableCollector<H> {
+    TableCollector {
+      psi_handler: handler,
+      table_ids,
+      tables: HashMap::new(),
+    }
+  }
+}
+
+/// Implements a parser for PSI sections as specified in ISO/IEC 13818-1
+/// 2.4.4.
+pub struct PsiParser<S> {
+  section_handler: S,
   data: Vec<u8>,
   started: bool,
 }
 
-impl<H> PsiParser<H>
+impl<H> PsiParser<TableCollector<H>>
 where
   H: PsiHandler,
 {
-  pub fn new(handler: H) -> PsiParser<H> {
+  pub fn new(handler: H) -> PsiParser<TableCollector<H>> {
+    PsiParser::with_section_handler(TableCollector::new(handler))
+  }
+}
+
+impl<S> PsiParser<S>
+where
+  S: SectionHandler,
+{
+  pub fn with_section_handler(handler: S) -> PsiParser<S> {
     PsiParser {
-      psi_handler: handler,
+      section_handler: handler,
       data: Vec::new(),
       started: false,
     }
@@ -41,10 +124,7 @@ where
     let mut pkt_data = pkt.payload;
 
     if pkt.payload_start {
-      self.data.clear();
-      self.started = true;
-
-      if pkt_data.len() < 1 {
+      if pkt_data.is_empty() {
         return false;
       }
 
@@ -53,59 +133,262 @@ where
         return false;
       }
 
-      // The pointer_field points to where the real data is in the packet, i.e.
-      // it's the number of bytes to discard.
+      // The pointer_field points to where the first new section starts in the
+      // packet. The bytes before it are the tail of the section that started
+      // in previous packets.
+      if self.started {
+        self.data.extend_from_slice(&pkt_data[..pointer_field]);
+        if !self.parse_sections(ctx) {
+          ctx.stats.invalid_psi += 1;
+        }
+      }
       pkt_data.advance(pointer_field);
+
+      self.data.clear();
+      self.started = true;
     }
 
     self.data.extend_from_slice(pkt_data);
 
-    let mut psi = &self.data[..];
+    self.parse_sections(ctx)
+  }
 
-    if psi.len() < 3 {
-      // Not enough data to start parsing, yet.
-      return true;
+  // Parses all the complete sections accumulated in `data`. Sections can follow
+  // each other back to back until the end of the payload or until stuffing
+  // bytes are found. The incomplete section at the end, if any, is kept until
+  // more data arrives.
+  fn parse_sections(&mut self, ctx: &mut Context) -> bool {
+    let mut ok = true;
+    let mut offset = 0;
+
+    loop {
+      let mut psi = &self.data[offset..];
+
+      if !psi.is_empty() && psi[0] == STUFFING_BYTE {
+        // The rest of the packet is stuffing.
+        offset = self.data.len();
+        break;
+      }
+
+      if psi.len() < 3 {
+        // Not enough data to start parsing, yet.
+        break;
+      }
+
+      let table_id = psi[0];
+      let max_section_len = if table_id < FIRST_PRIVATE_TABLE_ID {
+        MAX_SECTION_LEN
+      } else {
+        MAX_PRIVATE_SECTION_LEN
+      };
+
+      let b = (&psi[1..3]).get_u16();
+      let section_syntax_indicator = b.bit(15);
+      let section_len = b.bits(11..=0) as usize;
+      if section_len > max_section_len {
+        return false;
+      }
+
+      let psi_len = section_len + 3;
+
+      if psi.len() < psi_len {
+        // Wait for the rest of the PSI.
+        break;
+      }
+      psi = &psi[..psi_len];
+      offset += psi_len;
+
+      if !self.section_handler.accepts(table_id) {
+        // Several tables can share a PID: this is not an error.
+        ctx.stats.ignored_psi_sections += 1;
+        continue;
+      }
+
+      let has_crc = self
+        .section_handler
+        .has_crc(table_id, section_syntax_indicator);
+      if has_crc && crc::mpeg2(psi) != 0 {
+        // The section is lost, but the ones following it are not.
+        ctx.stats.psi_crc_errors += 1;
+        ok = false;
+        continue;
+      }
+
+      ok &= self.section_handler.on_section(ctx, psi);
     }
 
-    let table_id = psi[0];
-    if table_id != H::TABLE_ID {
-      return false;
+    self.data.drain(..offset);
+    if self.data.is_empty() {
+      // The next section must be signaled by a payload_unit_start_indicator.
+      self.started = false;
     }
 
-    let section_len = ((&psi[1..3]).get_u16() & 0xfff) as usize;
-    if section_len > MAX_SECTION_LEN {
+    ok
+  }
+}
+
+impl<H> SectionHandler for TableCollector<H>
+where
+  H: PsiHandler,
+{
+  fn accepts(&self, table_id: u8) -> bool {
+    self.table_ids.contains(&table_id)
+  }
+
+  // Tables are made of long-form sections, which all end with a CRC_32. A
+  // section_syntax_indicator of 0 is a corrupted header, which the CRC catches.
+  fn has_crc(&self, _table_id: u8, _section_syntax_indicator: bool) -> bool {
+    true
+  }
+
+  // A section with a different version_number or last_section_number than
+  // those collected so far starts a new table.
+  fn on_section(&mut self, ctx: &mut Context, section: &[u8]) -> bool {
+    let table_id = section[0];
+
+    // Only keep the section data (starting after section_length) minus the
+    // CRC.
+    if section.len() < 3 + SECTION_HEADER_LEN + 4 {
+      return false;
+    }
+    let section = &section[3..section.len() - 4];
+
+    let table_id_ext = (&section[0..2]).get_u16();
+    let version = section[2].bits(5..=1);
+    let current_next = section[2].bit(0);
+    let section_number = section[3] as usize;
+    let last_section_number = section[4] as usize;
+    if section_number > last_section_number {
       return false;
     }
 
-    let psi_len = section_len + 3;
+    let key = (table_id, table_id_ext, current_next);
 
-    if psi.len() < psi_len {
-      // Wait for the rest of the PSI.
+    if last_section_number == 0 {
+      // Single-section tables (by far the most common) need no collecting.
+      self.tables.remove(&key);
+      self.psi_handler.on_psi(ctx, table_id, &[section]);
       return true;
-    } else if psi.len() > psi_len {
-      psi = &psi[..psi_len];
     }
 
-    let crc_sum = crc::mpeg2(psi);
-    if crc_sum != 0 {
-      ctx.stats.psi_crc_errors += 1;
-      return false;
+    let table = self.tables.entry(key).or_insert_with(|| PendingTable {
+      version,
+      sections: Vec::new(),
+    });
+    if table.version != version
+      || table.sections.len() != last_section_number + 1
+    {
+      table.version = version;
+      table.sections.clear();
+      table.sections.resize(last_section_number + 1, None);
+    }
+    table.sections[section_number] = Some(section.to_vec());
+
+    if table.sections.iter().all(Option::is_some) {
+      let sections: Vec<&[u8]> = table
+        .sections
+        .iter()
+        .map(|s| s.as_ref().unwrap().as_slice())
+        .collect();
+      self.psi_handler.on_psi(ctx, table_id, &sections);
+      self.tables.remove(&key);
     }
 
-    // Send to the handler the section data (starting after section_length)
-    // minus the CRC.
-    self.psi_handler.on_psi(ctx, &psi[3..psi.len() - 4]);
+    true
+  }
+}
 
-    self.data.clear();
-    self.started = false;
+/// Tracks the versions of a table, as signaled by its version_number and
+/// current_next_indicator (ISO/IEC 13818-1 2.4.4.5).
+///
+/// A table sent with current_next_indicator = 0 is only announced: it does not
+/// replace the current table until it is sent again as the current one.
+/// Tables are considered changed when their version_number changes.
+pub struct TableVersions<T> {
+  current: Option<(u8, T)>,
+  // The version_number of the next table, once announced.
+  next_version: Option<u8>,
+  // The version_numbers of the tables that were applied, oldest first.
+  history: Vec<u8>,
+}
+
+/// A change in the current version of a table.
+pub struct TableUpdate<T> {
+  pub new: T,
+  pub old: Option<T>,
+  pub versions: Vec<u8>,
+}
+
+// version_number is 5 bits, so there is no point in remembering more versions
+// than that.
+const MAX_VERSION_HISTORY: usize = 32;
 
+impl<T> TableVersions<T>
+where
+  T: Clone,
+{
+  pub fn new() -> TableVersions<T> {
+    TableVersions {
+      current: None,
+      next_version: None,
+      history: Vec::new(),
+    }
+  }
+
+  pub fn current(&self) -> Option<&T> {
+    self.current.as_ref().map(|(_, table)| table)
+  }
+
+  /// Records the version of a table sent with current_next_indicator = 0, and
+  /// returns whether it was not announced yet.
+  pub fn announce(&mut self, version: u8) -> bool {
+    let current_version = self.current.as_ref().map(|(version, _)| *version);
+    if current_version == Some(version) || self.next_version == Some(version) {
+      return false;
+    }
+    self.next_version = Some(version);
     true
   }
+
+  /// Records a newly received table, and returns the resulting update if it
+  /// is a new current version.
+  pub fn update(
+    &mut self,
+    table: T,
+    version: u8,
+    current_next: bool,
+  ) -> Option<TableUpdate<T>> {
+    if !current_next {
+      self.announce(version);
+      return None;
+    }
+
+    if let Some((current_version, _)) = self.current {
+      if current_version == version {
+        return None;
+      }
+    }
+
+    // The next table, if any, became current or was superseded.
+    self.next_version = None;
+
+    if self.history.len() == MAX_VERSION_HISTORY {
+      self.history.remove(0);
+    }
+    self.history.push(version);
+
+    let old = self.current.replace((version, table.clone()));
+    Some(TableUpdate {
+      new: table,
+      old: old.map(|(_, table)| table),
+      versions: self.history.clone(),
+    })
+  }
 }
 
-impl<H> TsHandler for PsiParser<H>
+impl<S> TsHandler for PsiParser<S>
 where
-  H: PsiHandler,
+  S: SectionHandler,
 {
   fn on_pkt<'p>(&mut self, ctx: &mut Context, pkt: &TsPacket<'p>) {
     if !self.parse(ctx, pkt) {
@@ -114,6 +397,11 @@ where
       self.started = false;
     }
   }
+
+  fn on_reset(&mut self, _ctx: &mut Context) {
+    self.data.clear();
+    self.started = false;
+  }
 }
 
 #[cfg(test)]
@@ -122,12 +410,13 @@ mod tests {
   use mockall::automock;
   use mockall::predicate::{always, eq};
 
-  const PSI: &'static [u8] = &[
+  const PSI: &[u8] = &[
     0x05, // pointer_field
     0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // skipped by pointer_field
     0x02, 0xB0, 0x0B, // table_id + section_length
-    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, // psi specific
-    0x25, 0x1c, 0xd6, 0x79, // crc
+    0x00, 0x01, 0xC1, 0x00, 0x00, // section header
+    0x05, 0x06, // psi specific
+    0x92, 0x40, 0x7b, 0x7e, // crc
     0xFF, 0xFF, 0xFF, 0xFF, // padding
   ];
 
@@ -145,11 +434,90 @@ mod tests {
 
   impl PsiHandler for StubPsiHandler {
     const TABLE_ID: u8 = 2;
-    fn on_psi(&mut self, ctx: &mut Context, psi: &[u8]) {
-      self.mock.on_psi(ctx, psi);
+    fn on_psi(&mut self, ctx: &mut Context, _table_id: u8, sections: &[&[u8]]) {
+      self.mock.on_psi(ctx, &sections.concat());
+    }
+  }
+
+  // A table with two sections (version 3), each in its own packet.
+  const MULTI_SECTION_PSI: &[&[u8]] = &[
+    &[
+      0x00, // pointer_field
+      0x02, 0xb0, 0x0a, // table_id + section_length
+      0x00, 0x07, 0xc7, 0x00, 0x01, // section header
+      0xa0, // psi specific
+      0xbf, 0x8a, 0xc2, 0x1b, // crc
+    ],
+    &[
+      0x00, // pointer_field
+      0x02, 0xb0, 0x0a, // table_id + section_length
+      0x00, 0x07, 0xc7, 0x01, 0x01, // section header
+      0xa1, // psi specific
+      0xba, 0x93, 0x73, 0x2b, // crc
+    ],
+  ];
+
+  fn multi_section_table() -> Vec<u8> {
+    let mut table = MULTI_SECTION_PSI[0][4..10].to_vec();
+    table.extend_from_slice(&MULTI_SECTION_PSI[1][4..10]);
+    table
+  }
+
+  fn section_pkt<'a>(payload: &'a [u8]) -> TsPacket<'a> {
+    TsPacket {
+      payload,
+      payload_start: true,
+      ..Default::default()
     }
   }
 
+  #[test]
+  fn multiple_sections() {
+    let mut handler: StubPsiHandler = Default::default();
+
+    handler
+      .mock
+      .expect_on_psi()
+      .withf(|_, psi| psi == &multi_section_table()[..])
+      .times(2)
+      .return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = PsiParser::new(handler);
+
+    parser.on_pkt(&mut ctx, &section_pkt(MULTI_SECTION_PSI[0]));
+    parser.on_pkt(&mut ctx, &section_pkt(MULTI_SECTION_PSI[1]));
+
+    // Sections received out of order still make up the table.
+    parser.on_pkt(&mut ctx, &section_pkt(MULTI_SECTION_PSI[1]));
+    parser.on_pkt(&mut ctx, &section_pkt(MULTI_SECTION_PSI[0]));
+
+    assert_eq!(ctx.stats.invalid_psi, 0);
+  }
+
+  #[test]
+  fn multiple_sections_version_change() {
+    let mut handler: StubPsiHandler = Default::default();
+
+    handler.mock.expect_on_psi().times(0).return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = PsiParser::new(handler);
+
+    // A section of a different version_number restarts the table, so the
+    // sections of version 2 and 3 are never combined.
+    let mut section = MULTI_SECTION_PSI[0].to_vec();
+    section[6] = 0xc5;
+    let len = section.len();
+    let crc = crc::mpeg2(&section[1..len - 4]);
+    section[len - 4..].copy_from_slice(&crc.to_be_bytes());
+
+    parser.on_pkt(&mut ctx, &section_pkt(&section));
+    parser.on_pkt(&mut ctx, &section_pkt(MULTI_SECTION_PSI[1]));
+
+    assert_eq!(ctx.stats.invalid_psi, 0);
+  }
+
   #[test]
   fn simple() {
     let mut handler: StubPsiHandler = Default::default();
@@ -169,7 +537,7 @@ mod tests {
       payload_start: true,
       ..Default::default()
     };
-    parser.on_pkt(&mut ctx, &pkt);
+    parser.on_pkt(&mut ctx, pkt);
   }
 
   #[test]
@@ -283,4 +651,140 @@ mod tests {
       },
     );
   }
+
+  #[test]
+  fn sections_back_to_back() {
+    let mut handler: StubPsiHandler = Default::default();
+
+    handler
+      .mock
+      .expect_on_psi()
+      .withf(|_, psi| psi == &multi_section_table()[..])
+      .times(1)
+      .return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = PsiParser::new(handler);
+
+    let mut payload = MULTI_SECTION_PSI[0].to_vec();
+    payload.extend_from_slice(&MULTI_SECTION_PSI[1][1..]);
+    payload.extend_from_slice(&[0xFF, 0xFF, 0xFF]);
+    parser.on_pkt(&mut ctx, &section_pkt(&payload));
+
+    assert_eq!(ctx.stats.invalid_psi, 0);
+  }
+
+  #[test]
+  fn section_tail_before_pointer_field() {
+    let mut handler: StubPsiHandler = Default::default();
+
+    handler
+      .mock
+      .expect_on_psi()
+      .withf(|_, psi| psi == &multi_section_table()[..])
+      .times(1)
+      .return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = PsiParser::new(handler);
+
+    // The first section starts in one packet, and finishes in the next, before
+    // the second section starts.
+    let section0 = &MULTI_SECTION_PSI[0][1..];
+    let section1 = &MULTI_SECTION_PSI[1][1..];
+
+    let mut payload = vec![0x00];
+    payload.extend_from_slice(&section0[..6]);
+    parser.on_pkt(&mut ctx, &section_pkt(&payload));
+
+    let mut payload = vec![(section0.len() - 6) as u8];
+    payload.extend_from_slice(&section0[6..]);
+    payload.extend_from_slice(section1);
+    parser.on_pkt(&mut ctx, &section_pkt(&payload));
+
+    assert_eq!(ctx.stats.invalid_psi, 0);
+  }
+
+  #[test]
+  fn crc_error_skips_one_section() {
+    let mut handler: StubPsiHandler = Default::default();
+
+    handler
+      .mock
+      .expect_on_psi()
+      .with(always(), eq(&PSI[9..16]))
+      .times(1)
+      .return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = PsiParser::new(handler);
+
+    let mut payload = vec![0x00];
+    payload.extend_from_slice(&PSI[6..20]);
+    payload.extend_from_slice(&PSI[6..20]);
+    payload[12] ^= 0x01;
+    parser.on_pkt(&mut ctx, &section_pkt(&payload));
+
+    assert_eq!(ctx.stats.psi_crc_errors, 1);
+  }
+
+  #[test]
+  fn short_form_section_without_crc() {
+    let mut ctx = Context::new();
+    let mut parser = PsiParser::with_section_handler(ShortSectionHandler {
+      sections: Vec::new(),
+    });
+
+    // A private section with section_syntax_indicator = 0 and no CRC.
+    parser.on_pkt(
+      &mut ctx,
+      &section_pkt(&[0x00, 0x70, 0x70, 0x02, 0xab, 0xcd, 0xff, 0xff]),
+    );
+
+    assert_eq!(ctx.stats.psi_crc_errors, 0);
+    assert_eq!(
+      parser.section_handler.sections,
+      vec![vec![0x70, 0x70, 0x02, 0xab, 0xcd]]
+    );
+  }
+
+  struct ShortSectionHandler {
+    sections: Vec<Vec<u8>>,
+  }
+
+  impl SectionHandler for ShortSectionHandler {
+    fn accepts(&self, table_id: u8) -> bool {
+      table_id == 0x70
+    }
+
+    fn on_section(&mut self, _ctx: &mut Context, section: &[u8]) -> bool {
+      self.sections.push(section.to_vec());
+      true
+    }
+  }
+
+  #[test]
+  fn table_versions() {
+    let mut versions = TableVersions::<&str>::new();
+
+    let update = versions.update("a", 1, true).unwrap();
+    assert_eq!((update.new, update.old), ("a", None));
+    assert_eq!(update.versions, vec![1]);
+
+    // Same version: not a change, even if the content differs.
+    assert!(versions.update("a'", 1, true).is_none());
+
+    // The next version is announced once, and only applied when it becomes
+    // current.
+    assert!(versions.announce(2));
+    assert!(!versions.announce(2));
+    assert!(versions.update("b", 2, false).is_none());
+    assert_eq!(versions.current(), Some(&"a"));
+
+    let update = versions.update("b", 2, true).unwrap();
+    assert_eq!((update.new, update.old), ("b", Some("a")));
+    assert_eq!(update.versions, vec![1, 2]);
+    assert!(!versions.announce(2));
+    assert!(versions.announce(3));
+  }
 }
@@ -0,0 +1,229 @@
+use crate::mp2t::demuxer::{Context, Event};
+use crate::mp2t::desc;
+use crate::mp2t::dvb_text;
+use crate::mp2t::psi_parser::{PsiHandler, TableVersions};
+use crate::mp2t::{Sdt, Service, StreamDesc};
+use bytes::Buf;
+use std::collections::hash_map::HashMap;
+use twiddle::Twiddle;
+
+// ETSI EN 300 468 Table 2
+pub const SDT_ACTUAL_TABLE_ID: u8 = 0x42;
+pub const SDT_OTHER_TABLE_ID: u8 = 0x46;
+
+/// Parses the SDTs of the actual transport stream and of other transport
+/// streams.
+pub struct SdtParser {
+  // SDT other describes many transport streams, so versions are tracked per
+  // table_id, transport_stream_id and original_network_id.
+  versions: HashMap<(u8, u16, u16), TableVersions<Sdt>>,
+}
+
+impl SdtParser {
+  pub fn new() -> SdtParser {
+    SdtParser {
+      versions: HashMap::new(),
+    }
+  }
+
+  fn parse_psi(
+    &mut self,
+    ctx: &mut Context,
+    table_id: u8,
+    sections: &[&[u8]],
+  ) -> bool {
+    let mut sdt = Sdt {
+      actual: match table_id {
+        SDT_ACTUAL_TABLE_ID => true,
+        SDT_OTHER_TABLE_ID => false,
+        _ => return true,
+      },
+      ..Default::default()
+    };
+
+    for (i, section) in sections.iter().enumerate() {
+      let mut buf = *section;
+
+      if buf.len() < 8 {
+        return false;
+      }
+
+      let transport_stream_id = buf.get_u16();
+      let b = buf.get_u8();
+      let _section_number = buf.get_u8();
+      let _last_section_number = buf.get_u8();
+      let original_network_id = buf.get_u16();
+      let _reserved = buf.get_u8();
+
+      // All sections share the same header: take it from the first one.
+      if i == 0 {
+        sdt.transport_stream_id = transport_stream_id;
+        sdt.original_network_id = original_network_id;
+        sdt.version = b.bits(5..=1);
+        sdt.current_next = b.bit(0);
+      }
+
+      //  4          3          2          1          0
+      // 98765432 10987654 32109876 54321098 76543210
+      // iiiiiiii iiiiiiii ......ab rrrfllll llllllll
+      //
+      // i: service_id
+      // a: EIT_schedule_flag
+      // b: EIT_present_following_flag
+      // r: running_status
+      // f: free_CA_mode
+      // l: descriptors_loop_length
+      while buf.len() >= 5 {
+        let service_id = buf.get_u16();
+        let b = buf.get_u8();
+        let flags = buf.get_u16();
+        let descs_len = flags.bits(11..=0) as usize;
+        if descs_len > buf.len() {
+          return false;
+        }
+        let descs = match desc::parse_descs(&buf[..descs_len]) {
+          Some(descs) => descs,
+          None => return false,
+        };
+        buf.advance(descs_len);
+
+        let mut service = Service {
+          service_id,
+          eit_schedule: b.bit(1),
+          eit_present_following: b.bit(0),
+          running_status: flags.bits(15..=13) as u8,
+          free_ca_mode: flags.bit(12),
+          ..Default::default()
+        };
+        for desc in &descs {
+          if let StreamDesc::Service(ref service_desc) = desc {
+            service.service_type = Some(service_desc.service_type);
+            service.provider_name =
+              Some(dvb_text::decode(&service_desc.provider_name));
+            service.service_name =
+              Some(dvb_text::decode(&service_desc.service_name));
+          }
+        }
+        service.descs = descs;
+        sdt.services.push(service);
+      }
+    }
+
+    let key = (table_id, sdt.transport_stream_id, sdt.original_network_id);
+    let (version, current_next) = (sdt.version, sdt.current_next);
+    let update = self
+      .versions
+      .entry(key)
+      .or_insert_with(TableVersions::new)
+      .update(sdt, version, current_next);
+    if let Some(update) = update {
+      ctx.events.push_back(Event::Sdt {
+        new: update.new,
+        old: update.old,
+        versions: update.versions,
+      });
+    }
+
+    true
+  }
+}
+
+impl PsiHandler for SdtParser {
+  const TABLE_ID: u8 = SDT_ACTUAL_TABLE_ID;
+
+  fn on_psi(&mut self, ctx: &mut Context, table_id: u8, sections: &[&[u8]]) {
+    if !self.parse_psi(ctx, table_id, sections) {
+      ctx.stats.invalid_psi += 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // SDT actual with two services: a TV service with a service_descriptor using
+  // the default character table, and a radio service with its names in
+  // ISO/IEC 8859-5.
+  static SDT: &[u8] = &[
+    0x00, 0x01, 0xc5, 0x00, 0x00, // section header
+    0x00, 0x02, 0xff, // original_network_id, reserved
+    0x00, 0x01, 0xff, 0x80, 0x0f, // service 1
+    0x48, 0x0d, 0x01, 0x03, 0x41, 0x42, 0x43, 0x07, 0x43, 0x61, 0x66, 0xc2,
+    0x65, 0x20, 0x31, // service_descriptor
+    0x00, 0x02, 0xfc, 0x90, 0x0d, // service 2
+    0x48, 0x0b, 0x02, 0x02, 0x01, 0xb0, 0x06, 0x01, 0xc0, 0xd0, 0xd4, 0xd8,
+    0xde, // service_descriptor
+  ];
+
+  #[test]
+  fn basic() {
+    let mut ctx = Context::new();
+    let mut parser = SdtParser::new();
+    parser.on_psi(&mut ctx, SDT_ACTUAL_TABLE_ID, &[SDT]);
+    parser.on_psi(&mut ctx, SDT_ACTUAL_TABLE_ID, &[SDT]);
+    assert_eq!(ctx.stats.invalid_psi, 0);
+    assert_eq!(ctx.events.len(), 1);
+
+    let sdt = match ctx.events[0] {
+      Event::Sdt { ref new, .. } => new,
+      ref e => panic!("unexpected event {:?}", e),
+    };
+    assert!(sdt.actual);
+    assert_eq!(sdt.transport_stream_id, 1);
+    assert_eq!(sdt.original_network_id, 2);
+    assert_eq!(sdt.version, 2);
+    assert_eq!(sdt.services.len(), 2);
+
+    let service = &sdt.services[0];
+    assert_eq!(service.service_id, 1);
+    assert!(service.eit_schedule);
+    assert!(service.eit_present_following);
+    assert_eq!(service.running_status, 4);
+    assert!(!service.free_ca_mode);
+    assert_eq!(service.service_type, Some(1));
+    assert_eq!(service.provider_name.as_deref(), Some("ABC"));
+    assert_eq!(service.service_name.as_deref(), Some("Café 1"));
+
+    let service = &sdt.services[1];
+    assert_eq!(service.service_id, 2);
+    assert!(!service.eit_schedule);
+    assert!(!service.eit_present_following);
+    assert_eq!(service.running_status, 4);
+    assert!(service.free_ca_mode);
+    assert_eq!(service.service_type, Some(2));
+    assert_eq!(service.provider_name.as_deref(), Some("А"));
+    assert_eq!(service.service_name.as_deref(), Some("Радио"));
+  }
+
+  #[test]
+  fn actual_and_other() {
+    let mut ctx = Context::new();
+    let mut parser = SdtParser::new();
+    parser.on_psi(&mut ctx, SDT_ACTUAL_TABLE_ID, &[SDT]);
+    parser.on_psi(&mut ctx, SDT_OTHER_TABLE_ID, &[SDT]);
+
+    // The versions of the actual and other tables are tracked separately.
+    let actual: Vec<bool> = ctx
+      .events
+      .iter()
+      .map(|e| match e {
+        Event::Sdt { new, .. } => new.actual,
+        e => panic!("unexpected event {:?}", e),
+      })
+      .collect();
+    assert_eq!(actual, vec![true, false]);
+  }
+
+  #[test]
+  fn descriptors_overrun() {
+    let mut sdt = SDT.to_vec();
+    sdt[12] = 0xff;
+
+    let mut ctx = Context::new();
+    let mut parser = SdtParser::new();
+    parser.on_psi(&mut ctx, SDT_ACTUAL_TABLE_ID, &[&sdt]);
+    assert!(ctx.events.is_empty());
+    assert_eq!(ctx.stats.invalid_psi, 1);
+  }
+}
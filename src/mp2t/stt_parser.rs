@@ -0,0 +1,96 @@
+use crate::mp2t::demuxer::{Context, Event};
+use crate::mp2t::desc;
+use crate::mp2t::psi_parser::PsiHandler;
+use crate::mp2t::Stt;
+use bytes::Buf;
+use twiddle::Twiddle;
+
+// ATSC A/65 Table 4.2
+pub const STT_TABLE_ID: u8 = 0xCD;
+
+/// Parses the STT. Its version_number is always 0, so every STT is reported:
+/// the time is meaningful even when it did not change.
+pub struct SttParser {}
+
+impl SttParser {
+  pub fn new() -> SttParser {
+    SttParser {}
+  }
+
+  fn parse_psi(&mut self, ctx: &mut Context, sections: &[&[u8]]) -> bool {
+    let mut buf = sections[0];
+
+    if buf.len() < 13 {
+      return false;
+    }
+
+    buf.advance(5);
+
+    //  1          0
+    // 54321098 76543210
+    // s..ddddd hhhhhhhh
+    //
+    // s: DS_status
+    // d: DS_day_of_month
+    // h: DS_hour
+    let mut stt = Stt {
+      protocol_version: buf.get_u8(),
+      system_time: buf.get_u32(),
+      gps_utc_offset: buf.get_u8(),
+      ..Default::default()
+    };
+    let daylight_saving = buf.get_u16();
+    stt.ds_status = daylight_saving.bit(15);
+    stt.ds_day_of_month = daylight_saving.bits(12..=8) as u8;
+    stt.ds_hour = daylight_saving.bits(7..=0) as u8;
+    stt.descs = match desc::parse_descs(buf) {
+      Some(descs) => descs,
+      None => return false,
+    };
+
+    ctx.events.push_back(Event::Stt(stt));
+    true
+  }
+}
+
+impl PsiHandler for SttParser {
+  const TABLE_ID: u8 = STT_TABLE_ID;
+
+  fn on_psi(&mut self, ctx: &mut Context, _table_id: u8, sections: &[&[u8]]) {
+    if !self.parse_psi(ctx, sections) {
+      ctx.stats.invalid_psi += 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // STT of 2020/01/01 00:00:00 UTC, with 18 leap seconds.
+  static STT: &[u8] = &[
+    0x00, 0x00, 0xc1, 0x00, 0x00, // section header
+    0x00, // protocol_version
+    0x4b, 0x36, 0xa3, 0x92, // system_time
+    0x12, // GPS_UTC_offset
+    0x60, 0x00, // daylight_saving
+  ];
+
+  #[test]
+  fn basic() {
+    let mut ctx = Context::new();
+    let mut parser = SttParser::new();
+    parser.on_psi(&mut ctx, STT_TABLE_ID, &[STT]);
+    parser.on_psi(&mut ctx, STT_TABLE_ID, &[STT]);
+    assert_eq!(ctx.stats.invalid_psi, 0);
+    assert_eq!(ctx.events.len(), 2);
+
+    let stt = match ctx.events[0] {
+      Event::Stt(ref stt) => stt,
+      ref e => panic!("unexpected event {:?}", e),
+    };
+    assert_eq!(stt.gps_utc_offset, 18);
+    assert!(!stt.ds_status);
+    assert_eq!(stt.utc_time(), 1_577_836_800);
+  }
+}
@@ -0,0 +1,170 @@
+use crate::mp2t::demuxer::{Context, Event};
+use crate::mp2t::desc;
+use crate::mp2t::dvb_time;
+use crate::mp2t::psi_parser::SectionHandler;
+use crate::mp2t::Time;
+use bytes::Buf;
+use twiddle::Twiddle;
+
+// ETSI EN 300 468 Table 2
+const TDT_TABLE_ID: u8 = 0x70;
+const TOT_TABLE_ID: u8 = 0x73;
+
+/// Parses the TDT and the TOT. Both are short-form sections, each reported as
+/// it arrives: the time is meaningful even when it did not change.
+pub struct TimeParser {}
+
+impl TimeParser {
+  pub fn new() -> TimeParser {
+    TimeParser {}
+  }
+
+  fn parse(&mut self, ctx: &mut Context, section: &[u8]) -> bool {
+    let tot = section[0] == TOT_TABLE_ID;
+
+    // Skip table_id and section_length.
+    let mut buf = &section[3..];
+    if buf.len() < 5 {
+      return false;
+    }
+    let utc_time = match dvb_time::utc_time(buf.get_uint(5)) {
+      Some(utc_time) => utc_time,
+      None => return false,
+    };
+
+    let mut time = Time {
+      tot,
+      utc_time,
+      ..Default::default()
+    };
+
+    if tot {
+      // Drop the CRC.
+      if buf.len() < 2 + 4 {
+        return false;
+      }
+      buf = &buf[..buf.len() - 4];
+      let descs_len = buf.get_u16().bits(11..=0) as usize;
+      if descs_len > buf.len() {
+        return false;
+      }
+      match desc::parse_descs(&buf[..descs_len]) {
+        Some(descs) => time.descs = descs,
+        None => return false,
+      }
+    }
+
+    ctx.events.push_back(Event::Time(time));
+    true
+  }
+}
+
+impl SectionHandler for TimeParser {
+  fn accepts(&self, table_id: u8) -> bool {
+    table_id == TDT_TABLE_ID || table_id == TOT_TABLE_ID
+  }
+
+  // The TOT is a short-form section, yet it ends with a CRC_32.
+  fn has_crc(&self, table_id: u8, section_syntax_indicator: bool) -> bool {
+    section_syntax_indicator || table_id == TOT_TABLE_ID
+  }
+
+  fn on_section(&mut self, ctx: &mut Context, section: &[u8]) -> bool {
+    self.parse(ctx, section)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::crc;
+  use crate::mp2t::psi_parser::PsiParser;
+  use crate::mp2t::ts_parser::{TsHandler, TsPacket};
+  use crate::mp2t::LocalTimeOffset;
+
+  // TDT of 1993/10/13 12:45:00.
+  static TDT: &[u8] = &[
+    0x00, // pointer_field
+    0x70, 0x70, 0x05, // table_id, section_length
+    0xc0, 0x79, 0x12, 0x45, 0x00, // UTC_time
+  ];
+
+  // TOT of 1993/10/13 12:45:00, with a local_time_offset_descriptor for
+  // Spain, +01:00.
+  fn tot_section() -> Vec<u8> {
+    let mut tot = vec![
+      0x00, // pointer_field
+      0x73, 0x70, 0x1a, // table_id, section_length
+      0xc0, 0x79, 0x12, 0x45, 0x00, // UTC_time
+      0xf0, 0x0f, // descriptors_loop_length
+      0x58, 0x0d, 0x45, 0x53, 0x50, 0x02, 0x01, 0x00, 0xff, 0xff, 0xff, 0xff,
+      0xff, 0x01, 0x00, // local_time_offset_descriptor
+    ];
+    let crc = crc::mpeg2(&tot[1..]);
+    tot.extend_from_slice(&crc.to_be_bytes());
+    tot
+  }
+
+  fn parse(payload: &[u8]) -> Context {
+    let mut ctx = Context::new();
+    let mut parser = PsiParser::with_section_handler(TimeParser::new());
+    parser.on_pkt(
+      &mut ctx,
+      &TsPacket {
+        payload,
+        payload_start: true,
+        ..Default::default()
+      },
+    );
+    ctx
+  }
+
+  #[test]
+  fn tdt() {
+    let ctx = parse(TDT);
+    assert_eq!(ctx.stats.invalid_psi, 0);
+    assert_eq!(ctx.events.len(), 1);
+
+    let time = match ctx.events[0] {
+      Event::Time(ref time) => time,
+      ref e => panic!("unexpected event {:?}", e),
+    };
+    assert!(!time.tot);
+    assert_eq!(time.utc_time, 750_516_300);
+    assert!(time.descs.is_empty());
+  }
+
+  #[test]
+  fn tot() {
+    let ctx = parse(&tot_section());
+    assert_eq!(ctx.stats.invalid_psi, 0);
+    assert_eq!(ctx.events.len(), 1);
+
+    let time = match ctx.events[0] {
+      Event::Time(ref time) => time,
+      ref e => panic!("unexpected event {:?}", e),
+    };
+    assert!(time.tot);
+    assert_eq!(time.utc_time, 750_516_300);
+    assert_eq!(
+      time.local_time_offsets().collect::<Vec<_>>(),
+      vec![&LocalTimeOffset {
+        country_code: "ESP".to_string(),
+        country_region_id: 0,
+        local_time_offset: 3600,
+        time_of_change: None,
+        next_time_offset: 3600,
+      }]
+    );
+  }
+
+  #[test]
+  fn tot_crc_error() {
+    let mut tot = tot_section();
+    tot[5] ^= 0x01;
+
+    let ctx = parse(&tot);
+    assert_eq!(ctx.stats.psi_crc_errors, 1);
+    assert!(ctx.events.is_empty());
+  }
+}
@@ -1,30 +1,98 @@
 use crate::internal::byte_queue::ByteQueue;
-use crate::mp2t::demuxer::Context;
+use crate::mp2t::demuxer::{Context, Event};
 use bytes::Buf;
+use memchr::memchr;
 use twiddle::Twiddle;
 
 #[cfg(test)]
 use mockall::automock;
 
 const PACKET_SIZE: usize = 188;
+// Packets preceded by the 4-byte TP_extra_header of BDAV MPEG-2 transport
+// streams (Blu-ray and AVCHD .m2ts files).
+const M2TS_PACKET_SIZE: usize = 192;
+// Packets followed by 16 bytes of Reed-Solomon parity, as found in DVB-ASI
+// captures.
+const RS_PACKET_SIZE: usize = 204;
 const HEADER_SYNC_WORD: u8 = 0x47;
 
+/// How packet sync is acquired and lost, after the TS_sync_loss indicator of
+/// ETSI TR 101 290 5.2.1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncConfig {
+  /// The number of consecutive sync bytes, one packet apart, needed to acquire
+  /// sync. Values below 1 are treated as 1.
+  pub confirmations: usize,
+  /// The number of consecutive bad sync bytes tolerated while in sync. The
+  /// packets that carry them are dropped. One more loses sync, as does any
+  /// packet that fails to parse despite a good sync byte.
+  pub max_bad_sync_bytes: usize,
+}
+
+impl Default for SyncConfig {
+  fn default() -> SyncConfig {
+    SyncConfig {
+      confirmations: 4,
+      max_bad_sync_bytes: 0,
+    }
+  }
+}
+
 #[cfg_attr(test, automock)]
 pub trait TsHandler {
   fn on_pkt<'a>(&mut self, ctx: &mut Context, pkt: &TsPacket<'a>);
+
+  /// Called when packets were lost, so that any partially accumulated data
+  /// can be discarded.
+  fn on_reset(&mut self, _ctx: &mut Context) {}
+
+  /// Called when the input has ended, so that any partially accumulated data
+  /// can be flushed.
+  fn on_eos(&mut self, _ctx: &mut Context) {}
 }
 
 #[derive(Default)]
 pub struct TsPacket<'a> {
   pub pos: i64,
   pub payload: &'a [u8],
+  /// The whole packet, including the TP_extra_header or the Reed-Solomon
+  /// parity bytes, if any.
   pub raw_data: &'a [u8],
+  /// The arrival_time_stamp of the TP_extra_header of M2TS packets, in 27MHz
+  /// units.
+  pub arrival_time_stamp: Option<u32>,
+  /// The copy_permission_indicator of the TP_extra_header of M2TS packets.
+  pub copy_permission: Option<u8>,
   pub pid: u16,
   pub pcr: Option<u64>,
   pub continuity_counter: u8,
+  /// The transport_error_indicator: the packet has at least one
+  /// uncorrectable bit error.
+  pub transport_error: bool,
   pub payload_start: bool,
+  pub transport_priority: bool,
+  /// The transport_scrambling_control. 0 means that the payload is not
+  /// scrambled.
+  pub scrambling_control: u8,
   pub discontinuity: bool,
   pub random_access: bool,
+  pub es_priority: bool,
+  pub opcr: Option<u64>,
+  /// The number of packets of the PID until the splicing point.
+  pub splice_countdown: Option<i8>,
+  pub transport_private_data: Option<&'a [u8]>,
+  /// The legal time window offset, if signaled as valid.
+  pub ltw_offset: Option<u16>,
+  /// In units of 50 bytes/second.
+  pub piecewise_rate: Option<u32>,
+  pub seamless_splice: Option<SeamlessSplice>,
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SeamlessSplice {
+  pub splice_type: u8,
+  /// The DTS of the next access unit after the splicing point, in 90kHz units.
+  pub dts_next_au: u64,
 }
 
 /// Implements a parser for MPEG-ts transport_packets as specified in
@@ -33,6 +101,31 @@ pub struct TsParser<H> {
   handler: H,
   byte_queue: ByteQueue,
   synchronized: bool,
+  // The size of the packets, including their framing. Detected when
+  // synchronizing.
+  packet_size: usize,
+  // Whether the input ended, so that no more data is coming to confirm sync
+  // words with.
+  eos: bool,
+  sync_config: SyncConfig,
+  // The number of consecutive bad sync bytes seen since the last good one.
+  bad_sync_bytes: usize,
+}
+
+// The outcome of looking for a packet position.
+enum SyncSearch {
+  // A packet starts at the given position, with the given size.
+  Found(usize, usize),
+  // The bytes before the given position cannot start a packet. Those after it
+  // might, but there is not enough data to tell yet.
+  NeedMoreData(usize),
+}
+
+// The outcome of checking for sync words at packet intervals.
+enum Confirmation {
+  Confirmed,
+  Rejected,
+  Incomplete,
 }
 
 impl<H> TsParser<H>
@@ -41,9 +134,13 @@ where
 {
   pub fn new(handler: H) -> TsParser<H> {
     TsParser {
-      handler: handler,
+      handler,
       byte_queue: ByteQueue::new(),
       synchronized: false,
+      packet_size: PACKET_SIZE,
+      eos: false,
+      sync_config: Default::default(),
+      bad_sync_bytes: 0,
     }
   }
 
@@ -51,17 +148,51 @@ where
     self.byte_queue.write(data);
   }
 
+  /// Signals the end of the input. Packets that could not be confirmed by
+  /// the ones following them are then parsed anyway.
+  pub fn set_eos(&mut self) {
+    self.eos = true;
+  }
+
+  pub fn eos(&self) -> bool {
+    self.eos
+  }
+
+  pub fn set_sync_config(&mut self, config: SyncConfig) {
+    self.sync_config = config;
+  }
+
   pub fn parse(&mut self, ctx: &mut Context) {
-    while self.byte_queue.len() >= PACKET_SIZE {
-      if !self.synchronized {
-        self.synchronize(ctx);
+    while self.byte_queue.len() >= self.packet_size {
+      if !self.synchronized && !self.synchronize(ctx) {
+        break;
+      }
+      let pos = self.byte_queue.head();
+
+      if self.byte_queue[sync_word_offset(self.packet_size)] != HEADER_SYNC_WORD
+      {
+        ctx.stats.malformed_ts_packets += 1;
+        self.bad_sync_bytes += 1;
+        if self.bad_sync_bytes <= self.sync_config.max_bad_sync_bytes {
+          self.byte_queue.pop(self.packet_size);
+          continue;
+        }
+        // Sync is lost. Skip one byte (so we don't try the same packet
+        // again), and synchronize() will find the next packet.
+        ctx.events.push_back(Event::SyncLost { pos });
+        self.byte_queue.pop(1);
+        self.synchronized = false;
+        ctx.stats.unsynchronized_bytes += 1;
         continue;
       }
-      let packet = parse_ts_packet(&self.byte_queue[..PACKET_SIZE]);
+      self.bad_sync_bytes = 0;
+
+      let packet = parse_ts_packet(ctx, &self.byte_queue[..self.packet_size]);
       match packet {
-        Some(packet) => {
+        Some(mut packet) => {
+          packet.pos = pos;
           self.handler.on_pkt(ctx, &packet);
-          self.byte_queue.pop(PACKET_SIZE);
+          self.byte_queue.pop(self.packet_size);
           if !ctx.events.is_empty() {
             break;
           }
@@ -70,6 +201,7 @@ where
           // If we failed to parse a packet, we need to re-synchronize. Skip one
           // byte (so we don't try the same packet again), and synchronize()
           // will find the next packet.
+          ctx.events.push_back(Event::SyncLost { pos });
           self.byte_queue.pop(1);
           self.synchronized = false;
           ctx.stats.malformed_ts_packets += 1;
@@ -80,54 +212,135 @@ where
   }
 
   pub fn handler(&self) -> &H {
-    return &self.handler;
+    &self.handler
   }
 
   pub fn mut_handler(&mut self) -> &mut H {
-    return &mut self.handler;
+    &mut self.handler
   }
 
-  fn synchronize(&mut self, ctx: &mut Context) {
-    self.synchronized = false;
-    let sync_idx = self.find_sync_word();
-    match sync_idx {
-      Some(idx) => {
+  // Drops the bytes before the next packet, and locks on it. Returns false if
+  // more data is needed to find it.
+  fn synchronize(&mut self, ctx: &mut Context) -> bool {
+    match self.find_sync_word() {
+      SyncSearch::Found(idx, packet_size) => {
         ctx.stats.unsynchronized_bytes += idx as u64;
         self.byte_queue.pop(idx);
         self.synchronized = true;
+        self.packet_size = packet_size;
+        self.bad_sync_bytes = 0;
+        ctx.events.push_back(Event::SyncAcquired {
+          pos: self.byte_queue.head(),
+          packet_size,
+        });
+        true
       }
-      None => {
-        ctx.stats.unsynchronized_bytes += self.byte_queue.len() as u64;
-        self.byte_queue.pop_all();
+      SyncSearch::NeedMoreData(idx) => {
+        ctx.stats.unsynchronized_bytes += idx as u64;
+        self.byte_queue.pop(idx);
+        false
       }
     }
   }
 
-  fn find_sync_word(&self) -> Option<usize> {
+  // Looks for the first position where the configured number of sync words
+  // follow each other at packet intervals. The current packet size is tried
+  // first, so that a stream does not switch sizes on a spurious match.
+  fn find_sync_word(&self) -> SyncSearch {
+    let current_size = self.packet_size;
+    let packet_sizes = [PACKET_SIZE, M2TS_PACKET_SIZE, RS_PACKET_SIZE];
+    let packet_sizes = std::iter::once(current_size)
+      .chain(packet_sizes.iter().cloned().filter(|&s| s != current_size));
+
     let buf = &self.byte_queue[..];
-    for i in 0..buf.len() {
-      let mut is_header = false;
-      for j in 0..4 {
-        let idx = i + j * PACKET_SIZE;
-        if idx >= buf.len() {
-          break;
+    let mut start = 0;
+    while let Some(offset) = memchr(HEADER_SYNC_WORD, &buf[start..]) {
+      let sync_idx = start + offset;
+      let mut incomplete: Option<usize> = None;
+      for packet_size in packet_sizes.clone() {
+        let sync_offset = sync_word_offset(packet_size);
+        if sync_idx < sync_offset {
+          continue;
         }
-        if buf[idx] != HEADER_SYNC_WORD {
-          is_header = false;
-          break;
+        let idx = sync_idx - sync_offset;
+        match self.confirm(buf, sync_idx, packet_size) {
+          Confirmation::Confirmed => {
+            return SyncSearch::Found(idx, packet_size)
+          }
+          Confirmation::Incomplete if self.eos => {
+            return SyncSearch::Found(idx, packet_size)
+          }
+          Confirmation::Incomplete => {
+            incomplete = Some(incomplete.map_or(idx, |i| i.min(idx)));
+          }
+          Confirmation::Rejected => (),
         }
-        is_header = true;
       }
-      if is_header {
-        return Some(i);
+      if let Some(idx) = incomplete {
+        return SyncSearch::NeedMoreData(idx);
       }
+      start = sync_idx + 1;
     }
-    None
+
+    // The last bytes might be the TP_extra_header of a packet whose sync word
+    // has not arrived yet.
+    let tail = sync_word_offset(M2TS_PACKET_SIZE);
+    SyncSearch::NeedMoreData(buf.len().saturating_sub(tail))
+  }
+
+  fn confirm(
+    &self,
+    buf: &[u8],
+    sync_idx: usize,
+    packet_size: usize,
+  ) -> Confirmation {
+    for i in 1..self.sync_config.confirmations {
+      match buf.get(sync_idx + i * packet_size) {
+        Some(&HEADER_SYNC_WORD) => (),
+        Some(_) => return Confirmation::Rejected,
+        None => return Confirmation::Incomplete,
+      }
+    }
+    Confirmation::Confirmed
+  }
+}
+
+// Returns the offset of the sync_byte in packets of the given size.
+fn sync_word_offset(packet_size: usize) -> usize {
+  match packet_size {
+    M2TS_PACKET_SIZE => M2TS_PACKET_SIZE - PACKET_SIZE,
+    _ => 0,
   }
 }
 
-fn parse_ts_packet(data: &[u8]) -> Option<TsPacket> {
-  debug_assert!(data.len() == PACKET_SIZE);
+fn parse_ts_packet<'a>(
+  ctx: &mut Context,
+  framed_data: &'a [u8],
+) -> Option<TsPacket<'a>> {
+  let mut packet = TsPacket {
+    raw_data: framed_data,
+    ..Default::default()
+  };
+
+  let data = match framed_data.len() {
+    PACKET_SIZE => framed_data,
+    // BDAV MPEG-2 Transport Stream Format, TP_extra_header:
+    //
+    //  3          2          1          0
+    // 10987654 32109876 54321098 76543210
+    // ccaaaaaa aaaaaaaa aaaaaaaa aaaaaaaa
+    //
+    // c: copy_permission_indicator
+    // a: arrival_time_stamp
+    M2TS_PACKET_SIZE => {
+      let header = (&framed_data[..4]).get_u32();
+      packet.copy_permission = Some(header.bits(31..=30) as u8);
+      packet.arrival_time_stamp = Some(header.bits(29..=0));
+      &framed_data[4..]
+    }
+    RS_PACKET_SIZE => &framed_data[..PACKET_SIZE],
+    _ => unreachable!(),
+  };
 
   // ISO/IEC 13818-1: 2.4.3.2 Transport Stream packet layer
 
@@ -149,14 +362,22 @@ fn parse_ts_packet(data: &[u8]) -> Option<TsPacket> {
   }
 
   let mut buf = data;
-  let mut packet: TsPacket = Default::default();
   let header = buf.get_u32();
-  packet.raw_data = data;
+  packet.transport_error = header.bit(23);
   packet.payload_start = header.bit(22);
+  packet.transport_priority = header.bit(21);
   packet.pid = header.bits(20..=8) as u16;
+  packet.scrambling_control = header.bits(7..=6) as u8;
   let adaptation_field_control = header.bits(5..=4);
   packet.continuity_counter = header.bits(3..=0) as u8;
 
+  if packet.transport_error {
+    ctx.stats.transport_error_packets += 1;
+  }
+  if packet.scrambling_control != 0 {
+    ctx.stats.scrambled_ts_packets += 1;
+  }
+
   let has_adaptation_field = adaptation_field_control & 0x2 != 0;
   let has_payload = adaptation_field_control & 0x1 != 0;
   if !has_adaptation_field {
@@ -185,32 +406,166 @@ fn parse_ts_packet(data: &[u8]) -> Option<TsPacket> {
     return Some(packet);
   }
 
-  let mut adaptation_field = buf;
-
-  let mut t = adaptation_field.get_u8() as u64;
-  packet.discontinuity = t.bit(7);
-  packet.random_access = t.bit(6);
-  let pcr_flag = t.bit(4);
-
-  if pcr_flag {
-    t = adaptation_field.get_uint(6);
-    let mut pcr: u64 = t.bits(47..=15) * 300;
-    pcr += t.bits(8..=0);
-    packet.pcr = Some(pcr);
+  // The optional fields that do not fit in the adaptation field are left out,
+  // but the packet itself is still valid.
+  if !parse_adaptation_field(&mut packet, &buf[..adaptation_field_len]) {
+    ctx.stats.malformed_adaptation_fields += 1;
   }
 
-  // Skip the remaining adaptation field.
   buf.advance(adaptation_field_len);
   packet.payload = buf;
 
   Some(packet)
 }
 
+// ISO/IEC 13818-1: 2.4.3.4 Adaptation field
+//
+// 76543210
+// abcdefgh
+//
+// a: discontinuity_indicator
+// b: random_access_indicator
+// c: elementary_stream_priority_indicator
+// d: PCR_flag
+// e: OPCR_flag
+// f: splicing_point_flag
+// g: transport_private_data_flag
+// h: adaptation_field_extension_flag
+fn parse_adaptation_field<'a>(
+  packet: &mut TsPacket<'a>,
+  adaptation_field: &'a [u8],
+) -> bool {
+  let mut buf = adaptation_field;
+
+  let flags = buf.get_u8();
+  packet.discontinuity = flags.bit(7);
+  packet.random_access = flags.bit(6);
+  packet.es_priority = flags.bit(5);
+
+  if flags.bit(4) {
+    if buf.len() < 6 {
+      return false;
+    }
+    packet.pcr = Some(get_pcr(&mut buf));
+  }
+
+  if flags.bit(3) {
+    if buf.len() < 6 {
+      return false;
+    }
+    packet.opcr = Some(get_pcr(&mut buf));
+  }
+
+  if flags.bit(2) {
+    if buf.is_empty() {
+      return false;
+    }
+    packet.splice_countdown = Some(buf.get_i8());
+  }
+
+  if flags.bit(1) {
+    if buf.is_empty() {
+      return false;
+    }
+    let len = buf.get_u8() as usize;
+    if buf.len() < len {
+      return false;
+    }
+    packet.transport_private_data = Some(&buf[..len]);
+    buf.advance(len);
+  }
+
+  if flags.bit(0) {
+    if buf.is_empty() {
+      return false;
+    }
+    let len = buf.get_u8() as usize;
+    if buf.len() < len {
+      return false;
+    }
+    return parse_adaptation_field_extension(packet, &buf[..len]);
+  }
+
+  true
+}
+
+// Reads a program_clock_reference (or original_program_clock_reference) in
+// 27MHz units.
+//
+//  4          3          2          1          0
+// 76543210 98765432 10987654 32109876 54321098 76543210
+// bbbbbbbb bbbbbbbb bbbbbbbb bbbbbbbb b......e eeeeeeee
+//
+// b: base (90kHz)
+// e: extension
+fn get_pcr(buf: &mut &[u8]) -> u64 {
+  let t = buf.get_uint(6);
+  t.bits(47..=15) * 300 + t.bits(8..=0)
+}
+
+// ISO/IEC 13818-1: 2.4.3.4 Adaptation field, adaptation_field_extension.
+//
+// 76543210
+// abc.....
+//
+// a: ltw_flag
+// b: piecewise_rate_flag
+// c: seamless_splice_flag
+fn parse_adaptation_field_extension(
+  packet: &mut TsPacket,
+  extension: &[u8],
+) -> bool {
+  let mut buf = extension;
+  if buf.is_empty() {
+    return false;
+  }
+  let flags = buf.get_u8();
+
+  if flags.bit(7) {
+    if buf.len() < 2 {
+      return false;
+    }
+    let ltw = buf.get_u16();
+    if ltw.bit(15) {
+      packet.ltw_offset = Some(ltw.bits(14..=0));
+    }
+  }
+
+  if flags.bit(6) {
+    if buf.len() < 3 {
+      return false;
+    }
+    packet.piecewise_rate = Some(buf.get_uint(3).bits(21..=0) as u32);
+  }
+
+  //  4          3          2          1          0
+  // 98765432 10987654 32109876 54321098 76543210
+  // ttttdddm dddddddd dddddddm dddddddd dddddddm
+  //
+  // t: splice_type
+  // d: DTS_next_AU
+  // m: marker_bit
+  if flags.bit(5) {
+    if buf.len() < 5 {
+      return false;
+    }
+    let t = buf.get_uint(5);
+    packet.seamless_splice = Some(SeamlessSplice {
+      splice_type: t.bits(39..=36) as u8,
+      dts_next_au: (t.bits(35..=33) << 30)
+        | (t.bits(31..=17) << 15)
+        | t.bits(15..=1),
+    });
+  }
+
+  true
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
-  const PKT_NO_AF: &'static [u8] = &[
+  const PKT_NO_AF: &[u8] = &[
     0x47, 0x00, 0x65, 0x15, 0x9c, 0x04, 0x84, 0x4c, 0x16, 0x73, 0x53, 0x6e,
     0xb5, 0xf1, 0xd8, 0x55, 0x66, 0x62, 0xb8, 0xc7, 0x72, 0x31, 0xda, 0x0c,
     0x1a, 0xb2, 0x92, 0x28, 0x36, 0xd4, 0x10, 0xfb, 0x9c, 0x7e, 0xfa, 0xf7,
@@ -229,7 +584,7 @@ mod tests {
     0xf4, 0x10, 0xc6, 0xad, 0xe4, 0x92, 0x45, 0xa2,
   ];
 
-  const PKT_TINY_AF: &'static [u8] = &[
+  const PKT_TINY_AF: &[u8] = &[
     0x47, 0x40, 0x00, 0x30, 0x01, 0x00, 0x00, 0x00, 0xb0, 0x0d, 0x00, 0x01,
     0xc1, 0x00, 0x00, 0x00, 0x01, 0xe0, 0x64, 0x85, 0x41, 0x2f, 0xea, 0xff,
     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
@@ -248,7 +603,7 @@ mod tests {
     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
   ];
 
-  const PKT_ZERO_AF: &'static [u8] = &[
+  const PKT_ZERO_AF: &[u8] = &[
     0x47, 0x40, 0x00, 0x30, 0x00, 0x62, 0xc7, 0x4b, 0xb0, 0x0d, 0x00, 0x01,
     0xb5, 0xf1, 0xd8, 0x55, 0x66, 0x62, 0xb8, 0xc7, 0x72, 0x31, 0xda, 0x0c,
     0x1a, 0xb2, 0x92, 0x28, 0x36, 0xd4, 0x10, 0xfb, 0x9c, 0x7e, 0xfa, 0xf7,
@@ -267,7 +622,7 @@ mod tests {
     0xf4, 0x10, 0xc6, 0xad, 0xe4, 0x92, 0x45, 0xa2,
   ];
 
-  const PKT_AF_PCR: &'static [u8] = &[
+  const PKT_AF_PCR: &[u8] = &[
     0x47, 0x40, 0x65, 0x30, 0x07, 0x50, 0xde, 0x36, 0xea, 0x29, 0x80, 0x00,
     0x00, 0x00, 0x01, 0xe0, 0x34, 0x08, 0x84, 0xc0, 0x0a, 0x3d, 0xf1, 0xb7,
     0xc0, 0x1d, 0x1d, 0xf1, 0xb7, 0xa8, 0xa7, 0x00, 0x00, 0x00, 0x01, 0x09,
@@ -286,7 +641,7 @@ mod tests {
     0x2f, 0x2f, 0x77, 0x77, 0x77, 0x2e, 0x76, 0x69,
   ];
 
-  const PKT_NO_PAYLOAD: &'static [u8] = &[
+  const PKT_NO_PAYLOAD: &[u8] = &[
     0x47, 0x40, 0x65, 0x20, 0xB7, 0x50, 0xde, 0x36, 0xea, 0x29, 0x80, 0x00,
     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
@@ -305,6 +660,23 @@ mod tests {
     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
   ];
 
+  // Parses all the packets and returns the events raised, as the demuxer does:
+  // the parser stops after a packet that raises events, until they are
+  // handled.
+  fn parse_all<H: TsHandler>(
+    parser: &mut TsParser<H>,
+    ctx: &mut Context,
+  ) -> Vec<Event> {
+    let mut events = Vec::new();
+    loop {
+      parser.parse(ctx);
+      if ctx.events.is_empty() {
+        return events;
+      }
+      events.extend(ctx.events.drain(..));
+    }
+  }
+
   #[test]
   fn pkt_no_af() {
     let mut handler = MockTsHandler::new();
@@ -320,12 +692,41 @@ mod tests {
     let mut ctx = Context::new();
     let mut parser = TsParser::new(handler);
     parser.push(PKT_NO_AF);
+    parser.set_eos();
     parser.parse(&mut ctx);
 
     assert_eq!(ctx.stats.unsynchronized_bytes, 0);
     assert_eq!(ctx.stats.malformed_ts_packets, 0);
   }
 
+  #[test]
+  fn pkt_header_flags() {
+    let mut handler = MockTsHandler::new();
+
+    handler
+      .expect_on_pkt()
+      .times(1)
+      .withf(|_ctx, pkt| {
+        pkt.pid == 0x65
+          && pkt.transport_error
+          && pkt.transport_priority
+          && pkt.scrambling_control == 2
+      })
+      .return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = TsParser::new(handler);
+    let mut pkt = PKT_NO_AF.to_vec();
+    pkt[1] |= 0xa0;
+    pkt[3] |= 0x80;
+    parser.push(&pkt);
+    parser.set_eos();
+    parser.parse(&mut ctx);
+
+    assert_eq!(ctx.stats.transport_error_packets, 1);
+    assert_eq!(ctx.stats.scrambled_ts_packets, 1);
+  }
+
   #[test]
   fn pkt_tiny_af() {
     let mut handler = MockTsHandler::new();
@@ -339,6 +740,7 @@ mod tests {
     let mut ctx = Context::new();
     let mut parser = TsParser::new(handler);
     parser.push(PKT_TINY_AF);
+    parser.set_eos();
     parser.parse(&mut ctx);
 
     assert_eq!(ctx.stats.unsynchronized_bytes, 0);
@@ -360,6 +762,7 @@ mod tests {
     let mut ctx = Context::new();
     let mut parser = TsParser::new(handler);
     parser.push(PKT_AF_PCR);
+    parser.set_eos();
     parser.parse(&mut ctx);
 
     assert_eq!(ctx.stats.unsynchronized_bytes, 0);
@@ -379,6 +782,7 @@ mod tests {
     let mut ctx = Context::new();
     let mut parser = TsParser::new(handler);
     parser.push(PKT_ZERO_AF);
+    parser.set_eos();
     parser.parse(&mut ctx);
 
     assert_eq!(ctx.stats.unsynchronized_bytes, 0);
@@ -392,12 +796,13 @@ mod tests {
     handler
       .expect_on_pkt()
       .times(1)
-      .withf(|_ctx, pkt| pkt.payload.len() == 0)
+      .withf(|_ctx, pkt| pkt.payload.is_empty())
       .return_const(());
 
     let mut ctx = Context::new();
     let mut parser = TsParser::new(handler);
     parser.push(PKT_NO_PAYLOAD);
+    parser.set_eos();
     parser.parse(&mut ctx);
 
     assert_eq!(ctx.stats.unsynchronized_bytes, 0);
@@ -413,14 +818,162 @@ mod tests {
     let mut ctx = Context::new();
     let mut parser = TsParser::new(handler);
 
-    let mut data: Vec<u8> = PKT_NO_PAYLOAD.iter().cloned().collect();
+    let mut data: Vec<u8> = PKT_NO_PAYLOAD.to_vec();
     data[3] |= 0x10;
     parser.push(&data);
+    parser.set_eos();
     parser.parse(&mut ctx);
 
     assert_eq!(ctx.stats.malformed_ts_packets, 1);
   }
 
+  // A packet with every optional field of the adaptation field, followed by a
+  // payload of 0xaa bytes.
+  fn full_af_pkt() -> Vec<u8> {
+    let mut pkt = vec![
+      0x47, 0x01, 0x00, 0x30, // header
+      0x1d, // adaptation_field_length
+      0xff, // flags
+      0x00, 0x00, 0x00, 0x01, 0x7e, 0x02, // PCR
+      0x00, 0x00, 0x00, 0x02, 0x7e, 0x03, // OPCR
+      0xfe, // splice_countdown
+      0x02, 0xbe, 0xef, // transport_private_data
+      0x0b, 0xe0, // adaptation_field_extension, flags
+      0x80, 0x10, // ltw
+      0xc0, 0x01, 0x00, // piecewise_rate
+      0x23, 0x00, 0x05, 0x00, 0x07, // seamless_splice
+    ];
+    pkt.resize(PACKET_SIZE, 0xaa);
+    pkt
+  }
+
+  #[test]
+  fn pkt_full_af() {
+    let mut handler = MockTsHandler::new();
+
+    handler
+      .expect_on_pkt()
+      .times(1)
+      .withf(|_ctx, pkt| {
+        pkt.payload == &full_af_pkt()[34..]
+          && pkt.discontinuity
+          && pkt.random_access
+          && pkt.es_priority
+          && pkt.pcr == Some(2 * 300 + 2)
+          && pkt.opcr == Some(4 * 300 + 3)
+          && pkt.splice_countdown == Some(-2)
+          && pkt.transport_private_data == Some(&[0xbe, 0xef][..])
+          && pkt.ltw_offset == Some(0x10)
+          && pkt.piecewise_rate == Some(0x100)
+          && pkt.seamless_splice
+            == Some(SeamlessSplice {
+              splice_type: 2,
+              dts_next_au: (1 << 30) | (2 << 15) | 3,
+            })
+      })
+      .return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = TsParser::new(handler);
+    parser.push(&full_af_pkt());
+    parser.set_eos();
+    parser.parse(&mut ctx);
+
+    assert_eq!(ctx.stats.malformed_ts_packets, 0);
+    assert_eq!(ctx.stats.malformed_adaptation_fields, 0);
+  }
+
+  #[test]
+  fn pkt_truncated_af() {
+    let mut handler = MockTsHandler::new();
+
+    // The transport_private_data overruns the adaptation field: it is left
+    // out, along with the extension, but the payload is still delivered.
+    handler
+      .expect_on_pkt()
+      .times(1)
+      .withf(|_ctx, pkt| {
+        pkt.payload == &full_af_pkt()[34..]
+          && pkt.splice_countdown == Some(-2)
+          && pkt.transport_private_data.is_none()
+          && pkt.seamless_splice.is_none()
+      })
+      .return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = TsParser::new(handler);
+    let mut pkt = full_af_pkt();
+    pkt[19] = 0x20;
+    parser.push(&pkt);
+    parser.set_eos();
+    parser.parse(&mut ctx);
+
+    assert_eq!(ctx.stats.malformed_ts_packets, 0);
+    assert_eq!(ctx.stats.malformed_adaptation_fields, 1);
+  }
+
+  #[test]
+  fn m2ts_packets() {
+    let mut handler = MockTsHandler::new();
+
+    handler
+      .expect_on_pkt()
+      .times(4)
+      .withf(|_ctx, pkt| {
+        pkt.raw_data.len() == M2TS_PACKET_SIZE
+          && pkt.payload == &PKT_AF_PCR[12..]
+          && pkt.pcr == Some(2236884504900)
+          && pkt.copy_permission == Some(1)
+          && pkt.arrival_time_stamp == Some(0x123456)
+      })
+      .return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = TsParser::new(handler);
+
+    let mut data: Vec<u8> = vec![0x00, 0x00];
+    for _ in 0..4 {
+      data.extend([0x40, 0x12, 0x34, 0x56].iter());
+      data.extend(PKT_AF_PCR.iter().cloned());
+    }
+
+    parser.push(&data);
+    parse_all(&mut parser, &mut ctx);
+
+    assert_eq!(ctx.stats.unsynchronized_bytes, 2);
+    assert_eq!(ctx.stats.malformed_ts_packets, 0);
+  }
+
+  #[test]
+  fn rs_packets() {
+    let mut handler = MockTsHandler::new();
+
+    handler
+      .expect_on_pkt()
+      .times(4)
+      .withf(|_ctx, pkt| {
+        pkt.raw_data.len() == RS_PACKET_SIZE
+          && pkt.payload == &PKT_AF_PCR[12..]
+          && pkt.arrival_time_stamp.is_none()
+      })
+      .return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = TsParser::new(handler);
+
+    let mut data: Vec<u8> = Vec::new();
+    for _ in 0..4 {
+      data.extend(PKT_AF_PCR.iter().cloned());
+      data.extend([0x47; 16].iter());
+    }
+
+    parser.push(&data);
+    parse_all(&mut parser, &mut ctx);
+
+    assert_eq!(ctx.stats.unsynchronized_bytes, 0);
+    assert_eq!(ctx.stats.malformed_ts_packets, 0);
+  }
+
   #[test]
   fn sync_no_skip() {
     let mut handler = MockTsHandler::new();
@@ -430,13 +983,13 @@ mod tests {
     let mut ctx = Context::new();
     let mut parser = TsParser::new(handler);
 
-    let mut data: Vec<u8> = PKT_AF_PCR.iter().cloned().collect();
+    let mut data: Vec<u8> = PKT_AF_PCR.to_vec();
     data.extend(PKT_AF_PCR.iter().cloned());
     data.extend(PKT_AF_PCR.iter().cloned());
     data.extend(PKT_AF_PCR.iter().cloned());
 
     parser.push(&data);
-    parser.parse(&mut ctx);
+    parse_all(&mut parser, &mut ctx);
 
     assert_eq!(ctx.stats.unsynchronized_bytes, 0);
     assert_eq!(ctx.stats.malformed_ts_packets, 0);
@@ -457,12 +1010,38 @@ mod tests {
     data.extend(PKT_AF_PCR.iter().cloned());
 
     parser.push(&data);
-    parser.parse(&mut ctx);
+    parser.set_eos();
+    parse_all(&mut parser, &mut ctx);
 
     assert_eq!(ctx.stats.unsynchronized_bytes, 4);
     assert_eq!(ctx.stats.malformed_ts_packets, 0);
   }
 
+  #[test]
+  fn sync_across_pushes() {
+    let mut handler = MockTsHandler::new();
+
+    handler.expect_on_pkt().times(4).return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = TsParser::new(handler);
+
+    let mut data: Vec<u8> = vec![0x00, 0x47, 0xaa];
+    for _ in 0..4 {
+      data.extend(PKT_AF_PCR.iter().cloned());
+    }
+
+    // The sync words cannot be confirmed until the last chunk arrives, so the
+    // bytes after the garbage must be kept in the meantime.
+    for chunk in data.chunks(100) {
+      parser.push(chunk);
+      parse_all(&mut parser, &mut ctx);
+    }
+
+    assert_eq!(ctx.stats.unsynchronized_bytes, 3);
+    assert_eq!(ctx.stats.malformed_ts_packets, 0);
+  }
+
   #[test]
   fn sync_middle() {
     let mut handler = MockTsHandler::new();
@@ -472,7 +1051,7 @@ mod tests {
     let mut ctx = Context::new();
     let mut parser = TsParser::new(handler);
 
-    let mut data: Vec<u8> = PKT_AF_PCR.iter().cloned().collect();
+    let mut data: Vec<u8> = PKT_AF_PCR.to_vec();
     data.extend(PKT_AF_PCR.iter().cloned());
     data.extend([0x00u8, 0x47, 0x00].iter());
     data.extend(PKT_AF_PCR.iter().cloned());
@@ -481,7 +1060,7 @@ mod tests {
     data.extend(PKT_AF_PCR.iter().cloned());
 
     parser.push(&data);
-    parser.parse(&mut ctx);
+    parse_all(&mut parser, &mut ctx);
 
     // The first two packets (+ the 3 garbage bytes) were skipped because the
     // parser needs consecutive 4 packets to synchronize.
@@ -498,7 +1077,7 @@ mod tests {
     let mut ctx = Context::new();
     let mut parser = TsParser::new(handler);
 
-    let mut data: Vec<u8> = PKT_AF_PCR.iter().cloned().collect();
+    let mut data: Vec<u8> = PKT_AF_PCR.to_vec();
     data.extend(PKT_AF_PCR.iter().cloned());
     data.extend(PKT_AF_PCR.iter().cloned());
     data.extend(PKT_AF_PCR.iter().cloned());
@@ -508,9 +1087,100 @@ mod tests {
     data.extend(PKT_AF_PCR.iter().cloned());
 
     parser.push(&data);
-    parser.parse(&mut ctx);
+    parser.set_eos();
+    parse_all(&mut parser, &mut ctx);
 
     assert_eq!(ctx.stats.unsynchronized_bytes, 3);
     assert_eq!(ctx.stats.malformed_ts_packets, 1);
   }
+
+  #[test]
+  fn sync_events() {
+    let mut handler = MockTsHandler::new();
+
+    handler.expect_on_pkt().times(7).return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = TsParser::new(handler);
+
+    let mut data: Vec<u8> = Vec::new();
+    for _ in 0..5 {
+      data.extend(PKT_AF_PCR.iter().cloned());
+    }
+    data.extend([0x00u8, 0x47, 0x00].iter());
+    data.extend(PKT_AF_PCR.iter().cloned());
+    data.extend(PKT_AF_PCR.iter().cloned());
+
+    parser.push(&data);
+    parser.set_eos();
+    let events = parse_all(&mut parser, &mut ctx);
+    assert_eq!(events.len(), 3);
+    assert!(matches!(
+      events[0],
+      Event::SyncAcquired {
+        pos: 0,
+        packet_size: PACKET_SIZE
+      }
+    ));
+    assert!(matches!(events[1], Event::SyncLost { pos: 940 }));
+    assert!(matches!(events[2], Event::SyncAcquired { pos: 943, .. }));
+  }
+
+  #[test]
+  fn sync_config() {
+    let mut handler = MockTsHandler::new();
+
+    handler.expect_on_pkt().times(3).return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = TsParser::new(handler);
+    parser.set_sync_config(SyncConfig {
+      confirmations: 2,
+      max_bad_sync_bytes: 1,
+    });
+
+    let mut data: Vec<u8> = Vec::new();
+    for _ in 0..4 {
+      data.extend(PKT_AF_PCR.iter().cloned());
+    }
+    // A single bad sync byte is tolerated: its packet is dropped, but sync is
+    // kept.
+    data[2 * PACKET_SIZE] = 0x00;
+
+    parser.push(&data);
+    let events = parse_all(&mut parser, &mut ctx);
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(ctx.stats.unsynchronized_bytes, 0);
+    assert_eq!(ctx.stats.malformed_ts_packets, 1);
+  }
+
+  #[test]
+  fn malformed_pkt_loses_sync() {
+    let mut handler = MockTsHandler::new();
+
+    handler.expect_on_pkt().times(6).return_const(());
+
+    let mut ctx = Context::new();
+    let mut parser = TsParser::new(handler);
+
+    // The third packet has a good sync byte, but indicates a payload it does
+    // not have.
+    let mut data: Vec<u8> = PKT_AF_PCR.to_vec();
+    data.extend(PKT_AF_PCR.iter().cloned());
+    data.extend(PKT_NO_PAYLOAD.iter().cloned());
+    data[2 * PACKET_SIZE + 3] |= 0x10;
+    for _ in 0..4 {
+      data.extend(PKT_AF_PCR.iter().cloned());
+    }
+
+    parser.push(&data);
+    let events = parse_all(&mut parser, &mut ctx);
+
+    assert_eq!(events.len(), 3);
+    assert!(matches!(events[1], Event::SyncLost { pos: 376 }));
+    assert!(matches!(events[2], Event::SyncAcquired { pos: 564, .. }));
+    assert_eq!(ctx.stats.unsynchronized_bytes, PACKET_SIZE as u64);
+    assert_eq!(ctx.stats.malformed_ts_packets, 1);
+  }
 }
@@ -0,0 +1,263 @@
+use crate::mp2t::demuxer::{Context, Event};
+use crate::mp2t::desc;
+use crate::mp2t::psi_parser::{PsiHandler, TableVersions};
+use crate::mp2t::{Vct, VirtualChannel};
+use bytes::Buf;
+use std::collections::hash_map::HashMap;
+use twiddle::Twiddle;
+
+// ATSC A/65 Table 4.2
+pub const TVCT_TABLE_ID: u8 = 0xC8;
+pub const CVCT_TABLE_ID: u8 = 0xC9;
+
+/// Parses the terrestrial and the cable VCT. They only differ in the
+/// path_select and out_of_band flags, which the TVCT leaves reserved.
+pub struct VctParser {
+  // By table_id.
+  versions: HashMap<u8, TableVersions<Vct>>,
+}
+
+impl VctParser {
+  pub fn new() -> VctParser {
+    VctParser {
+      versions: HashMap::new(),
+    }
+  }
+
+  fn parse_psi(
+    &mut self,
+    ctx: &mut Context,
+    table_id: u8,
+    sections: &[&[u8]],
+  ) -> bool {
+    let mut vct = Vct {
+      cable: table_id == CVCT_TABLE_ID,
+      ..Default::default()
+    };
+
+    for (i, section) in sections.iter().enumerate() {
+      let mut buf = *section;
+
+      if buf.len() < 7 {
+        return false;
+      }
+
+      let transport_stream_id = buf.get_u16();
+      let b = buf.get_u8();
+      let _section_number = buf.get_u8();
+      let _last_section_number = buf.get_u8();
+      let protocol_version = buf.get_u8();
+
+      // All sections share the same header: take it from the first one.
+      if i == 0 {
+        vct.transport_stream_id = transport_stream_id;
+        vct.version = b.bits(5..=1);
+        vct.current_next = b.bit(0);
+        vct.protocol_version = protocol_version;
+      }
+
+      let num_channels_in_section = buf.get_u8();
+      for _ in 0..num_channels_in_section {
+        let channel = match parse_channel(&mut buf, vct.cable) {
+          Some(channel) => channel,
+          None => return false,
+        };
+        vct.channels.push(channel);
+      }
+
+      if buf.len() < 2 {
+        return false;
+      }
+      let descs_len = buf.get_u16().bits(9..=0) as usize;
+      if descs_len > buf.len() {
+        return false;
+      }
+      match desc::parse_descs(&buf[..descs_len]) {
+        Some(descs) => vct.descs.extend(descs),
+        None => return false,
+      }
+    }
+
+    let (version, current_next) = (vct.version, vct.current_next);
+    let update = self
+      .versions
+      .entry(table_id)
+      .or_insert_with(TableVersions::new)
+      .update(vct, version, current_next);
+    if let Some(update) = update {
+      ctx.events.push_back(Event::Vct {
+        new: update.new,
+        old: update.old,
+        versions: update.versions,
+      });
+    }
+
+    true
+  }
+}
+
+// Following short_name (7 UTF-16 code units):
+//
+//  2          1          0
+// 32109876 54321098 76543210
+// ....MMMM MMMMMMmm mmmmmmmm
+//
+// M: major_channel_number
+// m: minor_channel_number
+//
+// Then modulation_mode, carrier_frequency, channel_TSID, program_number and:
+//
+//  1          0
+// 54321098 76543210
+// eeahpogr rrssssss
+//
+// e: ETM_location
+// a: access_controlled
+// h: hidden
+// p: path_select
+// o: out_of_band
+// g: hide_guide
+// s: service_type
+fn parse_channel(buf: &mut &[u8], cable: bool) -> Option<VirtualChannel> {
+  if buf.len() < 32 {
+    return None;
+  }
+  let short_name: Vec<u16> = buf[..14]
+    .chunks_exact(2)
+    .map(|c| ((c[0] as u16) << 8) | c[1] as u16)
+    .take_while(|&c| c != 0)
+    .collect();
+  buf.advance(14);
+
+  let numbers = buf.get_uint(3) as u32;
+  let mut channel = VirtualChannel {
+    short_name: String::from_utf16_lossy(&short_name),
+    major_channel_number: numbers.bits(19..=10) as u16,
+    minor_channel_number: numbers.bits(9..=0) as u16,
+    modulation_mode: buf.get_u8(),
+    carrier_frequency: buf.get_u32(),
+    channel_tsid: buf.get_u16(),
+    program_number: buf.get_u16(),
+    ..Default::default()
+  };
+
+  let flags = buf.get_u16();
+  channel.etm_location = flags.bits(15..=14) as u8;
+  channel.access_controlled = flags.bit(13);
+  channel.hidden = flags.bit(12);
+  if cable {
+    channel.path_select = flags.bit(11);
+    channel.out_of_band = flags.bit(10);
+  }
+  channel.hide_guide = flags.bit(9);
+  channel.service_type = flags.bits(5..=0) as u8;
+  channel.source_id = buf.get_u16();
+
+  let descs_len = buf.get_u16().bits(9..=0) as usize;
+  if descs_len > buf.len() {
+    return None;
+  }
+  channel.descs = desc::parse_descs(&buf[..descs_len])?;
+  buf.advance(descs_len);
+
+  Some(channel)
+}
+
+impl PsiHandler for VctParser {
+  const TABLE_ID: u8 = TVCT_TABLE_ID;
+
+  fn on_psi(&mut self, ctx: &mut Context, table_id: u8, sections: &[&[u8]]) {
+    if !self.parse_psi(ctx, table_id, sections) {
+      ctx.stats.invalid_psi += 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // TVCT of transport stream 0x0456 with one channel, "KQED-HD" 9.1, carried
+  // as program 3 and with source_id 4.
+  static TVCT: &[u8] = &[
+    0x04, 0x56, 0xc5, 0x00, 0x00, // section header
+    0x00, // protocol_version
+    0x01, // num_channels_in_section
+    0x00, 0x4b, 0x00, 0x51, 0x00, 0x45, 0x00, 0x44, 0x00, 0x2d, 0x00, 0x48,
+    0x00, 0x44, // short_name
+    0xf0, 0x24, 0x01, // major_channel_number, minor_channel_number
+    0x04, // modulation_mode
+    0x00, 0x00, 0x00, 0x00, // carrier_frequency
+    0x04, 0x56, 0x00, 0x03, // channel_TSID, program_number
+    0x0d, 0xc2, // ETM_location, ..., service_type
+    0x00, 0x04, // source_id
+    0xfc, 0x00, // descriptors_length
+    0xfc, 0x00, // additional_descriptors_length
+  ];
+
+  #[test]
+  fn basic() {
+    let mut ctx = Context::new();
+    let mut parser = VctParser::new();
+    parser.on_psi(&mut ctx, TVCT_TABLE_ID, &[TVCT]);
+    parser.on_psi(&mut ctx, TVCT_TABLE_ID, &[TVCT]);
+    assert_eq!(ctx.stats.invalid_psi, 0);
+    assert_eq!(ctx.events.len(), 1);
+
+    let vct = match ctx.events[0] {
+      Event::Vct { ref new, .. } => new,
+      ref e => panic!("unexpected event {:?}", e),
+    };
+    assert!(!vct.cable);
+    assert_eq!(vct.transport_stream_id, 0x456);
+    assert_eq!(vct.version, 2);
+    assert_eq!(
+      vct.channels,
+      vec![VirtualChannel {
+        short_name: "KQED-HD".to_string(),
+        major_channel_number: 9,
+        minor_channel_number: 1,
+        modulation_mode: 4,
+        carrier_frequency: 0,
+        channel_tsid: 0x456,
+        program_number: 3,
+        etm_location: 0,
+        access_controlled: false,
+        hidden: false,
+        path_select: false,
+        out_of_band: false,
+        hide_guide: false,
+        service_type: 2,
+        source_id: 4,
+        descs: Vec::new(),
+      }]
+    );
+  }
+
+  #[test]
+  fn cvct() {
+    let mut ctx = Context::new();
+    let mut parser = VctParser::new();
+    parser.on_psi(&mut ctx, CVCT_TABLE_ID, &[TVCT]);
+
+    let vct = match ctx.events[0] {
+      Event::Vct { ref new, .. } => new,
+      ref e => panic!("unexpected event {:?}", e),
+    };
+    assert!(vct.cable);
+    assert!(vct.channels[0].path_select);
+    assert!(vct.channels[0].out_of_band);
+  }
+
+  #[test]
+  fn channel_overrun() {
+    let mut tvct = TVCT.to_vec();
+    tvct[6] = 0x02;
+
+    let mut ctx = Context::new();
+    let mut parser = VctParser::new();
+    parser.on_psi(&mut ctx, TVCT_TABLE_ID, &[&tvct]);
+    assert!(ctx.events.is_empty());
+    assert_eq!(ctx.stats.invalid_psi, 1);
+  }
+}